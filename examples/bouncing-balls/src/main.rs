@@ -4,11 +4,13 @@ use marmalade::dom::window;
 use marmalade::dom_stack;
 use marmalade::draw_scheduler;
 use marmalade::font;
+use marmalade::font::FontStack;
 use marmalade::input;
 use marmalade::input::Key;
 use marmalade::render::canvas2d::Canvas2d;
 use marmalade::render::canvas2d::DrawTarget2d;
 use marmalade::render::color;
+use marmalade::render::debug::DebugOverlay;
 use marmalade::tick_scheduler::TickScheduler;
 use std::cell::RefCell;
 use std::time::Duration;
@@ -85,7 +87,7 @@ impl Ball {
 async fn async_main() {
     let sound = audio::from_bytes(include_bytes!("resources/bounce.flac")).await;
 
-    let mut font = font::from_bytes(font::MONOGRAM);
+    let mut font = FontStack::new(font::from_bytes(font::MONOGRAM));
 
     dom_stack::set_title("Bouncing Balls");
 
@@ -102,7 +104,12 @@ async fn async_main() {
 
     let white_texture = canvas.white_texture();
 
+    let mut debug_overlay = DebugOverlay::new(Some(Key::D));
+
     draw_scheduler::set_on_draw(move || {
+        canvas.begin_frame();
+        debug_overlay.begin_frame();
+
         if input::is_key_pressed(Key::Space) {
             let win = window();
 
@@ -120,7 +127,10 @@ async fn async_main() {
 
         let mut loudest = 0f32;
 
-        for _ in 0..tick_scheduler.tick_count() {
+        let tick_count = tick_scheduler.tick_count();
+        debug_overlay.set_tick_count(tick_count);
+
+        for _ in 0..tick_count {
             for ball in &mut balls {
                 let collision_strength = ball.borrow_mut().tick();
 
@@ -168,6 +178,13 @@ async fn async_main() {
                 &canvas.white_texture(),
             );
         }
+
+        for ball in &balls {
+            let ball = ball.borrow();
+            debug_overlay.circle(ball.position, ball.radius, color::rgb(0., 1., 0.));
+        }
+
+        debug_overlay.draw(&mut canvas, &mut font);
     });
 }
 
@@ -2,6 +2,7 @@ use glam::Vec2;
 use marmalade::dom_stack;
 use marmalade::draw_scheduler;
 use marmalade::font;
+use marmalade::font::FontStack;
 use marmalade::image;
 use marmalade::input;
 use marmalade::input::Key;
@@ -28,7 +29,7 @@ async fn async_main() {
     let image_rect = canvas.create_texture(&image);
 
     // Load the default font
-    let mut font = font::from_bytes(font::MONOGRAM);
+    let mut font = FontStack::new(font::from_bytes(font::MONOGRAM));
 
     // Player position
     let mut position = Vec2::new(300., 300.);
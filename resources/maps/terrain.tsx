@@ -0,0 +1,13 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="terrain" tilewidth="16" tileheight="16" spacing="2" margin="1" tilecount="6" columns="3">
+ <image source="terrain.png" width="56" height="38"/>
+ <tile id="2" type="water">
+  <properties>
+   <property name="speed" type="float" value="0.5"/>
+  </properties>
+  <animation>
+   <frame tileid="2" duration="200"/>
+   <frame tileid="5" duration="300"/>
+  </animation>
+ </tile>
+</tileset>
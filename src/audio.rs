@@ -1,10 +1,12 @@
 use js_sys::Uint8Array;
+use std::cell::OnceCell;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{AudioBuffer, AudioContext};
+use web_sys::{AudioBuffer, AudioContext, MediaStream, MediaStreamAudioDestinationNode};
 
 thread_local! {
     static CONTEXT: AudioContext = AudioContext::new().unwrap();
+    static CAPTURE: OnceCell<MediaStreamAudioDestinationNode> = const { OnceCell::new() };
 }
 
 pub async fn from_bytes(bytes: &[u8]) -> AudioBuffer {
@@ -25,6 +27,12 @@ pub fn play(audio: &AudioBuffer, volume: f32) {
 
         gain.connect_with_audio_node(&c.destination()).unwrap();
 
+        CAPTURE.with(|capture| {
+            if let Some(capture) = capture.get() {
+                gain.connect_with_audio_node(capture).unwrap();
+            }
+        });
+
         gain.gain().set_value(volume);
 
         source.set_buffer(Some(audio));
@@ -34,3 +42,13 @@ pub fn play(audio: &AudioBuffer, volume: f32) {
         source.start().unwrap();
     });
 }
+
+/// Get a stream of the sounds played from now on, to record them
+#[must_use]
+pub fn capture_stream() -> MediaStream {
+    CAPTURE.with(|capture| {
+        capture
+            .get_or_init(|| CONTEXT.with(|c| c.create_media_stream_destination().unwrap()))
+            .stream()
+    })
+}
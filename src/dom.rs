@@ -1,10 +1,19 @@
+use js_sys::{Function, Promise};
 use std::clone::Clone;
+use wasm_bindgen::JsCast;
+use web_sys::{Blob, DedicatedWorkerGlobalScope, WorkerGlobalScope};
 
 thread_local! {
     static WINDOW: web_sys::Window = web_sys::window().unwrap();
     static DOCUMENT: web_sys::Document = WINDOW.with(web_sys::Window::document).unwrap();
-    static PERFORMANCE: web_sys::Performance = WINDOW.with(web_sys::Window::performance).unwrap();
+    static PERFORMANCE: web_sys::Performance = if is_worker() {
+        worker_scope().performance()
+    } else {
+        WINDOW.with(web_sys::Window::performance)
+    }
+    .unwrap();
     static BODY: web_sys::HtmlElement = DOCUMENT.with(web_sys::Document::body).unwrap();
+    static IS_WORKER: bool = js_sys::global().is_instance_of::<WorkerGlobalScope>();
 }
 
 #[must_use]
@@ -26,3 +35,62 @@ pub fn performance() -> web_sys::Performance {
 pub fn body() -> web_sys::HtmlElement {
     BODY.with(Clone::clone)
 }
+
+/// Check if this code runs in a web worker, where `window`, `document` and `body` aren't available
+#[must_use]
+pub fn is_worker() -> bool {
+    IS_WORKER.with(|is_worker| *is_worker)
+}
+
+/// Get the global scope of the dedicated worker running this code
+///
+/// # Panics
+///
+/// Panics if this isn't running in a dedicated worker
+#[must_use]
+pub fn worker_scope() -> DedicatedWorkerGlobalScope {
+    js_sys::global()
+        .dyn_into()
+        .expect("Error, not running in a dedicated worker")
+}
+
+/// Call the callback before the next repaint, from the main thread or a worker
+pub(crate) fn request_animation_frame(callback: &Function) {
+    if is_worker() {
+        worker_scope().request_animation_frame(callback).unwrap();
+    } else {
+        window().request_animation_frame(callback).unwrap();
+    }
+}
+
+/// Call the callback after the given delay in milliseconds, from the main thread or a worker
+pub(crate) fn set_timeout(callback: &Function, timeout: i32) {
+    if is_worker() {
+        worker_scope()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(callback, timeout)
+            .unwrap();
+    } else {
+        window()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(callback, timeout)
+            .unwrap();
+    }
+}
+
+/// Start fetching the given url, from the main thread or a worker
+pub(crate) fn fetch(url: &str) -> Promise {
+    if is_worker() {
+        worker_scope().fetch_with_str(url)
+    } else {
+        window().fetch_with_str(url)
+    }
+}
+
+/// Start decoding an image file, from the main thread or a worker
+pub(crate) fn create_image_bitmap(blob: &Blob) -> Promise {
+    if is_worker() {
+        worker_scope().create_image_bitmap_with_blob(blob)
+    } else {
+        window().create_image_bitmap_with_blob(blob)
+    }
+    .unwrap()
+}
@@ -1,4 +1,4 @@
-use crate::dom::window;
+use crate::dom::request_animation_frame;
 use std::{
     cell::{OnceCell, RefCell},
     rc::Rc,
@@ -24,27 +24,23 @@ impl DrawScheduler {
                 Closure::<dyn FnMut()>::new(move || {
                     (draw_closure_clone.borrow_mut())();
 
-                    window()
-                        .request_animation_frame(
-                            request_animation_frame_closure_clone
-                                .get()
-                                .unwrap()
-                                .unchecked_ref(),
-                        )
-                        .unwrap();
+                    request_animation_frame(
+                        request_animation_frame_closure_clone
+                            .get()
+                            .unwrap()
+                            .unchecked_ref(),
+                    );
                 })
                 .into_js_value(),
             )
             .unwrap();
 
-        window()
-            .request_animation_frame(
-                request_animation_frame_closure
-                    .get()
-                    .unwrap()
-                    .unchecked_ref(),
-            )
-            .unwrap();
+        request_animation_frame(
+            request_animation_frame_closure
+                .get()
+                .unwrap()
+                .unchecked_ref(),
+        );
 
         Self { draw_closure }
     }
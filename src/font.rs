@@ -1,8 +1,156 @@
-use meshtext::MeshGenerator;
+use crate::net::fetch_bytes;
+use glam::Vec2;
+use meshtext::{Face, MeshGenerator, MeshText, TextSection};
 
 pub const MONOGRAM: &[u8] = include_bytes!("../resources/fonts/monogram-extended.ttf");
 
+pub type Font = MeshGenerator<Face<'static>>;
+
+#[derive(Debug)]
+pub struct FontError;
+
 #[must_use]
-pub fn from_bytes(bytes: &'static [u8]) -> MeshGenerator<meshtext::Face<'static>> {
+pub fn from_bytes(bytes: &'static [u8]) -> Font {
     MeshGenerator::new(bytes)
 }
+
+/// Create a font from bytes known only at runtime.
+/// The bytes are kept alive for the rest of the program, fonts are expected to be loaded once and reused
+///
+/// # Errors
+///
+/// Returns Err if the bytes aren't a valid font
+pub fn from_owned_bytes(bytes: Vec<u8>) -> Result<Font, FontError> {
+    if Face::parse(&bytes, 0).is_err() {
+        return Err(FontError);
+    }
+
+    Ok(MeshGenerator::new(Box::leak(bytes.into_boxed_slice())))
+}
+
+/// Load a font from the network at the given src
+///
+/// # Errors
+///
+/// Returns Err if the font couldn't be fetched or isn't a valid font
+pub async fn load(src: &str) -> Result<Font, FontError> {
+    from_owned_bytes(fetch_bytes(src).await.map_err(|_| FontError)?)
+}
+
+/// A list of fonts where glyphs missing in a font are looked up in the following ones
+pub struct FontStack {
+    fonts: Vec<Font>,
+}
+
+impl FontStack {
+    #[must_use]
+    pub fn new(primary: Font) -> Self {
+        Self {
+            fonts: vec![primary],
+        }
+    }
+
+    /// Add a fallback font, it is used only for glyphs missing in all the fonts added before it
+    #[must_use]
+    pub fn with_fallback(mut self, font: Font) -> Self {
+        self.push_fallback(font);
+        self
+    }
+
+    /// Add a fallback font, it is used only for glyphs missing in all the fonts added before it
+    pub fn push_fallback(&mut self, font: Font) {
+        self.fonts.push(font);
+    }
+
+    /// Index of the first font containing the given character, the primary font is used if none contains it
+    #[must_use]
+    pub fn font_index_for(&self, c: char) -> usize {
+        self.fonts
+            .iter()
+            .position(|font| font.font().glyph_index(c).is_some())
+            .unwrap_or(0)
+    }
+
+    /// Horizontal advance of the given character for a text of height 1
+    #[must_use]
+    pub fn advance(&self, c: char) -> f32 {
+        let font = self.fonts[self.font_index_for(c)].font();
+
+        font.glyph_index(c)
+            .and_then(|id| font.glyph_hor_advance(id))
+            .unwrap_or(0) as f32
+            / font.height() as f32
+    }
+
+    /// Width of the given text for a text of height 1
+    #[must_use]
+    pub fn text_width(&self, text: &str) -> f32 {
+        text.chars().map(|c| self.advance(c)).sum()
+    }
+
+    /// Generate the flat triangle list of the given text, each character is taken from the first font containing it.
+    /// Characters that fail to be meshed are skipped, the rest of their run is still drawn
+    pub fn generate_vertices(&mut self, position: Vec2, height: f32, text: &str) -> Vec<f32> {
+        let mut runs: Vec<(usize, String)> = Vec::new();
+
+        for c in text.chars() {
+            let font_index = self.font_index_for(c);
+
+            match runs.last_mut() {
+                Some((index, run)) if *index == font_index => run.push(c),
+                _ => runs.push((font_index, c.to_string())),
+            }
+        }
+
+        let mut vertices = Vec::new();
+        let mut x = position.x;
+
+        for (index, run) in runs {
+            if let Some(mesh) = self.mesh_section(index, Vec2::new(x, position.y), height, &run) {
+                vertices.extend_from_slice(&mesh.vertices);
+            } else {
+                // Mesh the characters one by one to only skip the failing ones
+                let mut character_x = x;
+
+                for c in run.chars() {
+                    let position = Vec2::new(character_x, position.y);
+
+                    if let Some(mesh) = self.mesh_section(index, position, height, &c.to_string()) {
+                        vertices.extend_from_slice(&mesh.vertices);
+                    }
+
+                    character_x += self.advance(c) * height;
+                }
+            }
+
+            x += self.text_width(&run) * height;
+        }
+
+        vertices
+    }
+
+    fn mesh_section(
+        &mut self,
+        index: usize,
+        position: Vec2,
+        height: f32,
+        text: &str,
+    ) -> Option<MeshText> {
+        let mesh: Result<MeshText, _> = self.fonts[index].generate_section_2d(
+            text,
+            Some(&[
+                height, 0., 0., // x
+                0., height, 0., // y
+                position.x, position.y, 0., // z
+            ]),
+        ); // Ideally would use MeshTextIndexed, but it has errors with some characters
+
+        mesh.ok()
+    }
+}
+
+impl From<Font> for FontStack {
+    fn from(font: Font) -> Self {
+        Self::new(font)
+    }
+}
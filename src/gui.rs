@@ -0,0 +1,1421 @@
+use crate::{
+    font::FontStack,
+    input::{self, Button, Key},
+    render::canvas2d::{Canvas2d, DrawTarget2d, TextureRect},
+};
+use glam::{Vec2, Vec4};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    ops::RangeInclusive,
+};
+
+/// Colors and sizes used by the widgets, sizes are in world units
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub font_size: f32,
+    /// Height of buttons, sliders and other single line widgets
+    pub row_height: f32,
+    /// Space between the border of windows and their content
+    pub padding: f32,
+    /// Space between widgets
+    pub spacing: f32,
+    /// Width of widgets in rows when they have no natural width, like sliders
+    pub item_width: f32,
+    pub focus_width: f32,
+    pub scrollbar_width: f32,
+    pub text: Vec4,
+    pub window: Vec4,
+    pub title_bar: Vec4,
+    pub widget: Vec4,
+    pub widget_hovered: Vec4,
+    pub widget_active: Vec4,
+    /// Background of text fields and slider tracks
+    pub field: Vec4,
+    /// Checked boxes, slider fills and scrollbars
+    pub accent: Vec4,
+    pub focus: Vec4,
+}
+
+impl Theme {
+    #[must_use]
+    pub const fn dark() -> Self {
+        Self {
+            font_size: 16.,
+            row_height: 24.,
+            padding: 8.,
+            spacing: 4.,
+            item_width: 160.,
+            focus_width: 2.,
+            scrollbar_width: 6.,
+            text: Vec4::new(0.92, 0.92, 0.92, 1.),
+            window: Vec4::new(0.12, 0.12, 0.14, 0.94),
+            title_bar: Vec4::new(0.2, 0.2, 0.26, 1.),
+            widget: Vec4::new(0.24, 0.24, 0.3, 1.),
+            widget_hovered: Vec4::new(0.32, 0.32, 0.4, 1.),
+            widget_active: Vec4::new(0.4, 0.4, 0.52, 1.),
+            field: Vec4::new(0.07, 0.07, 0.09, 1.),
+            accent: Vec4::new(0.3, 0.55, 0.95, 1.),
+            focus: Vec4::new(0.95, 0.8, 0.3, 1.),
+        }
+    }
+
+    #[must_use]
+    pub const fn light() -> Self {
+        Self {
+            text: Vec4::new(0.08, 0.08, 0.1, 1.),
+            window: Vec4::new(0.94, 0.94, 0.95, 0.96),
+            title_bar: Vec4::new(0.78, 0.8, 0.86, 1.),
+            widget: Vec4::new(0.84, 0.85, 0.88, 1.),
+            widget_hovered: Vec4::new(0.76, 0.78, 0.84, 1.),
+            widget_active: Vec4::new(0.66, 0.7, 0.8, 1.),
+            field: Vec4::new(1., 1., 1., 1.),
+            accent: Vec4::new(0.2, 0.45, 0.9, 1.),
+            focus: Vec4::new(0.9, 0.5, 0.1, 1.),
+            ..Self::dark()
+        }
+    }
+}
+
+/// Focus movements, directions pick the closest widget on that side while next and previous follow the declaration order
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Navigation {
+    Up,
+    Down,
+    Left,
+    Right,
+    Next,
+    Previous,
+}
+
+/// Input read by the ui for one frame.
+/// It can be built from the keyboard and mouse with `from_input`, or filled from any other device like a gamepad
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UiInput {
+    /// Pointer position in world coordinates, None when there is no pointer
+    pub pointer: Option<Vec2>,
+    pub pointer_down: bool,
+    /// Scroll amount in world units, positive values scroll down
+    pub scroll: f32,
+    pub navigate: Option<Navigation>,
+    /// Press the focused widget
+    pub activate: bool,
+    /// Close the open dropdown or leave the focused text field
+    pub cancel: bool,
+    /// Characters typed in the focused text field
+    pub text: String,
+    /// Number of characters to remove at the end of the focused text field
+    pub backspace: u32,
+}
+
+impl UiInput {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the mouse and keyboard, the pointer is converted to world coordinates with the view matrix of the canvas.
+    /// Arrows and tab move the focus, enter and space activate, escape cancels.
+    /// Key presses and typed text are consumed, they are not seen anymore by the `input` functions
+    #[must_use]
+    pub fn from_input(canvas: &Canvas2d) -> Self {
+        let shift = input::is_key_down(Key::ShiftLeft) || input::is_key_down(Key::ShiftRight);
+
+        let tab = input::is_key_pressed(Key::Tab).then_some(if shift {
+            Navigation::Previous
+        } else {
+            Navigation::Next
+        });
+
+        let arrow = [
+            (Key::ArrowUp, Navigation::Up),
+            (Key::ArrowDown, Navigation::Down),
+            (Key::ArrowLeft, Navigation::Left),
+            (Key::ArrowRight, Navigation::Right),
+        ]
+        .into_iter()
+        .filter(|&(key, _)| input::is_key_pressed(key))
+        .map(|(_, navigation)| navigation)
+        .next_back();
+
+        let enter = input::is_key_pressed(Key::Enter);
+        let space = input::is_key_pressed(Key::Space);
+
+        Self {
+            pointer: Some(canvas.screen_to_world_pos(input::mouse_position().as_vec2())),
+            pointer_down: input::is_button_down(Button::Left),
+            scroll: input::wheel_scroll() as f32,
+            navigate: tab.or(arrow),
+            activate: enter || space,
+            cancel: input::is_key_pressed(Key::Escape),
+            text: input::typed_text(),
+            backspace: input::is_key_pressed(Key::Backspace).into(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Id(u64);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Rect {
+    position: Vec2,
+    size: Vec2,
+}
+
+impl Rect {
+    const fn new(position: Vec2, size: Vec2) -> Self {
+        Self { position, size }
+    }
+
+    fn max(&self) -> Vec2 {
+        self.position + self.size
+    }
+
+    fn contains(&self, point: Vec2) -> bool {
+        point.cmpge(self.position).all() && point.cmple(self.max()).all()
+    }
+
+    fn intersection(&self, other: &Self) -> Option<Self> {
+        let min = self.position.max(other.position);
+        let max = self.max().min(other.max());
+
+        (max.cmpgt(min).all()).then(|| Self::new(min, max - min))
+    }
+
+    fn center(&self) -> Vec2 {
+        self.position + self.size / 2.
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Layout {
+    left: f32,
+    width: f32,
+    /// Top of the next vertical item
+    top: f32,
+    /// Left of the next item in a row
+    x: f32,
+    horizontal: bool,
+    /// Width of items in rows, their natural width is used when None
+    column_width: Option<f32>,
+    /// Lowest point reached by the items
+    bottom: f32,
+}
+
+impl Layout {
+    const fn vertical(left: f32, top: f32, width: f32) -> Self {
+        Self {
+            left,
+            width,
+            top,
+            x: left,
+            horizontal: false,
+            column_width: None,
+            bottom: top,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Command {
+    Rect {
+        position: Vec2,
+        size: Vec2,
+        color: Vec4,
+    },
+    Text {
+        position: Vec2,
+        text: String,
+        color: Vec4,
+    },
+}
+
+/// Axis of the navigation kept by the focused widget for itself
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Horizontal,
+    Vertical,
+    Both,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pointer {
+    Up,
+    Pressed,
+    Down,
+    Released,
+}
+
+impl Pointer {
+    const fn is_down(self) -> bool {
+        matches!(self, Self::Pressed | Self::Down)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Interaction {
+    hovered: bool,
+    held: bool,
+    clicked: bool,
+}
+
+/// An immediate-mode ui, widgets are declared every frame and report how they were used.
+///
+/// Each frame, call `begin_frame`, declare windows and widgets, then `draw`.
+/// Widgets are identified by their label and the window or scope containing them,
+/// the part of a label after `##` isn't displayed so that widgets with the same text can be told apart
+pub struct Ui {
+    pub theme: Theme,
+    font: FontStack,
+    white_texture: TextureRect,
+    input: UiInput,
+    pointer: Pointer,
+    active: Option<Id>,
+    focused: Option<Id>,
+    captured_axis: Option<Axis>,
+    focus_order: Vec<(Id, Vec2)>,
+    editing_text: bool,
+    focused_rect: Option<Rect>,
+    open_dropdown: Option<Id>,
+    popup: Option<Rect>,
+    previous_popup: Option<Rect>,
+    dragged_window: Option<(Id, Vec2)>,
+    window_positions: HashMap<Id, Vec2>,
+    windows: Vec<Rect>,
+    previous_windows: Vec<Rect>,
+    scroll_offsets: HashMap<Id, f32>,
+    id_stack: Vec<u64>,
+    layouts: Vec<Layout>,
+    clips: Vec<Rect>,
+    commands: Vec<Command>,
+    popup_commands: Vec<Command>,
+}
+
+impl Ui {
+    /// Create a ui drawing its text with the given font, `white_texture` is used for the other shapes
+    #[must_use]
+    pub fn new(font: FontStack, white_texture: TextureRect) -> Self {
+        Self {
+            theme: Theme::dark(),
+            font,
+            white_texture,
+            input: UiInput::new(),
+            pointer: Pointer::Up,
+            active: None,
+            focused: None,
+            captured_axis: None,
+            focus_order: Vec::new(),
+            editing_text: false,
+            focused_rect: None,
+            open_dropdown: None,
+            popup: None,
+            previous_popup: None,
+            dragged_window: None,
+            window_positions: HashMap::new(),
+            windows: Vec::new(),
+            previous_windows: Vec::new(),
+            scroll_offsets: HashMap::new(),
+            id_stack: vec![0],
+            layouts: Vec::new(),
+            clips: Vec::new(),
+            commands: Vec::new(),
+            popup_commands: Vec::new(),
+        }
+    }
+
+    /// Start a frame, widgets declared outside of windows are laid out from the top of the given area
+    pub fn begin_frame(&mut self, input: UiInput, position: Vec2, size: Vec2) {
+        self.pointer = match (self.pointer.is_down(), input.pointer_down) {
+            (false, false) => Pointer::Up,
+            (false, true) => Pointer::Pressed,
+            (true, true) => Pointer::Down,
+            (true, false) => Pointer::Released,
+        };
+
+        if self.pointer == Pointer::Up {
+            self.active = None;
+            self.dragged_window = None;
+        }
+
+        if self.pointer == Pointer::Pressed {
+            self.focused = None;
+        }
+
+        self.input = input;
+
+        self.navigate();
+
+        if self.input.cancel && self.open_dropdown.is_none() {
+            self.focused = None;
+        }
+
+        self.focus_order.clear();
+        self.editing_text = false;
+        self.previous_windows = std::mem::take(&mut self.windows);
+        self.previous_popup = self.popup.take();
+        self.focused_rect = None;
+        self.captured_axis = None;
+
+        self.id_stack.truncate(1);
+        self.clips.clear();
+        self.commands.clear();
+        self.popup_commands.clear();
+
+        self.layouts.clear();
+        self.layouts
+            .push(Layout::vertical(position.x, position.y + size.y, size.x));
+    }
+
+    /// Check if the pointer is over a window or popup of the last frame, the game should ignore it then
+    #[must_use]
+    pub fn wants_pointer(&self) -> bool {
+        self.input.pointer.is_some_and(|pointer| {
+            self.previous_windows
+                .iter()
+                .chain(&self.previous_popup)
+                .any(|rect| rect.contains(pointer))
+        })
+    }
+
+    /// Check if a text field is being edited, the game should ignore the keyboard then
+    #[must_use]
+    pub const fn wants_keyboard(&self) -> bool {
+        self.editing_text
+    }
+
+    /// Draw the ui declared since `begin_frame`, popups are drawn last
+    pub fn draw<T: DrawTarget2d + ?Sized>(&mut self, target: &mut T) {
+        for command in self.commands.iter().chain(&self.popup_commands) {
+            match command {
+                Command::Rect {
+                    position,
+                    size,
+                    color,
+                } => target.draw_rect(*position, *size, *color, &self.white_texture),
+                Command::Text {
+                    position,
+                    text,
+                    color,
+                } => target.draw_text(
+                    *position,
+                    self.theme.font_size,
+                    text,
+                    &mut self.font,
+                    *color,
+                    &self.white_texture,
+                ),
+            }
+        }
+    }
+
+    /// Declare widgets with ids scoped by the given label, for widgets with the same labels in loops
+    pub fn scope(&mut self, label: &str, f: impl FnOnce(&mut Self)) {
+        let id = self.id(label);
+
+        self.id_stack.push(id.0);
+        f(self);
+        self.id_stack.pop();
+    }
+
+    /// A movable window, `position` and `size` are its initial bottom left corner and size
+    pub fn window(&mut self, title: &str, position: Vec2, size: Vec2, f: impl FnOnce(&mut Self)) {
+        let id = self.id(title);
+        let theme = self.theme.clone();
+
+        let mut position = *self.window_positions.entry(id).or_insert(position);
+
+        let bar = Rect::new(
+            position + Vec2::new(0., size.y - theme.row_height),
+            Vec2::new(size.x, theme.row_height),
+        );
+
+        if let Some(pointer) = self.input.pointer {
+            if (self.pointer == Pointer::Pressed) && self.pointer_hits(bar, false) {
+                self.dragged_window = Some((id, pointer - position));
+            }
+
+            if let Some((_, grab)) = self.dragged_window.filter(|(window, _)| *window == id) {
+                position = pointer - grab;
+                self.window_positions.insert(id, position);
+            }
+        }
+
+        let panel = Rect::new(position, size);
+        let bar = Rect::new(
+            position + Vec2::new(0., size.y - theme.row_height),
+            bar.size,
+        );
+
+        self.windows.push(panel);
+
+        self.push_rect(panel.position, panel.size, theme.window);
+        self.push_rect(bar.position, bar.size, theme.title_bar);
+        self.push_text_in(bar, theme.padding, display_text(title), theme.text);
+
+        self.id_stack.push(id.0);
+        self.clips
+            .push(Rect::new(position, size - Vec2::Y * theme.row_height));
+
+        let content_top = bar.position.y - theme.padding;
+        let content_width = size.x - theme.padding * 2.;
+
+        // Windows are placed freely, they don't take space in the layout they are declared in
+        let parent_layouts = std::mem::take(&mut self.layouts);
+        self.layouts.push(Layout::vertical(
+            position.x + theme.padding,
+            content_top,
+            content_width,
+        ));
+
+        f(self);
+
+        self.layouts = parent_layouts;
+        self.clips.pop();
+        self.id_stack.pop();
+    }
+
+    /// Lay the widgets declared in `f` side by side with their natural width
+    pub fn row(&mut self, f: impl FnOnce(&mut Self)) {
+        self.nested(None, f);
+    }
+
+    /// Lay the widgets declared in `f` side by side in `count` columns of equal width
+    pub fn columns(&mut self, count: u32, f: impl FnOnce(&mut Self)) {
+        let (_, _, width) = self.next_area();
+        let spacing = self.theme.spacing;
+
+        let column_width = (width - spacing * count.saturating_sub(1) as f32) / count.max(1) as f32;
+
+        self.nested(Some(column_width), f);
+    }
+
+    /// Lay the widgets declared in `f` vertically with the given space around them
+    pub fn padded(&mut self, padding: f32, f: impl FnOnce(&mut Self)) {
+        let (left, top, width) = self.next_area();
+
+        self.layouts.push(Layout::vertical(
+            left + padding,
+            top - padding,
+            width - padding * 2.,
+        ));
+
+        f(self);
+
+        let layout = self.layouts.pop().unwrap();
+
+        self.allocate(width, top - layout.bottom + padding);
+    }
+
+    /// Empty space
+    pub fn space(&mut self, height: f32) {
+        self.allocate(0., height);
+    }
+
+    pub fn label(&mut self, text: &str) {
+        let text = display_text(text);
+        let width = self.text_width(text);
+        let rect = self.allocate(width, self.theme.row_height);
+
+        self.push_text_in(rect, 0., text, self.theme.text);
+    }
+
+    /// A button, returns true when it is clicked or activated
+    pub fn button(&mut self, label: &str) -> bool {
+        let id = self.id(label);
+        let text = display_text(label);
+
+        let width = self.text_width(text) + self.theme.padding * 2.;
+        let rect = self.allocate(width, self.theme.row_height);
+
+        let interaction = self.interact(id, rect, false);
+        self.focusable(id, rect);
+
+        self.push_rect(rect.position, rect.size, self.widget_color(interaction));
+        self.push_text_centered(rect, text, self.theme.text);
+
+        interaction.clicked
+    }
+
+    /// A checkbox, returns true when its value changed
+    pub fn checkbox(&mut self, label: &str, checked: &mut bool) -> bool {
+        let id = self.id(label);
+        let text = display_text(label);
+        let theme = self.theme.clone();
+
+        let box_size = theme.row_height - theme.spacing * 2.;
+        let width = box_size + theme.spacing * 2. + self.text_width(text);
+        let rect = self.allocate(width, theme.row_height);
+
+        let interaction = self.interact(id, rect, false);
+        self.focusable(id, rect);
+
+        if interaction.clicked {
+            *checked = !*checked;
+        }
+
+        let box_position = rect.position + Vec2::splat(theme.spacing);
+
+        self.push_rect(
+            box_position,
+            Vec2::splat(box_size),
+            self.widget_color(interaction),
+        );
+
+        if *checked {
+            let inset = box_size / 4.;
+
+            self.push_rect(
+                box_position + inset,
+                Vec2::splat(box_size - inset * 2.),
+                theme.accent,
+            );
+        }
+
+        self.push_text_in(rect, box_size + theme.spacing * 2., text, theme.text);
+
+        interaction.clicked
+    }
+
+    /// A slider, it can be dragged or moved with left and right when focused. Returns true when its value changed
+    pub fn slider(&mut self, label: &str, value: &mut f32, range: RangeInclusive<f32>) -> bool {
+        let id = self.id(label);
+        let theme = self.theme.clone();
+
+        let rect = self.allocate(theme.item_width, theme.row_height);
+
+        let interaction = self.interact(id, rect, false);
+        self.focusable(id, rect);
+
+        let (min, max) = (*range.start(), *range.end());
+        let previous = *value;
+
+        if interaction.held {
+            if let Some(pointer) = self.input.pointer {
+                let factor = ((pointer.x - rect.position.x) / rect.size.x).clamp(0., 1.);
+                *value = min + (max - min) * factor;
+            }
+        }
+
+        if self.focused == Some(id) {
+            self.captured_axis = Some(Axis::Horizontal);
+
+            let step = (max - min) / 20.;
+
+            match self.input.navigate {
+                Some(Navigation::Left) => *value -= step,
+                Some(Navigation::Right) => *value += step,
+                _ => {}
+            }
+        }
+
+        *value = value.clamp(min.min(max), max.max(min));
+
+        let factor = ((*value - min) / (max - min)).clamp(0., 1.);
+        let factor = if factor.is_nan() { 0. } else { factor };
+
+        self.push_rect(rect.position, rect.size, theme.field);
+        self.push_rect(
+            rect.position,
+            Vec2::new(rect.size.x * factor, rect.size.y),
+            theme.accent,
+        );
+        self.push_text_centered(
+            rect,
+            &format!("{}: {:.2}", display_text(label), value),
+            theme.text,
+        );
+
+        (*value - previous).abs() > 0.
+    }
+
+    /// A single line text field, it is edited when focused. Returns true when its text changed
+    pub fn text_field(&mut self, label: &str, text: &mut String) -> bool {
+        let id = self.id(label);
+        let theme = self.theme.clone();
+
+        let rect = self.allocate(theme.item_width, theme.row_height);
+
+        let interaction = self.interact(id, rect, false);
+        self.focusable(id, rect);
+
+        if interaction.clicked {
+            self.focused = Some(id);
+        }
+
+        let mut changed = false;
+
+        if self.focused == Some(id) {
+            // Arrows typed while editing don't leave the field
+            self.editing_text = true;
+            self.captured_axis = Some(Axis::Both);
+
+            for _ in 0..self.input.backspace {
+                changed |= text.pop().is_some();
+            }
+
+            let typed = self.input.text.chars().filter(|c| !c.is_control());
+
+            for c in typed {
+                text.push(c);
+                changed = true;
+            }
+        }
+
+        self.push_rect(rect.position, rect.size, theme.field);
+
+        let shown = if text.is_empty() && self.focused != Some(id) {
+            display_text(label)
+        } else {
+            text.as_str()
+        };
+
+        let color = if text.is_empty() {
+            theme.text * Vec4::new(1., 1., 1., 0.5)
+        } else {
+            theme.text
+        };
+
+        self.push_text_in(rect, theme.padding, shown, color);
+
+        if self.focused == Some(id) {
+            let caret_x = theme.padding + self.text_width(text);
+
+            self.push_rect(
+                rect.position + Vec2::new(caret_x.min(rect.size.x - 2.), theme.spacing),
+                Vec2::new(2., rect.size.y - theme.spacing * 2.),
+                theme.text,
+            );
+        }
+
+        changed
+    }
+
+    /// A dropdown selecting one of the options, the options are shown under it when it is open.
+    /// Returns true when the selection changed
+    pub fn dropdown(&mut self, label: &str, selected: &mut usize, options: &[&str]) -> bool {
+        let id = self.id(label);
+        let theme = self.theme.clone();
+
+        let current = options.get(*selected).copied().unwrap_or_default();
+        let text = format!("{}: {}", display_text(label), current);
+
+        let width = self.text_width(&text) + theme.padding * 2.;
+        let rect = self.allocate(width, theme.row_height);
+
+        let interaction = self.interact(id, rect, false);
+        self.focusable(id, rect);
+
+        let previous = *selected;
+        let mut open = self.open_dropdown == Some(id);
+
+        if interaction.clicked {
+            open = !open;
+        }
+
+        if open {
+            self.captured_axis = Some(Axis::Vertical);
+
+            match self.input.navigate {
+                Some(Navigation::Up) => *selected = selected.saturating_sub(1),
+                Some(Navigation::Down) => {
+                    *selected = (*selected + 1).min(options.len().saturating_sub(1));
+                }
+                _ => {}
+            }
+
+            if self.input.cancel {
+                open = false;
+            }
+
+            let popup = Rect::new(
+                rect.position - Vec2::Y * theme.row_height * options.len() as f32,
+                Vec2::new(rect.size.x, theme.row_height * options.len() as f32),
+            );
+
+            self.popup = Some(popup);
+            self.popup_commands.push(Command::Rect {
+                position: popup.position,
+                size: popup.size,
+                color: theme.window,
+            });
+
+            let mut clicked_option = false;
+
+            for (index, option) in options.iter().enumerate() {
+                let option_rect = Rect::new(
+                    rect.position - Vec2::Y * theme.row_height * (index + 1) as f32,
+                    Vec2::new(rect.size.x, theme.row_height),
+                );
+
+                let option_id = self.id(&format!("{label}##{index}"));
+                let option_interaction = self.interact(option_id, option_rect, true);
+
+                if option_interaction.clicked {
+                    *selected = index;
+                    clicked_option = true;
+                }
+
+                let color = if index == *selected || option_interaction.hovered {
+                    self.widget_color(option_interaction)
+                } else {
+                    theme.window
+                };
+
+                self.popup_commands.push(Command::Rect {
+                    position: option_rect.position,
+                    size: option_rect.size,
+                    color,
+                });
+                self.popup_commands.push(Command::Text {
+                    position: self.text_position(option_rect, theme.padding),
+                    text: (*option).to_string(),
+                    color: theme.text,
+                });
+            }
+
+            let clicked_outside = (self.pointer == Pointer::Released) && !interaction.hovered;
+
+            if clicked_option || clicked_outside {
+                open = false;
+            }
+        }
+
+        self.open_dropdown = if open {
+            Some(id)
+        } else {
+            self.open_dropdown.filter(|&dropdown| dropdown != id)
+        };
+
+        self.push_rect(rect.position, rect.size, self.widget_color(interaction));
+        self.push_text_in(rect, theme.padding, &text, theme.text);
+
+        *selected != previous
+    }
+
+    /// A vertical area of the given height scrolled with the mouse wheel, its content is clipped
+    pub fn scroll_area(&mut self, label: &str, height: f32, f: impl FnOnce(&mut Self)) {
+        let id = self.id(label);
+        let theme = self.theme.clone();
+
+        let (_, _, width) = self.next_area();
+        let rect = self.allocate(width, height);
+
+        let mut offset = self.scroll_offsets.get(&id).copied().unwrap_or(0.);
+
+        if self.pointer_hits(rect, false) {
+            offset += self.input.scroll;
+        }
+
+        let content_width = rect.size.x - theme.scrollbar_width - theme.spacing;
+        let top = rect.max().y;
+
+        let clip = self
+            .clip()
+            .map_or(Some(rect), |clip| clip.intersection(&rect));
+        self.clips
+            .push(clip.unwrap_or_else(|| Rect::new(rect.position, Vec2::ZERO)));
+        self.id_stack.push(id.0);
+        self.layouts.push(Layout::vertical(
+            rect.position.x,
+            top + offset,
+            content_width,
+        ));
+
+        let focused_rect = self.focused_rect.take();
+
+        f(self);
+
+        let layout = self.layouts.pop().unwrap();
+        self.id_stack.pop();
+        self.clips.pop();
+
+        let content_height = top + offset - layout.bottom;
+        let max_offset = (content_height - height).max(0.);
+
+        // Keep the widget focused from the keyboard visible
+        if let Some(focused) = self.focused_rect {
+            if focused.max().y > top {
+                offset -= focused.max().y - top;
+            } else if focused.position.y < rect.position.y {
+                offset += rect.position.y - focused.position.y;
+            }
+        } else {
+            self.focused_rect = focused_rect;
+        }
+
+        offset = offset.clamp(0., max_offset);
+        self.scroll_offsets.insert(id, offset);
+
+        if max_offset > 0. {
+            let thumb_height = height * height / content_height;
+            let thumb_y = top - thumb_height - (height - thumb_height) * offset / max_offset;
+
+            self.push_rect(
+                Vec2::new(rect.max().x - theme.scrollbar_width, rect.position.y),
+                Vec2::new(theme.scrollbar_width, height),
+                theme.field,
+            );
+            self.push_rect(
+                Vec2::new(rect.max().x - theme.scrollbar_width, thumb_y),
+                Vec2::new(theme.scrollbar_width, thumb_height),
+                theme.accent,
+            );
+        }
+    }
+
+    fn id(&self, label: &str) -> Id {
+        let mut hasher = DefaultHasher::new();
+
+        self.id_stack.last().hash(&mut hasher);
+        label.hash(&mut hasher);
+
+        Id(hasher.finish())
+    }
+
+    /// Move the focus from the navigation input, using the widgets of the last frame
+    fn navigate(&mut self) {
+        let order = &self.focus_order;
+
+        if order.is_empty() {
+            return;
+        }
+
+        let current = self
+            .focused
+            .and_then(|focused| order.iter().position(|(id, _)| *id == focused));
+
+        let next = match self.input.navigate {
+            None => None,
+            Some(Navigation::Next) => Some(current.map_or(0, |index| (index + 1) % order.len())),
+            Some(Navigation::Previous) => Some(current.map_or(order.len() - 1, |index| {
+                (index + order.len() - 1) % order.len()
+            })),
+            // The focused widget uses this axis itself
+            Some(Navigation::Left | Navigation::Right)
+                if matches!(self.captured_axis, Some(Axis::Horizontal | Axis::Both)) =>
+            {
+                None
+            }
+            Some(Navigation::Up | Navigation::Down)
+                if matches!(self.captured_axis, Some(Axis::Vertical | Axis::Both)) =>
+            {
+                None
+            }
+            Some(direction) => current.map_or(Some(0), |index| {
+                closest_in_direction(order, index, direction)
+            }),
+        };
+
+        if let Some(next) = next {
+            self.focused = Some(order[next].0);
+            self.open_dropdown = None;
+        }
+    }
+
+    /// Left, top and available width for the next item
+    fn next_area(&self) -> (f32, f32, f32) {
+        let layout = self.layouts.last().unwrap();
+
+        if layout.horizontal {
+            let width = layout
+                .column_width
+                .unwrap_or(layout.left + layout.width - layout.x);
+
+            (layout.x, layout.top, width)
+        } else {
+            (layout.left, layout.top, layout.width)
+        }
+    }
+
+    fn nested(&mut self, column_width: Option<f32>, f: impl FnOnce(&mut Self)) {
+        let (left, top, width) = self.next_area();
+
+        self.layouts.push(Layout {
+            horizontal: true,
+            column_width,
+            ..Layout::vertical(left, top, width)
+        });
+
+        f(self);
+
+        let layout = self.layouts.pop().unwrap();
+
+        self.allocate(layout.x - left, top - layout.bottom);
+    }
+
+    /// Take space in the current layout for an item, its width is used in rows without columns
+    fn allocate(&mut self, natural_width: f32, height: f32) -> Rect {
+        let spacing = self.theme.spacing;
+        let layout = self.layouts.last_mut().unwrap();
+
+        let rect = if layout.horizontal {
+            let width = layout.column_width.unwrap_or(natural_width);
+            let rect = Rect::new(
+                Vec2::new(layout.x, layout.top - height),
+                Vec2::new(width, height),
+            );
+
+            layout.x += width + spacing;
+
+            rect
+        } else {
+            let rect = Rect::new(
+                Vec2::new(layout.left, layout.top - height),
+                Vec2::new(layout.width, height),
+            );
+
+            layout.top -= height + spacing;
+
+            rect
+        };
+
+        layout.bottom = layout.bottom.min(rect.position.y);
+
+        rect
+    }
+
+    fn clip(&self) -> Option<Rect> {
+        self.clips.last().copied()
+    }
+
+    fn pointer_hits(&self, rect: Rect, popup: bool) -> bool {
+        let Some(pointer) = self.input.pointer else {
+            return false;
+        };
+
+        let blocked = !popup
+            && self
+                .previous_popup
+                .is_some_and(|popup| popup.contains(pointer));
+
+        let clipped = !popup && self.clip().is_some_and(|clip| !clip.contains(pointer));
+
+        rect.contains(pointer) && !blocked && !clipped && self.dragged_window.is_none()
+    }
+
+    fn interact(&mut self, id: Id, rect: Rect, popup: bool) -> Interaction {
+        let hovered = self.pointer_hits(rect, popup);
+
+        if hovered && (self.pointer == Pointer::Pressed) {
+            self.active = Some(id);
+        }
+
+        let held = self.active == Some(id) && self.input.pointer_down;
+
+        let clicked = (self.active == Some(id) && (self.pointer == Pointer::Released) && hovered)
+            || (!popup && self.focused == Some(id) && self.input.activate);
+
+        Interaction {
+            hovered,
+            held,
+            clicked,
+        }
+    }
+
+    fn focusable(&mut self, id: Id, rect: Rect) {
+        self.focus_order.push((id, rect.center()));
+
+        if self.focused != Some(id) {
+            return;
+        }
+
+        self.focused_rect = Some(rect);
+
+        let width = self.theme.focus_width;
+        let color = self.theme.focus;
+        let Rect { position, size } = rect;
+
+        self.push_rect(position, Vec2::new(size.x, width), color);
+        self.push_rect(
+            position + Vec2::Y * (size.y - width),
+            Vec2::new(size.x, width),
+            color,
+        );
+        self.push_rect(position, Vec2::new(width, size.y), color);
+        self.push_rect(
+            position + Vec2::X * (size.x - width),
+            Vec2::new(width, size.y),
+            color,
+        );
+    }
+
+    const fn widget_color(&self, interaction: Interaction) -> Vec4 {
+        if interaction.held {
+            self.theme.widget_active
+        } else if interaction.hovered {
+            self.theme.widget_hovered
+        } else {
+            self.theme.widget
+        }
+    }
+
+    fn text_width(&self, text: &str) -> f32 {
+        self.font.text_width(text) * self.theme.font_size
+    }
+
+    /// Baseline position of a text vertically centered in the rect
+    fn text_position(&self, rect: Rect, indent: f32) -> Vec2 {
+        let font_size = self.theme.font_size;
+
+        rect.position + Vec2::new(indent, (rect.size.y - font_size) / 2. + font_size * 0.25)
+    }
+
+    fn push_rect(&mut self, position: Vec2, size: Vec2, color: Vec4) {
+        let rect = Rect::new(position, size);
+
+        let Some(rect) = self
+            .clip()
+            .map_or(Some(rect), |clip| clip.intersection(&rect))
+        else {
+            return;
+        };
+
+        self.commands.push(Command::Rect {
+            position: rect.position,
+            size: rect.size,
+            color,
+        });
+    }
+
+    fn push_text_in(&mut self, rect: Rect, indent: f32, text: &str, color: Vec4) {
+        let position = self.text_position(rect, indent);
+
+        // Text can't be cut, it is hidden unless it is vertically inside the clip rect
+        let hidden = self.clip().is_some_and(|clip| {
+            rect.position.y < clip.position.y - 1. || rect.max().y > clip.max().y + 1.
+        });
+
+        if !hidden && !text.is_empty() {
+            self.commands.push(Command::Text {
+                position,
+                text: text.to_string(),
+                color,
+            });
+        }
+    }
+
+    fn push_text_centered(&mut self, rect: Rect, text: &str, color: Vec4) {
+        let indent = ((rect.size.x - self.text_width(text)) / 2.).max(0.);
+
+        self.push_text_in(rect, indent, text, color);
+    }
+}
+
+/// Part of a label shown to the user
+fn display_text(label: &str) -> &str {
+    label.split_once("##").map_or(label, |(text, _)| text)
+}
+
+/// Index of the closest focusable widget in the given direction, favoring widgets aligned with the current one
+fn closest_in_direction(
+    order: &[(Id, Vec2)],
+    current: usize,
+    direction: Navigation,
+) -> Option<usize> {
+    let axis = match direction {
+        Navigation::Up => Vec2::Y,
+        Navigation::Down => Vec2::NEG_Y,
+        Navigation::Left => Vec2::NEG_X,
+        Navigation::Right => Vec2::X,
+        Navigation::Next | Navigation::Previous => return None,
+    };
+
+    let origin = order[current].1;
+
+    order
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (_, center))| {
+            let offset = *center - origin;
+            let along = offset.dot(axis);
+
+            (index != current && along > 0.).then(|| {
+                let across = offset.perp_dot(axis).abs();
+                (index, along + across * 2.)
+            })
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{font, render::software::SoftwareCanvas2d};
+    use glam::UVec2;
+
+    const AREA: Vec2 = Vec2::new(200., 100.);
+
+    fn ui() -> Ui {
+        Ui::new(
+            FontStack::new(font::from_bytes(font::MONOGRAM)),
+            SoftwareCanvas2d::new(UVec2::ONE).white_texture(),
+        )
+    }
+
+    fn pointer(position: Vec2, down: bool) -> UiInput {
+        UiInput {
+            pointer: Some(position),
+            pointer_down: down,
+            ..UiInput::new()
+        }
+    }
+
+    fn navigate(navigation: Navigation) -> UiInput {
+        UiInput {
+            navigate: Some(navigation),
+            ..UiInput::new()
+        }
+    }
+
+    fn frame<R>(ui: &mut Ui, input: UiInput, f: impl FnOnce(&mut Ui) -> R) -> R {
+        ui.begin_frame(input, Vec2::ZERO, AREA);
+        f(ui)
+    }
+
+    /// Three buttons stacked from the top of the area, returns which ones were clicked
+    fn buttons(ui: &mut Ui) -> [bool; 3] {
+        [ui.button("a"), ui.button("b"), ui.button("c")]
+    }
+
+    #[test]
+    fn labels_and_rects() {
+        assert_eq!(display_text("Play##menu"), "Play");
+        assert_eq!(display_text("Play"), "Play");
+        assert_eq!(display_text("##hidden"), "");
+
+        let rect = Rect::new(Vec2::ZERO, Vec2::splat(4.));
+
+        assert_eq!(
+            rect.intersection(&Rect::new(Vec2::new(2., 1.), Vec2::splat(4.))),
+            Some(Rect::new(Vec2::new(2., 1.), Vec2::new(2., 3.)))
+        );
+        // Touching rects have no area in common
+        assert_eq!(
+            rect.intersection(&Rect::new(Vec2::new(4., 0.), Vec2::ONE)),
+            None
+        );
+        assert_eq!(
+            rect.intersection(&Rect::new(Vec2::splat(-3.), Vec2::ONE)),
+            None
+        );
+    }
+
+    #[test]
+    fn layout() {
+        let mut ui = ui();
+
+        frame(&mut ui, UiInput::new(), |ui| {
+            assert_eq!(
+                ui.allocate(50., 10.),
+                Rect::new(Vec2::new(0., 90.), Vec2::new(200., 10.))
+            );
+
+            let mut columns = Vec::new();
+            ui.columns(2, |ui| {
+                columns.push(ui.allocate(10., 20.));
+                columns.push(ui.allocate(10., 10.));
+            });
+
+            assert_eq!(
+                columns,
+                [
+                    Rect::new(Vec2::new(0., 66.), Vec2::new(98., 20.)),
+                    Rect::new(Vec2::new(102., 76.), Vec2::new(98., 10.)),
+                ]
+            );
+
+            let mut row = Vec::new();
+            ui.row(|ui| {
+                row.push(ui.allocate(30., 10.));
+                row.push(ui.allocate(40., 10.));
+            });
+
+            // Items of rows keep their natural width, the next item is under the tallest one
+            assert_eq!(row[1], Rect::new(Vec2::new(34., 52.), Vec2::new(40., 10.)));
+            assert_eq!(ui.allocate(0., 10.).position, Vec2::new(0., 38.));
+
+            ui.padded(5., |ui| {
+                assert_eq!(
+                    ui.allocate(0., 10.),
+                    Rect::new(Vec2::new(5., 19.), Vec2::new(190., 10.))
+                );
+            });
+            assert_eq!(ui.allocate(0., 1.).position, Vec2::new(0., 9.));
+        });
+    }
+
+    #[test]
+    fn pointer_states() {
+        let mut ui = ui();
+        let mut states = Vec::new();
+
+        for down in [false, true, true, false, false] {
+            frame(&mut ui, pointer(Vec2::ZERO, down), |_| {});
+            states.push(ui.pointer);
+        }
+
+        assert!(
+            states
+                == [
+                    Pointer::Up,
+                    Pointer::Pressed,
+                    Pointer::Down,
+                    Pointer::Released,
+                    Pointer::Up
+                ]
+        );
+    }
+
+    #[test]
+    fn clicks() {
+        let mut ui = ui();
+        let on_b = Vec2::new(10., 60.);
+
+        // Clicks happen on release over the pressed widget
+        assert_eq!(frame(&mut ui, pointer(on_b, false), buttons), [false; 3]);
+        assert_eq!(frame(&mut ui, pointer(on_b, true), buttons), [false; 3]);
+        assert_eq!(
+            frame(&mut ui, pointer(on_b, false), buttons),
+            [false, true, false]
+        );
+
+        // Pressing a widget and releasing over another one clicks nothing
+        frame(&mut ui, pointer(on_b, true), buttons);
+        assert_eq!(
+            frame(&mut ui, pointer(Vec2::new(10., 30.), false), buttons),
+            [false; 3]
+        );
+
+        // Without a pointer there are no clicks
+        frame(&mut ui, UiInput::new(), buttons);
+        assert!(!ui.wants_pointer());
+    }
+
+    #[test]
+    fn focus_navigation() {
+        let mut ui = ui();
+        frame(&mut ui, UiInput::new(), buttons);
+
+        frame(&mut ui, navigate(Navigation::Next), buttons);
+        assert_eq!(ui.focused, Some(ui.id("a")));
+
+        frame(&mut ui, navigate(Navigation::Down), buttons);
+        assert_eq!(ui.focused, Some(ui.id("b")));
+
+        frame(&mut ui, navigate(Navigation::Previous), buttons);
+        frame(&mut ui, navigate(Navigation::Previous), buttons);
+        assert_eq!(ui.focused, Some(ui.id("c")));
+
+        // Nothing is below the last button
+        frame(&mut ui, navigate(Navigation::Down), buttons);
+        assert_eq!(ui.focused, Some(ui.id("c")));
+
+        let input = UiInput {
+            activate: true,
+            ..UiInput::new()
+        };
+        assert_eq!(frame(&mut ui, input, buttons), [false, false, true]);
+
+        let input = UiInput {
+            cancel: true,
+            ..UiInput::new()
+        };
+        frame(&mut ui, input, buttons);
+        assert_eq!(ui.focused, None);
+    }
+
+    #[test]
+    fn closest_widget_in_direction() {
+        let order = [(0., 0.), (10., 0.), (0., 10.), (10., 12.), (-5., -20.)]
+            .map(|(x, y)| (Id(0), Vec2::new(x, y)));
+
+        assert_eq!(closest_in_direction(&order, 0, Navigation::Right), Some(1));
+        assert_eq!(closest_in_direction(&order, 0, Navigation::Up), Some(2));
+        assert_eq!(closest_in_direction(&order, 0, Navigation::Down), Some(4));
+        assert_eq!(closest_in_direction(&order, 0, Navigation::Left), Some(4));
+        assert_eq!(closest_in_direction(&order, 1, Navigation::Up), Some(3));
+        assert_eq!(closest_in_direction(&order, 3, Navigation::Right), None);
+        assert_eq!(closest_in_direction(&order, 0, Navigation::Next), None);
+    }
+
+    #[test]
+    fn text_field_keeps_the_arrows() {
+        let mut ui = ui();
+        let mut text = String::from("ab");
+
+        let widgets = |ui: &mut Ui, text: &mut String| {
+            ui.text_field("name", text);
+            ui.button("ok");
+        };
+
+        let on_field = Vec2::new(10., 80.);
+        frame(&mut ui, pointer(on_field, true), |ui| {
+            widgets(ui, &mut text);
+        });
+        frame(&mut ui, pointer(on_field, false), |ui| {
+            widgets(ui, &mut text);
+        });
+        assert!(ui.wants_keyboard());
+
+        let input = UiInput {
+            navigate: Some(Navigation::Down),
+            text: "c\n".into(),
+            backspace: 1,
+            ..UiInput::new()
+        };
+        frame(&mut ui, input, |ui| widgets(ui, &mut text));
+
+        assert_eq!(text, "ac");
+        assert_eq!(ui.focused, Some(ui.id("name")));
+
+        // Tab still leaves the field
+        frame(&mut ui, navigate(Navigation::Next), |ui| {
+            widgets(ui, &mut text);
+        });
+        assert_eq!(ui.focused, Some(ui.id("ok")));
+        assert!(!ui.wants_keyboard());
+    }
+
+    #[test]
+    fn dropdown() {
+        let mut ui = ui();
+        let mut selected = 0;
+        let options = ["low", "medium", "high"];
+
+        let widget = |ui: &mut Ui, selected: &mut usize, input| {
+            frame(ui, input, |ui| ui.dropdown("quality", selected, &options))
+        };
+
+        let on_dropdown = Vec2::new(10., 80.);
+        widget(&mut ui, &mut selected, pointer(on_dropdown, true));
+        assert!(!widget(&mut ui, &mut selected, pointer(on_dropdown, false)));
+        assert!(ui.open_dropdown.is_some());
+
+        // Up and down change the selection of the open dropdown
+        assert!(widget(&mut ui, &mut selected, navigate(Navigation::Down)));
+        assert!(ui.open_dropdown.is_some());
+
+        // Clicking an option selects it and closes the dropdown
+        let on_high = Vec2::new(10., 10.);
+        widget(&mut ui, &mut selected, pointer(on_high, true));
+        assert!(ui.wants_pointer());
+        assert!(widget(&mut ui, &mut selected, pointer(on_high, false)));
+        assert!(ui.open_dropdown.is_none());
+        assert_eq!(selected, 2);
+
+        // Cancel closes it without changing the selection
+        widget(&mut ui, &mut selected, pointer(on_dropdown, true));
+        widget(&mut ui, &mut selected, pointer(on_dropdown, false));
+        assert!(ui.open_dropdown.is_some());
+
+        let input = UiInput {
+            cancel: true,
+            ..UiInput::new()
+        };
+        assert!(!widget(&mut ui, &mut selected, input));
+        assert!(ui.open_dropdown.is_none());
+        assert_eq!(selected, 2);
+    }
+}
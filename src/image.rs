@@ -1,44 +1,73 @@
-use crate::dom::window;
+use crate::dom;
 use js_sys::{Array, Uint8Array};
-use wasm_bindgen::{JsCast, __rt::IntoJsResult};
+use wasm_bindgen::{__rt::IntoJsResult, JsCast};
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{Blob, HtmlImageElement, ImageBitmap};
+use web_sys::{Blob, ImageBitmap, OffscreenCanvas, OffscreenCanvasRenderingContext2d, Response};
 
-/// Load an image from the network at the given src
+/// Load an image from the network at the given src, from the main thread or a worker
 ///
 /// # Errors
 ///
-/// Returns Err if the image couldn't be loaded from src
+/// Returns Err if the image couldn't be loaded from src or decoded
 pub async fn load(src: &str) -> Result<ImageBitmap, ()> {
-    let img = HtmlImageElement::new().unwrap();
-    img.set_src(src);
-
-    if JsFuture::from(img.decode()).await.is_ok() {
-        if let Ok(value) = JsFuture::from(
-            window()
-                .create_image_bitmap_with_html_image_element(&img)
-                .unwrap(),
-        )
+    let response = JsFuture::from(dom::fetch(src))
         .await
-        {
-            return Ok(value.dyn_into::<ImageBitmap>().unwrap());
-        }
+        .map_err(|_| ())?
+        .dyn_into::<Response>()
+        .map_err(|_| ())?;
+
+    if !response.ok() {
+        return Err(());
     }
 
-    Err(())
+    let blob = JsFuture::from(response.blob().map_err(|_| ())?)
+        .await
+        .map_err(|_| ())?
+        .dyn_into::<Blob>()
+        .map_err(|_| ())?;
+
+    decode(&blob).await
 }
 
+/// Decode the bytes of an image file
+///
+/// # Panics
+///
+/// Panics if the bytes aren't an image format supported by the browser
 pub async fn from_bytes(bytes: &[u8]) -> ImageBitmap {
     let array = Array::new();
     array.push(&Uint8Array::from(bytes).into_js_result().unwrap());
 
-    JsFuture::from(
-        window()
-            .create_image_bitmap_with_blob(&Blob::new_with_u8_array_sequence(&array).unwrap())
-            .unwrap(),
-    )
-    .await
-    .unwrap()
-    .dyn_into::<ImageBitmap>()
-    .unwrap()
+    decode(&Blob::new_with_u8_array_sequence(&array).unwrap())
+        .await
+        .expect("Error, the bytes aren't a supported image")
+}
+
+async fn decode(blob: &Blob) -> Result<ImageBitmap, ()> {
+    JsFuture::from(dom::create_image_bitmap(blob))
+        .await
+        .map_err(|_| ())?
+        .dyn_into::<ImageBitmap>()
+        .map_err(|_| ())
+}
+
+/// Read the pixels of an image as RGBA bytes, rows going from top to bottom
+#[must_use]
+pub fn pixels(image: &ImageBitmap) -> Vec<u8> {
+    let canvas = OffscreenCanvas::new(image.width(), image.height()).unwrap();
+
+    let context = canvas
+        .get_context("2d")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<OffscreenCanvasRenderingContext2d>()
+        .unwrap();
+
+    context.draw_image_with_image_bitmap(image, 0., 0.).unwrap();
+
+    context
+        .get_image_data(0., 0., image.width().into(), image.height().into())
+        .unwrap()
+        .data()
+        .0
 }
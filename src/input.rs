@@ -1,14 +1,15 @@
-use crate::dom::window;
+use crate::dom::{is_worker, window};
 use glam::IVec2;
+use serde::{Deserialize, Serialize};
 use std::{
     cell::{Cell, RefCell},
     collections::BTreeSet,
     rc::Rc,
 };
 use wasm_bindgen::{prelude::Closure, JsCast};
-use web_sys::{AddEventListenerOptions, Event, KeyboardEvent, MouseEvent, WheelEvent};
+use web_sys::{AddEventListenerOptions, Element, Event, KeyboardEvent, MouseEvent, WheelEvent};
 
-#[derive(Clone, Copy, Eq, Hash, PartialEq, PartialOrd, Ord, Debug)]
+#[derive(Clone, Copy, Eq, Hash, PartialEq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
 pub enum Key {
     Digit0,
     Digit1,
@@ -130,7 +131,7 @@ impl Key {
     }
 }
 
-#[derive(Clone, Copy, Eq, Hash, PartialEq, PartialOrd, Ord, Debug)]
+#[derive(Clone, Copy, Eq, Hash, PartialEq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
 pub enum Button {
     Left,
     Middle,
@@ -154,6 +155,27 @@ impl Button {
     }
 }
 
+/// A change of the input state, produced by DOM events or forwarded to a worker, see `worker::WorkerHost`
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum InputEvent {
+    /// Text is the printed character of the key, with the keyboard layout and modifiers applied
+    KeyDown {
+        key: Option<Key>,
+        text: Option<String>,
+    },
+    KeyUp(Key),
+    ButtonDown(Button),
+    ButtonUp(Button),
+    MouseMove(IVec2),
+    Wheel(f64),
+    /// The page lost focus, every key and button is released
+    Blur,
+}
+
+/// Number of typed characters kept until `typed_text` is called
+const MAX_TYPED_TEXT: usize = 256;
+
+#[derive(Clone)]
 struct Input {
     keys_down: Rc<RefCell<BTreeSet<Key>>>,
     keys_pressed: Rc<RefCell<BTreeSet<Key>>>,
@@ -161,145 +183,59 @@ struct Input {
     buttons_pressed: Rc<RefCell<BTreeSet<Button>>>,
     wheel_move: Rc<Cell<f64>>,
     position: Rc<Cell<IVec2>>,
+    typed_text: Rc<RefCell<String>>,
 }
 
 impl Input {
+    /// In a worker, events are received from the page instead of listened to
     #[must_use]
     fn new() -> Self {
-        let window = window();
-
-        let keys_down = Rc::new(RefCell::new(BTreeSet::new()));
-        let keys_pressed = Rc::new(RefCell::new(BTreeSet::new()));
-        let buttons_down = Rc::new(RefCell::new(BTreeSet::new()));
-        let buttons_pressed = Rc::new(RefCell::new(BTreeSet::new()));
-        let wheel_move = Rc::new(Cell::new(0.0));
-        let position = Rc::new(Cell::new(IVec2::ZERO));
-
-        window
-            .add_event_listener_with_callback_and_bool(
-                "contextmenu",
-                Closure::wrap(Box::new(move |event: MouseEvent| {
-                    event.prevent_default();
-                }) as Box<dyn Fn(MouseEvent)>)
-                .into_js_value()
-                .unchecked_ref(),
-                true,
-            )
-            .unwrap();
-
-        let buttons_down_clone = buttons_down.clone();
-        let buttons_pressed_clone = buttons_pressed.clone();
-        window
-            .add_event_listener_with_callback_and_bool(
-                "mousedown",
-                Closure::wrap(Box::new(move |event: MouseEvent| {
-                    event.prevent_default();
-                    if let Some(button) = Button::from_code(event.button()) {
-                        buttons_down_clone.borrow_mut().insert(button);
-                        buttons_pressed_clone.borrow_mut().insert(button);
-                    }
-                }) as Box<dyn Fn(MouseEvent)>)
-                .into_js_value()
-                .unchecked_ref(),
-                true,
-            )
-            .unwrap();
-
-        let buttons_down_clone = buttons_down.clone();
-        window
-            .add_event_listener_with_callback(
-                "mouseup",
-                Closure::wrap(Box::new(move |event: MouseEvent| {
-                    if let Some(button) = Button::from_code(event.button()) {
-                        buttons_down_clone.borrow_mut().remove(&button);
-                    }
-                }) as Box<dyn Fn(MouseEvent)>)
-                .into_js_value()
-                .unchecked_ref(),
-            )
-            .unwrap();
-
-        let position_clone = position.clone();
-        window
-            .add_event_listener_with_callback(
-                "mousemove",
-                Closure::wrap(Box::new(move |event: MouseEvent| {
-                    position_clone.set(IVec2::new(event.page_x(), event.page_y()));
-                }) as Box<dyn Fn(MouseEvent)>)
-                .into_js_value()
-                .unchecked_ref(),
-            )
-            .unwrap();
-
-        let wheel_move_clone = wheel_move.clone();
-
-        let wheel_event_listener_options = AddEventListenerOptions::new();
-        wheel_event_listener_options.set_passive(false);
-        window
-            .add_event_listener_with_callback_and_add_event_listener_options(
-                "wheel",
-                Closure::wrap(Box::new(move |event: WheelEvent| {
-                    wheel_move_clone.set(event.delta_y());
-                    event.prevent_default();
-                }) as Box<dyn Fn(WheelEvent)>)
-                .into_js_value()
-                .unchecked_ref(),
-                &wheel_event_listener_options,
-            )
-            .unwrap();
-
-        let keys_down_clone = keys_down.clone();
-        let keys_pressed_clone = keys_pressed.clone();
-        window
-            .add_event_listener_with_callback(
-                "keydown",
-                Closure::wrap(Box::new(move |event: KeyboardEvent| {
-                    event.prevent_default();
-                    if let Some(key) = Key::from_code(event.code().as_str()) {
-                        keys_down_clone.borrow_mut().insert(key);
-                        keys_pressed_clone.borrow_mut().insert(key);
-                    }
-                }) as Box<dyn Fn(KeyboardEvent)>)
-                .into_js_value()
-                .unchecked_ref(),
-            )
-            .unwrap();
-
-        let keys_down_clone = keys_down.clone();
-        window
-            .add_event_listener_with_callback(
-                "keyup",
-                Closure::wrap(Box::new(move |event: KeyboardEvent| {
-                    if let Some(key) = Key::from_code(event.code().as_str()) {
-                        keys_down_clone.borrow_mut().remove(&key);
-                    }
-                }) as Box<dyn Fn(KeyboardEvent)>)
-                .into_js_value()
-                .unchecked_ref(),
-            )
-            .unwrap();
-
-        let keys_down_clone = keys_down.clone();
-        let buttons_down_clone = buttons_down.clone();
-        window
-            .add_event_listener_with_callback(
-                "blur",
-                Closure::wrap(Box::new(move |_: Event| {
-                    keys_down_clone.borrow_mut().clear();
-                    buttons_down_clone.borrow_mut().clear();
-                }) as Box<dyn Fn(Event)>)
-                .into_js_value()
-                .unchecked_ref(),
-            )
-            .unwrap();
-
-        Self {
-            keys_down,
-            keys_pressed,
-            buttons_down,
-            buttons_pressed,
-            wheel_move,
-            position,
+        let input = Self {
+            keys_down: Rc::new(RefCell::new(BTreeSet::new())),
+            keys_pressed: Rc::new(RefCell::new(BTreeSet::new())),
+            buttons_down: Rc::new(RefCell::new(BTreeSet::new())),
+            buttons_pressed: Rc::new(RefCell::new(BTreeSet::new())),
+            wheel_move: Rc::new(Cell::new(0.0)),
+            position: Rc::new(Cell::new(IVec2::ZERO)),
+            typed_text: Rc::new(RefCell::new(String::new())),
+        };
+
+        if !is_worker() {
+            let input_clone = input.clone();
+            listen(move |event| input_clone.apply(event));
+        }
+
+        input
+    }
+
+    fn apply(&self, event: InputEvent) {
+        match event {
+            InputEvent::KeyDown { key, text } => {
+                if let Some(key) = key {
+                    self.keys_down.borrow_mut().insert(key);
+                    self.keys_pressed.borrow_mut().insert(key);
+                }
+
+                if let Some(text) = text {
+                    push_typed_text(&mut self.typed_text.borrow_mut(), &text);
+                }
+            }
+            InputEvent::KeyUp(key) => {
+                self.keys_down.borrow_mut().remove(&key);
+            }
+            InputEvent::ButtonDown(button) => {
+                self.buttons_down.borrow_mut().insert(button);
+                self.buttons_pressed.borrow_mut().insert(button);
+            }
+            InputEvent::ButtonUp(button) => {
+                self.buttons_down.borrow_mut().remove(&button);
+            }
+            InputEvent::MouseMove(position) => self.position.set(position),
+            InputEvent::Wheel(delta) => self.wheel_move.set(delta),
+            InputEvent::Blur => {
+                self.keys_down.borrow_mut().clear();
+                self.buttons_down.borrow_mut().clear();
+            }
         }
     }
 
@@ -332,12 +268,180 @@ impl Input {
     pub fn is_key_pressed(&self, key: Key) -> bool {
         self.keys_pressed.borrow_mut().remove(&key)
     }
+
+    #[must_use]
+    pub fn typed_text(&self) -> String {
+        self.typed_text.take()
+    }
+}
+
+/// Append to the text typed since the last `typed_text` call, keeping only the last characters
+/// so that it doesn't grow forever in games that never read it
+fn push_typed_text(typed_text: &mut String, text: &str) {
+    typed_text.push_str(text);
+
+    if let Some((index, _)) = typed_text.char_indices().rev().nth(MAX_TYPED_TEXT - 1) {
+        typed_text.drain(..index);
+    }
+}
+
+/// Check if the event is meant for a DOM form element, like the ones of an `Overlay`, instead of the game
+fn targets_form_element(event: &Event) -> bool {
+    event
+        .target()
+        .and_then(|target| target.dyn_into::<Element>().ok())
+        .is_some_and(|element| {
+            matches!(
+                element.tag_name().as_str(),
+                "INPUT" | "TEXTAREA" | "SELECT" | "BUTTON"
+            )
+        })
+}
+
+/// Listen to the input events of the window, from the main thread
+pub(crate) fn listen(callback: impl Fn(InputEvent) + 'static) {
+    let window = window();
+    let callback = Rc::new(callback);
+
+    window
+        .add_event_listener_with_callback_and_bool(
+            "contextmenu",
+            Closure::wrap(Box::new(move |event: MouseEvent| {
+                event.prevent_default();
+            }) as Box<dyn Fn(MouseEvent)>)
+            .into_js_value()
+            .unchecked_ref(),
+            true,
+        )
+        .unwrap();
+
+    let callback_clone = callback.clone();
+    window
+        .add_event_listener_with_callback_and_bool(
+            "mousedown",
+            Closure::wrap(Box::new(move |event: MouseEvent| {
+                if targets_form_element(&event) {
+                    return;
+                }
+
+                event.prevent_default();
+                if let Some(button) = Button::from_code(event.button()) {
+                    callback_clone(InputEvent::ButtonDown(button));
+                }
+            }) as Box<dyn Fn(MouseEvent)>)
+            .into_js_value()
+            .unchecked_ref(),
+            true,
+        )
+        .unwrap();
+
+    let callback_clone = callback.clone();
+    window
+        .add_event_listener_with_callback(
+            "mouseup",
+            Closure::wrap(Box::new(move |event: MouseEvent| {
+                if let Some(button) = Button::from_code(event.button()) {
+                    callback_clone(InputEvent::ButtonUp(button));
+                }
+            }) as Box<dyn Fn(MouseEvent)>)
+            .into_js_value()
+            .unchecked_ref(),
+        )
+        .unwrap();
+
+    let callback_clone = callback.clone();
+    window
+        .add_event_listener_with_callback(
+            "mousemove",
+            Closure::wrap(Box::new(move |event: MouseEvent| {
+                callback_clone(InputEvent::MouseMove(IVec2::new(
+                    event.page_x(),
+                    event.page_y(),
+                )));
+            }) as Box<dyn Fn(MouseEvent)>)
+            .into_js_value()
+            .unchecked_ref(),
+        )
+        .unwrap();
+
+    let callback_clone = callback.clone();
+
+    let wheel_event_listener_options = AddEventListenerOptions::new();
+    wheel_event_listener_options.set_passive(false);
+    window
+        .add_event_listener_with_callback_and_add_event_listener_options(
+            "wheel",
+            Closure::wrap(Box::new(move |event: WheelEvent| {
+                callback_clone(InputEvent::Wheel(event.delta_y()));
+                event.prevent_default();
+            }) as Box<dyn Fn(WheelEvent)>)
+            .into_js_value()
+            .unchecked_ref(),
+            &wheel_event_listener_options,
+        )
+        .unwrap();
+
+    let callback_clone = callback.clone();
+    window
+        .add_event_listener_with_callback(
+            "keydown",
+            Closure::wrap(Box::new(move |event: KeyboardEvent| {
+                if targets_form_element(&event) {
+                    return;
+                }
+
+                event.prevent_default();
+
+                // Named keys like "Enter" have longer values than the printed character
+                let text = Some(event.key()).filter(|key| {
+                    key.chars().count() == 1 && !event.ctrl_key() && !event.meta_key()
+                });
+
+                callback_clone(InputEvent::KeyDown {
+                    key: Key::from_code(event.code().as_str()),
+                    text,
+                });
+            }) as Box<dyn Fn(KeyboardEvent)>)
+            .into_js_value()
+            .unchecked_ref(),
+        )
+        .unwrap();
+
+    let callback_clone = callback.clone();
+    window
+        .add_event_listener_with_callback(
+            "keyup",
+            Closure::wrap(Box::new(move |event: KeyboardEvent| {
+                if let Some(key) = Key::from_code(event.code().as_str()) {
+                    callback_clone(InputEvent::KeyUp(key));
+                }
+            }) as Box<dyn Fn(KeyboardEvent)>)
+            .into_js_value()
+            .unchecked_ref(),
+        )
+        .unwrap();
+
+    window
+        .add_event_listener_with_callback(
+            "blur",
+            Closure::wrap(Box::new(move |_: Event| {
+                callback(InputEvent::Blur);
+            }) as Box<dyn Fn(Event)>)
+            .into_js_value()
+            .unchecked_ref(),
+        )
+        .unwrap();
 }
 
 thread_local! {
     pub static INPUT: Input = Input::new();
 }
 
+/// Apply an input event received from the page, in a worker
+pub(crate) fn push_event(event: InputEvent) {
+    INPUT.with(|input| input.apply(event));
+}
+
 #[must_use]
 pub fn is_key_down(key: Key) -> bool {
     INPUT.with(|input| input.is_key_down(key))
@@ -365,3 +469,32 @@ pub fn wheel_scroll() -> f64 {
 pub fn mouse_position() -> IVec2 {
     INPUT.with(Input::position)
 }
+
+/// Get the characters typed since the last call, with the keyboard layout and modifiers applied.
+/// Only the last 256 characters are kept
+pub fn typed_text() -> String {
+    INPUT.with(Input::typed_text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_text_is_capped() {
+        let mut typed_text = String::new();
+
+        push_typed_text(&mut typed_text, "ab");
+        assert_eq!(typed_text, "ab");
+
+        for _ in 0..MAX_TYPED_TEXT {
+            push_typed_text(&mut typed_text, "é");
+        }
+
+        push_typed_text(&mut typed_text, "z");
+
+        assert_eq!(typed_text.chars().count(), MAX_TYPED_TEXT);
+        assert!(typed_text.starts_with('é'));
+        assert!(typed_text.ends_with('z'));
+    }
+}
@@ -21,9 +21,15 @@ pub mod dom;
 pub mod dom_stack;
 pub mod draw_scheduler;
 pub mod font;
+pub mod gui;
 pub mod image;
 pub mod input;
+pub mod map;
 pub mod net;
+pub mod overlay;
 pub mod render;
+pub mod screen_recorder;
 pub mod tick_scheduler;
 pub mod time;
+pub mod video;
+pub mod worker;
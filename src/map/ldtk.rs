@@ -0,0 +1,996 @@
+//! Loader for projects made with the [LDtk](https://ldtk.io) editor
+//!
+//! Parsing is pure rust, only `load` requires a browser to fetch the files.
+//! `LDtk` coordinates have y going down, `to_world` converts them to the y up coordinates used for drawing
+
+use crate::{
+    map::{default_one, default_true, fetch_file, ReferencedFiles},
+    render::{
+        canvas2d::{DrawTarget2d, TextureRect},
+        tilemap::Tile,
+    },
+};
+use glam::{IVec2, UVec2, Vec2, Vec4};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Debug)]
+pub enum LdtkError {
+    Json(serde_json::Error),
+    /// The file is well formed but its content doesn't describe a valid project
+    InvalidData(String),
+    /// A file couldn't be fetched from the network
+    Fetch(String),
+}
+
+impl From<serde_json::Error> for LdtkError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+/// Convert a position in `LDtk` world pixels (y down) to world coordinates (y up)
+#[must_use]
+pub fn to_world(position: Vec2) -> Vec2 {
+    Vec2::new(position.x, -position.y)
+}
+
+/// Value of an entity or level field
+#[derive(Clone, PartialEq, Debug)]
+pub enum FieldValue {
+    Null,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    /// Color in the `#rrggbb` format
+    Color(String),
+    /// Name of the enum value
+    Enum(String),
+    FilePath(String),
+    /// Cell coordinates in the level grid
+    Point(IVec2),
+    EntityRef {
+        entity_iid: String,
+        layer_iid: String,
+        level_iid: String,
+        world_iid: String,
+    },
+    /// Rectangle in pixels inside a tileset
+    Tile {
+        tileset_uid: i64,
+        position: UVec2,
+        size: UVec2,
+    },
+    Array(Vec<Self>),
+}
+
+impl FieldValue {
+    fn parse(kind: &str, value: &Value) -> Self {
+        if value.is_null() {
+            return Self::Null;
+        }
+
+        if let Some(inner) = kind
+            .strip_prefix("Array<")
+            .and_then(|kind| kind.strip_suffix('>'))
+        {
+            return Self::Array(
+                value
+                    .as_array()
+                    .map(|values| values.iter().map(|v| Self::parse(inner, v)).collect())
+                    .unwrap_or_default(),
+            );
+        }
+
+        let string = || value.as_str().unwrap_or_default().to_string();
+        let str_member = |name: &str| value[name].as_str().unwrap_or_default().to_string();
+        let int_member = |name: &str| value[name].as_i64().unwrap_or_default();
+
+        match kind {
+            "Int" => Self::Int(value.as_i64().unwrap_or_default()),
+            "Float" => Self::Float(value.as_f64().unwrap_or_default()),
+            "Bool" => Self::Bool(value.as_bool().unwrap_or_default()),
+            "Color" => Self::Color(string()),
+            "FilePath" => Self::FilePath(string()),
+            "Point" => Self::Point(IVec2::new(int_member("cx") as i32, int_member("cy") as i32)),
+            "EntityRef" => Self::EntityRef {
+                entity_iid: str_member("entityIid"),
+                layer_iid: str_member("layerIid"),
+                level_iid: str_member("levelIid"),
+                world_iid: str_member("worldIid"),
+            },
+            "Tile" => Self::Tile {
+                tileset_uid: int_member("tilesetUid"),
+                position: UVec2::new(int_member("x") as u32, int_member("y") as u32),
+                size: UVec2::new(int_member("w") as u32, int_member("h") as u32),
+            },
+            kind if kind.starts_with("LocalEnum.") || kind.starts_with("ExternEnum.") => {
+                Self::Enum(string())
+            }
+            _ => Self::String(string()),
+        }
+    }
+}
+
+/// Fields of an entity or a level, by identifier
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Fields(pub BTreeMap<String, FieldValue>);
+
+impl Fields {
+    #[must_use]
+    pub fn get(&self, identifier: &str) -> Option<&FieldValue> {
+        self.0.get(identifier)
+    }
+
+    #[must_use]
+    pub fn get_int(&self, identifier: &str) -> Option<i64> {
+        match self.get(identifier)? {
+            FieldValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get a float field, int fields are converted
+    #[must_use]
+    pub fn get_float(&self, identifier: &str) -> Option<f64> {
+        match self.get(identifier)? {
+            FieldValue::Float(value) => Some(*value),
+            FieldValue::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn get_bool(&self, identifier: &str) -> Option<bool> {
+        match self.get(identifier)? {
+            FieldValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get a string field, color, enum and file path fields are returned as strings too
+    #[must_use]
+    pub fn get_str(&self, identifier: &str) -> Option<&str> {
+        match self.get(identifier)? {
+            FieldValue::String(value)
+            | FieldValue::Color(value)
+            | FieldValue::Enum(value)
+            | FieldValue::FilePath(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn get_point(&self, identifier: &str) -> Option<IVec2> {
+        match self.get(identifier)? {
+            FieldValue::Point(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn get_array(&self, identifier: &str) -> Option<&[FieldValue]> {
+        match self.get(identifier)? {
+            FieldValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WorldLayout {
+    Free,
+    GridVania,
+    LinearHorizontal,
+    LinearVertical,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TilesetDef {
+    pub uid: i64,
+    pub identifier: String,
+    /// Path of the tileset image relative to the project file
+    pub rel_path: Option<String>,
+    /// Size of the image in pixels
+    pub size: UVec2,
+    pub grid_size: u32,
+    pub spacing: u32,
+    pub padding: u32,
+}
+
+impl TilesetDef {
+    /// Texture rect of the given pixel rectangle of this tileset, `texture` being the whole tileset image
+    #[must_use]
+    pub fn texture_rect(&self, texture: &TextureRect, position: UVec2, size: UVec2) -> TextureRect {
+        let image_size = self.size.as_vec2();
+
+        TextureRect {
+            texture: texture.texture.clone(),
+            position: texture.position + position.as_vec2() / image_size * texture.size,
+            size: size.as_vec2() / image_size * texture.size,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TileInstance {
+    /// Position of the top left corner in pixels, relative to the layer
+    pub position: IVec2,
+    /// Position of the top left corner in pixels inside the tileset
+    pub source: UVec2,
+    pub id: u32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub alpha: f32,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct EntityInstance {
+    pub identifier: String,
+    pub iid: String,
+    /// Cell coordinates in the layer grid
+    pub grid: IVec2,
+    /// Position in pixels of the pivot point, relative to the layer
+    pub position: IVec2,
+    /// Pivot point as a fraction of the entity size, (0, 0) being the top left corner
+    pub pivot: Vec2,
+    pub size: UVec2,
+    pub tags: Vec<String>,
+    pub fields: Fields,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LayerType {
+    IntGrid,
+    Entities,
+    Tiles,
+    AutoLayer,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct LayerInstance {
+    pub identifier: String,
+    pub iid: String,
+    pub kind: LayerType,
+    pub grid_size: u32,
+    /// Size of the layer in cells
+    pub grid_dimensions: UVec2,
+    pub opacity: f32,
+    /// Total offset in pixels, including the layer definition offset
+    pub offset: IVec2,
+    pub visible: bool,
+    pub tileset_uid: Option<i64>,
+    /// Values of an int grid layer row by row from the top left corner, 0 means empty
+    pub int_grid: Vec<i32>,
+    /// Tiles placed by hand or by auto-layer rules, in drawing order
+    pub tiles: Vec<TileInstance>,
+    pub entities: Vec<EntityInstance>,
+}
+
+impl LayerInstance {
+    /// Value of the int grid at the given cell, 0 if empty or outside of the grid
+    #[must_use]
+    pub fn int_grid_value(&self, cell: IVec2) -> i32 {
+        if cell.cmplt(IVec2::ZERO).any() || cell.cmpge(self.grid_dimensions.as_ivec2()).any() {
+            return 0;
+        }
+
+        self.int_grid
+            .get((cell.y * self.grid_dimensions.x as i32 + cell.x) as usize)
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Level {
+    pub identifier: String,
+    pub iid: String,
+    pub uid: i64,
+    /// Position of the top left corner in world pixels, computed for linear world layouts
+    pub world_position: IVec2,
+    pub world_depth: i32,
+    pub size: UVec2,
+    pub background_color: String,
+    pub fields: Fields,
+    /// Layers in drawing order, from the bottom one to the top one
+    pub layers: Vec<LayerInstance>,
+    /// Path of the level file relative to the project when levels are saved separately and not resolved yet
+    pub external_path: Option<String>,
+}
+
+impl Level {
+    #[must_use]
+    pub fn layer(&self, identifier: &str) -> Option<&LayerInstance> {
+        self.layers
+            .iter()
+            .find(|layer| layer.identifier == identifier)
+    }
+
+    /// All entities of this level
+    pub fn entities(&self) -> impl Iterator<Item = &EntityInstance> {
+        self.layers.iter().flat_map(|layer| &layer.entities)
+    }
+
+    /// All entities with the given identifier, useful for spawning
+    pub fn entities_with_identifier<'a>(
+        &'a self,
+        identifier: &'a str,
+    ) -> impl Iterator<Item = &'a EntityInstance> {
+        self.entities()
+            .filter(move |entity| entity.identifier == identifier)
+    }
+
+    /// World position (y up) of the given pixel position relative to a layer of this level
+    #[must_use]
+    pub fn layer_to_world(&self, layer: &LayerInstance, position: Vec2) -> Vec2 {
+        to_world((self.world_position + layer.offset).as_vec2() + position)
+    }
+
+    /// Draw the tiles of the given layer in world coordinates, `texture` is the whole image of the layer tileset.
+    /// Tiles are faded by their alpha and the layer opacity
+    pub fn draw_layer<T: DrawTarget2d + ?Sized>(
+        &self,
+        target: &mut T,
+        layer: &LayerInstance,
+        tileset: &TilesetDef,
+        texture: &TextureRect,
+    ) {
+        let grid_size = UVec2::splat(layer.grid_size);
+        let size = grid_size.as_vec2();
+
+        for tile in &layer.tiles {
+            // Tiles are drawn from their bottom left corner
+            let position = self.layer_to_world(
+                layer,
+                (tile.position + IVec2::new(0, grid_size.y as i32)).as_vec2(),
+            );
+
+            Tile {
+                id: tile.id,
+                flip_x: tile.flip_x,
+                flip_y: tile.flip_y,
+                flip_diagonal: false,
+            }
+            .draw_with_color(
+                target,
+                position,
+                size,
+                Vec4::new(1., 1., 1., tile.alpha * layer.opacity),
+                &tileset.texture_rect(texture, tile.source, grid_size),
+            );
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct World {
+    pub identifier: String,
+    pub iid: String,
+    pub layout: WorldLayout,
+    pub levels: Vec<Level>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct LdtkProject {
+    pub tilesets: Vec<TilesetDef>,
+    /// Worlds of the project, projects without multiple worlds have a single one
+    pub worlds: Vec<World>,
+}
+
+impl LdtkProject {
+    /// Parse a project file (.ldtk), external levels are left unresolved
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if the text isn't a valid project
+    pub fn from_json(text: &str) -> Result<Self, LdtkError> {
+        let raw = serde_json::from_str::<RawProject>(text)?;
+
+        let mut worlds = raw
+            .worlds
+            .into_iter()
+            .map(RawWorld::convert)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if worlds.is_empty() {
+            worlds.push(World {
+                identifier: String::new(),
+                iid: raw.iid,
+                layout: parse_layout(raw.world_layout.as_deref()),
+                levels: raw
+                    .levels
+                    .into_iter()
+                    .map(RawLevel::convert)
+                    .collect::<Result<_, _>>()?,
+            });
+        }
+
+        for world in &mut worlds {
+            world.compute_linear_positions();
+        }
+
+        Ok(Self {
+            tilesets: raw
+                .defs
+                .tilesets
+                .into_iter()
+                .map(|tileset| TilesetDef {
+                    uid: tileset.uid,
+                    identifier: tileset.identifier,
+                    rel_path: tileset.rel_path,
+                    size: UVec2::new(tileset.px_wid, tileset.px_hei),
+                    grid_size: tileset.tile_grid_size,
+                    spacing: tileset.spacing,
+                    padding: tileset.padding,
+                })
+                .collect(),
+            worlds,
+        })
+    }
+
+    /// Replace external levels by their content, `load` is called with the path of each external level file
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if `load` fails or a level is invalid
+    pub fn resolve_levels(
+        &mut self,
+        mut load: impl FnMut(&str) -> Result<String, LdtkError>,
+    ) -> Result<(), LdtkError> {
+        for world in &mut self.worlds {
+            for level in &mut world.levels {
+                if let Some(path) = level.external_path.take() {
+                    let world_position = level.world_position;
+
+                    *level = serde_json::from_str::<RawLevel>(&load(&path)?)?.convert()?;
+                    level.world_position = world_position;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetch a project and its external levels from the network, paths of levels are relative to the project url
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if a file can't be fetched or is invalid
+    pub async fn load(url: &str) -> Result<Self, LdtkError> {
+        let text = fetch_file(url).await.map_err(LdtkError::Fetch)?;
+
+        let mut project = Self::from_json(&text)?;
+
+        let files = ReferencedFiles::fetch(
+            url,
+            project
+                .levels()
+                .filter_map(|level| level.external_path.clone()),
+        )
+        .await
+        .map_err(LdtkError::Fetch)?;
+
+        project.resolve_levels(|path| files.get(path).map_err(LdtkError::Fetch))?;
+
+        Ok(project)
+    }
+
+    /// All levels of all worlds
+    pub fn levels(&self) -> impl Iterator<Item = &Level> {
+        self.worlds.iter().flat_map(|world| &world.levels)
+    }
+
+    #[must_use]
+    pub fn level(&self, identifier: &str) -> Option<&Level> {
+        self.levels().find(|level| level.identifier == identifier)
+    }
+
+    #[must_use]
+    pub fn tileset(&self, uid: i64) -> Option<&TilesetDef> {
+        self.tilesets.iter().find(|tileset| tileset.uid == uid)
+    }
+
+    /// All entities of all levels with the level they are in
+    pub fn entities(&self) -> impl Iterator<Item = (&Level, &EntityInstance)> {
+        self.levels()
+            .flat_map(|level| level.entities().map(move |entity| (level, entity)))
+    }
+
+    /// Find an entity by its iid, for example to follow an entity reference field
+    #[must_use]
+    pub fn entity_by_iid(&self, iid: &str) -> Option<(&Level, &EntityInstance)> {
+        self.entities().find(|(_, entity)| entity.iid == iid)
+    }
+
+    /// Draw every tile layer of the given level in drawing order.
+    /// `texture` is called with the uid of each tileset used and must return the texture of its whole image, layers without texture are skipped
+    pub fn draw_level<'a, T: DrawTarget2d + ?Sized>(
+        &self,
+        target: &mut T,
+        level: &Level,
+        mut texture: impl FnMut(i64) -> Option<&'a TextureRect>,
+    ) {
+        for layer in &level.layers {
+            if !layer.visible || layer.tiles.is_empty() {
+                continue;
+            }
+
+            let Some(uid) = layer.tileset_uid else {
+                continue;
+            };
+
+            if let (Some(tileset), Some(texture)) = (self.tileset(uid), texture(uid)) {
+                level.draw_layer(target, layer, tileset, texture);
+            }
+        }
+    }
+}
+
+impl World {
+    /// Linear layouts don't store level positions, levels are placed one after another
+    fn compute_linear_positions(&mut self) {
+        let mut offset = 0;
+
+        for level in &mut self.levels {
+            match self.layout {
+                WorldLayout::LinearHorizontal => {
+                    level.world_position = IVec2::new(offset, 0);
+                    offset += level.size.x as i32;
+                }
+                WorldLayout::LinearVertical => {
+                    level.world_position = IVec2::new(0, offset);
+                    offset += level.size.y as i32;
+                }
+                WorldLayout::Free | WorldLayout::GridVania => {}
+            }
+        }
+    }
+}
+
+fn parse_layout(layout: Option<&str>) -> WorldLayout {
+    match layout {
+        Some("GridVania") => WorldLayout::GridVania,
+        Some("LinearHorizontal") => WorldLayout::LinearHorizontal,
+        Some("LinearVertical") => WorldLayout::LinearVertical,
+        _ => WorldLayout::Free,
+    }
+}
+
+#[derive(Deserialize)]
+struct RawField {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(rename = "__type")]
+    kind: String,
+    #[serde(rename = "__value", default)]
+    value: Value,
+}
+
+fn convert_fields(raw: Vec<RawField>) -> Fields {
+    Fields(
+        raw.into_iter()
+            .map(|field| {
+                let value = FieldValue::parse(&field.kind, &field.value);
+                (field.identifier, value)
+            })
+            .collect(),
+    )
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawProject {
+    #[serde(default)]
+    iid: String,
+    world_layout: Option<String>,
+    defs: RawDefs,
+    #[serde(default)]
+    levels: Vec<RawLevel>,
+    #[serde(default)]
+    worlds: Vec<RawWorld>,
+}
+
+#[derive(Deserialize)]
+struct RawDefs {
+    #[serde(default)]
+    tilesets: Vec<RawTilesetDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawTilesetDef {
+    uid: i64,
+    #[serde(default)]
+    identifier: String,
+    rel_path: Option<String>,
+    px_wid: u32,
+    px_hei: u32,
+    tile_grid_size: u32,
+    #[serde(default)]
+    spacing: u32,
+    #[serde(default)]
+    padding: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawWorld {
+    #[serde(default)]
+    identifier: String,
+    #[serde(default)]
+    iid: String,
+    world_layout: Option<String>,
+    #[serde(default)]
+    levels: Vec<RawLevel>,
+}
+
+impl RawWorld {
+    fn convert(self) -> Result<World, LdtkError> {
+        Ok(World {
+            identifier: self.identifier,
+            iid: self.iid,
+            layout: parse_layout(self.world_layout.as_deref()),
+            levels: self
+                .levels
+                .into_iter()
+                .map(RawLevel::convert)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawLevel {
+    identifier: String,
+    #[serde(default)]
+    iid: String,
+    #[serde(default)]
+    uid: i64,
+    #[serde(default)]
+    world_x: i32,
+    #[serde(default)]
+    world_y: i32,
+    #[serde(default)]
+    world_depth: i32,
+    px_wid: u32,
+    px_hei: u32,
+    #[serde(rename = "__bgColor", default)]
+    bg_color: String,
+    #[serde(default)]
+    field_instances: Vec<RawField>,
+    layer_instances: Option<Vec<RawLayer>>,
+    external_rel_path: Option<String>,
+}
+
+impl RawLevel {
+    fn convert(self) -> Result<Level, LdtkError> {
+        let external_path = if self.layer_instances.is_none() {
+            Some(self.external_rel_path.ok_or_else(|| {
+                LdtkError::InvalidData(format!(
+                    "Level {} has neither layers nor external file",
+                    self.identifier
+                ))
+            })?)
+        } else {
+            None
+        };
+
+        Ok(Level {
+            identifier: self.identifier,
+            iid: self.iid,
+            uid: self.uid,
+            world_position: IVec2::new(self.world_x, self.world_y),
+            world_depth: self.world_depth,
+            size: UVec2::new(self.px_wid, self.px_hei),
+            background_color: self.bg_color,
+            fields: convert_fields(self.field_instances),
+            // LDtk lists layers from the top one to the bottom one
+            layers: self
+                .layer_instances
+                .unwrap_or_default()
+                .into_iter()
+                .rev()
+                .map(RawLayer::convert)
+                .collect::<Result<_, _>>()?,
+            external_path,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct RawTile {
+    px: [i32; 2],
+    src: [u32; 2],
+    #[serde(default)]
+    f: u8,
+    #[serde(default)]
+    t: u32,
+    #[serde(default = "default_one")]
+    a: f32,
+}
+
+impl RawTile {
+    const fn convert(self) -> TileInstance {
+        TileInstance {
+            position: IVec2::from_array(self.px),
+            source: UVec2::from_array(self.src),
+            id: self.t,
+            flip_x: self.f & 1 != 0,
+            flip_y: self.f & 2 != 0,
+            alpha: self.a,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawEntity {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(default)]
+    iid: String,
+    #[serde(rename = "__grid", default)]
+    grid: [i32; 2],
+    #[serde(rename = "__pivot", default)]
+    pivot: [f32; 2],
+    #[serde(rename = "__tags", default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+    px: [i32; 2],
+    #[serde(default)]
+    field_instances: Vec<RawField>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawLayer {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(default)]
+    iid: String,
+    #[serde(rename = "__type")]
+    kind: String,
+    #[serde(rename = "__cWid")]
+    c_wid: u32,
+    #[serde(rename = "__cHei")]
+    c_hei: u32,
+    #[serde(rename = "__gridSize")]
+    grid_size: u32,
+    #[serde(rename = "__opacity", default = "default_one")]
+    opacity: f32,
+    #[serde(rename = "__pxTotalOffsetX", default)]
+    px_total_offset_x: i32,
+    #[serde(rename = "__pxTotalOffsetY", default)]
+    px_total_offset_y: i32,
+    #[serde(rename = "__tilesetDefUid")]
+    tileset_def_uid: Option<i64>,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(default)]
+    int_grid_csv: Vec<i32>,
+    #[serde(default)]
+    auto_layer_tiles: Vec<RawTile>,
+    #[serde(default)]
+    grid_tiles: Vec<RawTile>,
+    #[serde(default)]
+    entity_instances: Vec<RawEntity>,
+}
+
+impl RawLayer {
+    fn convert(self) -> Result<LayerInstance, LdtkError> {
+        let kind = match self.kind.as_str() {
+            "IntGrid" => LayerType::IntGrid,
+            "Entities" => LayerType::Entities,
+            "Tiles" => LayerType::Tiles,
+            "AutoLayer" => LayerType::AutoLayer,
+            other => {
+                return Err(LdtkError::InvalidData(format!(
+                    "Unknown layer type {other}"
+                )));
+            }
+        };
+
+        Ok(LayerInstance {
+            identifier: self.identifier,
+            iid: self.iid,
+            kind,
+            grid_size: self.grid_size,
+            grid_dimensions: UVec2::new(self.c_wid, self.c_hei),
+            opacity: self.opacity,
+            offset: IVec2::new(self.px_total_offset_x, self.px_total_offset_y),
+            visible: self.visible,
+            tileset_uid: self.tileset_def_uid,
+            int_grid: self.int_grid_csv,
+            tiles: self
+                .auto_layer_tiles
+                .into_iter()
+                .chain(self.grid_tiles)
+                .map(RawTile::convert)
+                .collect(),
+            entities: self
+                .entity_instances
+                .into_iter()
+                .map(|entity| EntityInstance {
+                    identifier: entity.identifier,
+                    iid: entity.iid,
+                    grid: IVec2::from_array(entity.grid),
+                    position: IVec2::from_array(entity.px),
+                    pivot: Vec2::from_array(entity.pivot),
+                    size: UVec2::new(entity.width, entity.height),
+                    tags: entity.tags,
+                    fields: convert_fields(entity.field_instances),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::software::SoftwareCanvas2d;
+    use glam::Mat3;
+
+    const CAVE: &str = include_str!("../../resources/maps/project/Cave.ldtkl");
+
+    fn project() -> LdtkProject {
+        let mut project =
+            LdtkProject::from_json(include_str!("../../resources/maps/project.ldtk")).unwrap();
+
+        project
+            .resolve_levels(|path| {
+                assert_eq!(path, "project/Cave.ldtkl");
+                Ok(CAVE.into())
+            })
+            .unwrap();
+
+        project
+    }
+
+    #[test]
+    fn external_levels() {
+        let mut project =
+            LdtkProject::from_json(include_str!("../../resources/maps/project.ldtk")).unwrap();
+
+        let cave = project.level("Cave").unwrap();
+        assert_eq!(cave.external_path.as_deref(), Some("project/Cave.ldtkl"));
+        assert!(cave.layers.is_empty());
+
+        project.resolve_levels(|_| Ok(CAVE.into())).unwrap();
+
+        let cave = project.level("Cave").unwrap();
+        assert_eq!(cave.external_path, None);
+        assert_eq!(
+            cave.layer("Collisions")
+                .map(|layer| layer.int_grid_value(IVec2::new(3, 1))),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn linear_layout_positions() {
+        let project = project();
+
+        assert_eq!(project.worlds.len(), 1);
+        assert_eq!(project.worlds[0].layout, WorldLayout::LinearHorizontal);
+        assert_eq!(project.level("Start").unwrap().world_position, IVec2::ZERO);
+        assert_eq!(
+            project.level("Cave").unwrap().world_position,
+            IVec2::new(16, 0)
+        );
+    }
+
+    #[test]
+    fn layers() {
+        let project = project();
+        let start = project.level("Start").unwrap();
+
+        // Layers are listed from the bottom one
+        let identifiers = start
+            .layers
+            .iter()
+            .map(|layer| layer.identifier.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(identifiers, ["Collisions", "Ground", "Entities"]);
+
+        let collisions = start.layer("Collisions").unwrap();
+        assert_eq!(collisions.kind, LayerType::IntGrid);
+        assert!(!collisions.visible);
+        assert_eq!(collisions.int_grid_value(IVec2::new(0, 0)), 1);
+        assert_eq!(collisions.int_grid_value(IVec2::new(1, 1)), 2);
+        assert_eq!(collisions.int_grid_value(IVec2::new(2, 0)), 0);
+        assert_eq!(collisions.int_grid_value(IVec2::new(-1, 0)), 0);
+
+        let ground = start.layer("Ground").unwrap();
+        assert!((ground.opacity - 0.5).abs() < f32::EPSILON);
+        assert_eq!(ground.tileset_uid, Some(1));
+        assert_eq!(
+            ground.tiles[1],
+            TileInstance {
+                position: IVec2::new(8, 0),
+                source: UVec2::new(8, 0),
+                id: 1,
+                flip_x: true,
+                flip_y: false,
+                alpha: 0.5,
+            }
+        );
+
+        assert_eq!(start.fields.get_str("music"), Some("music/start.ogg"));
+    }
+
+    #[test]
+    fn entity_fields() {
+        let project = project();
+        let (level, player) = project.entities().next().unwrap();
+
+        assert_eq!(level.identifier, "Start");
+        assert_eq!(player.identifier, "Player");
+        assert_eq!(player.grid, IVec2::new(0, 1));
+        assert_eq!(player.position, IVec2::new(4, 16));
+        assert_eq!(player.pivot, Vec2::new(0.5, 1.));
+        assert_eq!(player.tags, ["hero"]);
+
+        let fields = &player.fields;
+        assert_eq!(fields.get_int("health"), Some(3));
+        assert_eq!(fields.get_float("speed"), Some(1.5));
+        assert_eq!(fields.get_str("title"), Some("hero"));
+        assert_eq!(
+            fields.get("tint"),
+            Some(&FieldValue::Color("#ff8000".into()))
+        );
+        assert_eq!(fields.get("kind"), Some(&FieldValue::Enum("Knight".into())));
+        assert_eq!(fields.get_point("spawn"), Some(IVec2::new(1, 0)));
+        assert_eq!(
+            fields.get_array("inventory"),
+            Some([FieldValue::Int(1), FieldValue::Int(2), FieldValue::Int(3)].as_slice())
+        );
+        assert_eq!(
+            fields.get("icon"),
+            Some(&FieldValue::Tile {
+                tileset_uid: 1,
+                position: UVec2::new(8, 0),
+                size: UVec2::splat(8),
+            })
+        );
+        assert_eq!(fields.get("missing"), Some(&FieldValue::Null));
+
+        let Some(FieldValue::EntityRef { entity_iid, .. }) = fields.get("chest") else {
+            panic!("Error, chest isn't an entity reference");
+        };
+
+        let (_, chest) = project.entity_by_iid(entity_iid).unwrap();
+        assert_eq!(chest.identifier, "Chest");
+        assert_eq!(
+            level.layer_to_world(level.layer("Entities").unwrap(), chest.position.as_vec2()),
+            Vec2::new(8., 0.)
+        );
+    }
+
+    #[test]
+    fn draw_layer_alpha() {
+        let project = project();
+        let start = project.level("Start").unwrap();
+
+        let mut canvas = SoftwareCanvas2d::new(UVec2::splat(16));
+        let texture = canvas.create_texture(16, 16, &[255; 16 * 16 * 4]);
+
+        // The level goes down from the world origin
+        canvas.pixel_perfect_view();
+        canvas.set_view_matrix(canvas.view_matrix() * Mat3::from_translation(Vec2::new(0., 16.)));
+
+        project.draw_level(&mut canvas, start, |uid| (uid == 1).then_some(&texture));
+
+        // Tile alpha multiplied by the layer opacity
+        assert_eq!(canvas.pixel(4, 4), [128; 4]);
+        assert_eq!(canvas.pixel(12, 4), [64; 4]);
+        assert_eq!(canvas.pixel(4, 12), [0; 4]);
+    }
+}
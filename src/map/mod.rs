@@ -0,0 +1,43 @@
+pub mod ldtk;
+pub mod tiled;
+
+use crate::net::fetch_text;
+use std::collections::BTreeMap;
+
+const fn default_one() -> f32 {
+    1.
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+/// Fetch a text file, the error is the url of the file
+async fn fetch_file(url: &str) -> Result<String, String> {
+    fetch_text(url).await.map_err(|_| url.into())
+}
+
+/// Files referenced by a map file, like external tilesets or levels
+struct ReferencedFiles(BTreeMap<String, String>);
+
+impl ReferencedFiles {
+    /// Fetch the files at the given paths, relative to the url of the map file.
+    /// The error is the url of the file that couldn't be fetched
+    async fn fetch(url: &str, paths: impl Iterator<Item = String>) -> Result<Self, String> {
+        let base = url.rfind('/').map_or("", |index| &url[..=index]);
+
+        let mut files = BTreeMap::new();
+
+        for path in paths {
+            let text = fetch_file(&format!("{base}{path}")).await?;
+            files.insert(path, text);
+        }
+
+        Ok(Self(files))
+    }
+
+    /// Text of the file at the given path, the error is the path if it wasn't fetched
+    fn get(&self, path: &str) -> Result<String, String> {
+        self.0.get(path).cloned().ok_or_else(|| path.into())
+    }
+}
@@ -0,0 +1,1590 @@
+//! Loader for maps made with the [Tiled](https://www.mapeditor.org) editor, in both JSON (.tmj) and XML (.tmx) formats
+//!
+//! Parsing is pure rust, only `load` requires a browser to fetch the files.
+//! Tiled coordinates have y going down, `TiledMap::to_world` converts them to the y up coordinates used by `Tilemap`
+
+use crate::{
+    map::{default_one, default_true, fetch_file, ReferencedFiles},
+    render::{
+        canvas2d::TextureRect,
+        tilemap::{Tile, TileAnimation, Tilemap, Tileset as GridTileset},
+    },
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use glam::Vec2;
+use roxmltree::{Document, Node};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::BTreeMap, path::Path, str::FromStr, time::Duration};
+
+#[derive(Debug)]
+pub enum TiledError {
+    Json(serde_json::Error),
+    Xml(roxmltree::Error),
+    /// The file is well formed but its content doesn't describe a valid map
+    InvalidData(String),
+    /// The map uses a feature this loader doesn't handle, like zstd compression
+    Unsupported(String),
+    /// A file couldn't be fetched from the network
+    Fetch(String),
+}
+
+impl From<serde_json::Error> for TiledError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+impl From<roxmltree::Error> for TiledError {
+    fn from(error: roxmltree::Error) -> Self {
+        Self::Xml(error)
+    }
+}
+
+/// A global tile id, it identifies a tile across all tilesets of a map and carries flip flags
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Gid(pub u32);
+
+impl Gid {
+    const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+    const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+    const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+    const ROTATED_HEXAGONAL_120: u32 = 0x1000_0000;
+
+    /// Id without the flip flags, 0 means no tile
+    #[must_use]
+    pub const fn id(self) -> u32 {
+        self.0
+            & !(Self::FLIPPED_HORIZONTALLY
+                | Self::FLIPPED_VERTICALLY
+                | Self::FLIPPED_DIAGONALLY
+                | Self::ROTATED_HEXAGONAL_120)
+    }
+
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.id() == 0
+    }
+
+    #[must_use]
+    pub const fn flipped_horizontally(self) -> bool {
+        self.0 & Self::FLIPPED_HORIZONTALLY != 0
+    }
+
+    #[must_use]
+    pub const fn flipped_vertically(self) -> bool {
+        self.0 & Self::FLIPPED_VERTICALLY != 0
+    }
+
+    #[must_use]
+    pub const fn flipped_diagonally(self) -> bool {
+        self.0 & Self::FLIPPED_DIAGONALLY != 0
+    }
+}
+
+/// Value of a custom property
+#[derive(Clone, PartialEq, Debug)]
+pub enum PropertyValue {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    /// Color in the `#AARRGGBB` format used by Tiled
+    Color(String),
+    File(String),
+    /// Id of an object of the map
+    Object(u32),
+    Class(Properties),
+}
+
+/// Custom properties attached to a map, layer, tile or object
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Properties(pub BTreeMap<String, PropertyValue>);
+
+impl Properties {
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&PropertyValue> {
+        self.0.get(name)
+    }
+
+    /// Get a string property, file and color properties are returned as strings too
+    #[must_use]
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            PropertyValue::String(value)
+            | PropertyValue::File(value)
+            | PropertyValue::Color(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn get_int(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            PropertyValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get a float property, int properties are converted
+    #[must_use]
+    pub fn get_float(&self, name: &str) -> Option<f64> {
+        match self.get(name)? {
+            PropertyValue::Float(value) => Some(*value),
+            PropertyValue::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            PropertyValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn get_object(&self, name: &str) -> Option<u32> {
+        match self.get(name)? {
+            PropertyValue::Object(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn get_class(&self, name: &str) -> Option<&Self> {
+        match self.get(name)? {
+            PropertyValue::Class(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// A frame of an animated tile
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Frame {
+    /// Id of the displayed tile, local to the tileset
+    pub tile_id: u32,
+    pub duration: Duration,
+}
+
+/// Extra data attached to a tile of a tileset
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct TileData {
+    pub class: String,
+    pub properties: Properties,
+    pub animation: Vec<Frame>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Tileset {
+    /// Gid of the first tile of this tileset in the map
+    pub first_gid: u32,
+    /// Path of the external tileset file, `None` once the tileset is embedded or resolved
+    pub source: Option<String>,
+    pub name: String,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tile_count: u32,
+    pub columns: u32,
+    pub spacing: u32,
+    pub margin: u32,
+    pub image: Option<String>,
+    pub image_width: u32,
+    pub image_height: u32,
+    /// Data of the tiles having properties, a class or an animation, by local id
+    pub tiles: BTreeMap<u32, TileData>,
+}
+
+impl Tileset {
+    /// Parse a standalone JSON tileset (.tsj)
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if the text isn't a valid tileset
+    pub fn from_tsj(text: &str, first_gid: u32) -> Result<Self, TiledError> {
+        let mut tileset = serde_json::from_str::<RawTileset>(text)?.convert();
+        tileset.first_gid = first_gid;
+        Ok(tileset)
+    }
+
+    /// Parse a standalone XML tileset (.tsx)
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if the text isn't a valid tileset
+    pub fn from_tsx(text: &str, first_gid: u32) -> Result<Self, TiledError> {
+        let document = Document::parse(text)?;
+
+        let mut tileset = parse_tmx_tileset(document.root_element())?;
+        tileset.first_gid = first_gid;
+        Ok(tileset)
+    }
+
+    /// Check if the given gid belongs to this tileset
+    #[must_use]
+    pub const fn contains(&self, gid: Gid) -> bool {
+        gid.id() >= self.first_gid && gid.id() - self.first_gid < self.tile_count
+    }
+
+    /// Create a grid tileset for a `Tilemap` from the texture of this tileset image, with its margin and spacing
+    #[must_use]
+    pub fn grid_tileset(&self, texture: TextureRect) -> GridTileset {
+        let columns = self.columns.max(1);
+        let rows = self.tile_count.div_ceil(columns).max(1);
+
+        if self.image_width == 0 || self.image_height == 0 {
+            return GridTileset::new(texture, columns, rows);
+        }
+
+        let image_size = Vec2::new(self.image_width as f32, self.image_height as f32);
+        let tile_size = Vec2::new(self.tile_width as f32, self.tile_height as f32);
+        let grid = Vec2::new(columns as f32, rows as f32);
+
+        // The image can be larger than the tiles, only the used part is kept
+        let used_size =
+            2. * self.margin as f32 + grid * tile_size + (grid - 1.) * self.spacing as f32;
+
+        let texture = TextureRect {
+            size: texture.size * used_size / image_size,
+            ..texture
+        };
+
+        GridTileset::with_spacing(
+            texture,
+            columns,
+            rows,
+            self.margin as f32 / used_size,
+            self.spacing as f32 / used_size,
+        )
+    }
+}
+
+/// A rectangle of tiles, finite maps have a single chunk per layer
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct TileChunk {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Tiles row by row, from the top left corner
+    pub tiles: Vec<Gid>,
+}
+
+impl TileChunk {
+    /// Chunks with tiles need a width to place them in rows
+    fn new(x: i32, y: i32, width: u32, height: u32, tiles: Vec<Gid>) -> Result<Self, TiledError> {
+        if width == 0 && !tiles.is_empty() {
+            return Err(TiledError::InvalidData(
+                "Tile layer with data but no width".into(),
+            ));
+        }
+
+        Ok(Self {
+            x,
+            y,
+            width,
+            height,
+            tiles,
+        })
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    /// Points relative to the object position
+    Polygon(Vec<Vec2>),
+    /// Points relative to the object position
+    Polyline(Vec<Vec2>),
+    Text(String),
+    /// A tile object, its gid is stored in the object
+    Tile,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Object {
+    pub id: u32,
+    pub name: String,
+    pub class: String,
+    /// Position in pixels with y going down, this is the bottom left corner for tile objects and the top left corner otherwise
+    pub position: Vec2,
+    pub size: Vec2,
+    /// Rotation in degrees, clockwise
+    pub rotation: f32,
+    pub visible: bool,
+    pub gid: Option<Gid>,
+    pub shape: ObjectShape,
+    pub properties: Properties,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum LayerKind {
+    Tiles(Vec<TileChunk>),
+    Objects(Vec<Object>),
+    Group(Vec<Layer>),
+    Image(Option<String>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Layer {
+    pub id: u32,
+    pub name: String,
+    pub class: String,
+    pub visible: bool,
+    pub opacity: f32,
+    /// Offset in pixels with y going down
+    pub offset: Vec2,
+    pub properties: Properties,
+    pub kind: LayerKind,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TiledMap {
+    /// Width in tiles
+    pub width: u32,
+    /// Height in tiles
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub infinite: bool,
+    pub tilesets: Vec<Tileset>,
+    pub layers: Vec<Layer>,
+    pub properties: Properties,
+}
+
+impl TiledMap {
+    /// Parse a JSON map (.tmj), external tilesets are left unresolved
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if the text isn't a valid map
+    pub fn from_tmj(text: &str) -> Result<Self, TiledError> {
+        serde_json::from_str::<RawMap>(text)?.convert()
+    }
+
+    /// Parse an XML map (.tmx), external tilesets are left unresolved
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if the text isn't a valid map
+    pub fn from_tmx(text: &str) -> Result<Self, TiledError> {
+        let document = Document::parse(text)?;
+        let map = document.root_element();
+
+        if !map.has_tag_name("map") {
+            return Err(TiledError::InvalidData("Root element isn't a map".into()));
+        }
+
+        Ok(Self {
+            width: required_attribute(map, "width")?,
+            height: required_attribute(map, "height")?,
+            tile_width: required_attribute(map, "tilewidth")?,
+            tile_height: required_attribute(map, "tileheight")?,
+            infinite: attribute(map, "infinite").unwrap_or(0) != 0,
+            tilesets: map
+                .children()
+                .filter(|node| node.has_tag_name("tileset"))
+                .map(parse_tmx_tileset)
+                .collect::<Result<_, _>>()?,
+            layers: parse_tmx_layers(map)?,
+            properties: parse_tmx_properties(map)?,
+        })
+    }
+
+    /// Replace external tilesets by their content, `load` is called with the source path of each external tileset.
+    /// Files ending with .tsx are parsed as XML, others as JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if `load` fails or a tileset is invalid
+    pub fn resolve_tilesets(
+        &mut self,
+        mut load: impl FnMut(&str) -> Result<String, TiledError>,
+    ) -> Result<(), TiledError> {
+        for tileset in &mut self.tilesets {
+            if let Some(source) = tileset.source.take() {
+                let text = load(&source)?;
+
+                *tileset = if has_extension(&source, "tsx") {
+                    Tileset::from_tsx(&text, tileset.first_gid)?
+                } else {
+                    Tileset::from_tsj(&text, tileset.first_gid)?
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetch a map and its external tilesets from the network, paths of tilesets are relative to the map url.
+    /// Urls ending with .tmx are parsed as XML, others as JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if a file can't be fetched or is invalid
+    pub async fn load(url: &str) -> Result<Self, TiledError> {
+        let text = fetch_file(url).await.map_err(TiledError::Fetch)?;
+
+        let mut map = if has_extension(url, "tmx") {
+            Self::from_tmx(&text)?
+        } else {
+            Self::from_tmj(&text)?
+        };
+
+        let files = ReferencedFiles::fetch(
+            url,
+            map.tilesets
+                .iter()
+                .filter_map(|tileset| tileset.source.clone()),
+        )
+        .await
+        .map_err(TiledError::Fetch)?;
+
+        map.resolve_tilesets(|source| files.get(source).map_err(TiledError::Fetch))?;
+
+        Ok(map)
+    }
+
+    /// All layers in drawing order, group layers are replaced by their content
+    #[must_use]
+    pub fn flattened_layers(&self) -> Vec<&Layer> {
+        fn flatten<'a>(layers: &'a [Layer], result: &mut Vec<&'a Layer>) {
+            for layer in layers {
+                if let LayerKind::Group(children) = &layer.kind {
+                    flatten(children, result);
+                } else {
+                    result.push(layer);
+                }
+            }
+        }
+
+        let mut result = Vec::new();
+        flatten(&self.layers, &mut result);
+        result
+    }
+
+    /// All objects of all object layers
+    pub fn objects(&self) -> impl Iterator<Item = &Object> {
+        self.flattened_layers()
+            .into_iter()
+            .filter_map(|layer| match &layer.kind {
+                LayerKind::Objects(objects) => Some(objects),
+                _ => None,
+            })
+            .flatten()
+    }
+
+    /// All objects with the given class, useful for spawning entities
+    pub fn objects_with_class<'a>(&'a self, class: &'a str) -> impl Iterator<Item = &'a Object> {
+        self.objects().filter(move |object| object.class == class)
+    }
+
+    #[must_use]
+    pub fn object_by_id(&self, id: u32) -> Option<&Object> {
+        self.objects().find(|object| object.id == id)
+    }
+
+    /// Find the tileset containing the given gid and the id of the tile inside it
+    #[must_use]
+    pub fn tileset_for(&self, gid: Gid) -> Option<(usize, u32)> {
+        self.tilesets
+            .iter()
+            .position(|tileset| tileset.contains(gid))
+            .map(|index| (index, gid.id() - self.tilesets[index].first_gid))
+    }
+
+    /// Convert a position in Tiled pixels (y down) to world coordinates of a tilemap filled by `fill_tilemap` with a tile size in pixels
+    #[must_use]
+    pub fn to_world(&self, position: Vec2) -> Vec2 {
+        Vec2::new(
+            position.x,
+            (self.height * self.tile_height) as f32 - position.y,
+        )
+    }
+
+    /// Add every tile layer of this map to the tilemap with its visibility and opacity, only tiles of the given tileset are set.
+    /// The visibility and opacity of group layers aren't applied.
+    /// Animations of the tileset are converted to ticks of the given duration.
+    /// Returns the indexes of the added tilemap layers
+    pub fn fill_tilemap(
+        &self,
+        tilemap: &mut Tilemap,
+        tileset: usize,
+        tick: Duration,
+    ) -> Vec<usize> {
+        let first_gid = self.tilesets[tileset].first_gid;
+
+        for (&id, data) in &self.tilesets[tileset].tiles {
+            // Frames shorter than a tick are still displayed for one tick
+            let animation = TileAnimation::from_durations(
+                data.animation
+                    .iter()
+                    .map(|frame| {
+                        (
+                            frame.tile_id,
+                            ((frame.duration.as_secs_f64() / tick.as_secs_f64()).round() as u32)
+                                .max(1),
+                        )
+                    })
+                    .collect(),
+            );
+
+            if let Ok(animation) = animation {
+                tilemap.set_animation(id, animation);
+            }
+        }
+
+        let mut added = Vec::new();
+
+        for layer in self.flattened_layers() {
+            let LayerKind::Tiles(chunks) = &layer.kind else {
+                continue;
+            };
+
+            let index = tilemap.add_layer();
+            tilemap.set_layer_visible(index, layer.visible);
+            tilemap.set_layer_opacity(index, layer.opacity);
+            added.push(index);
+
+            for chunk in chunks {
+                for (i, &gid) in chunk.tiles.iter().enumerate() {
+                    if self.tileset_for(gid).is_none_or(|(t, _)| t != tileset) {
+                        continue;
+                    }
+
+                    let x = chunk.x + (i as u32 % chunk.width) as i32;
+                    let y = chunk.y + (i as u32 / chunk.width) as i32;
+
+                    tilemap.set_tile(
+                        index,
+                        glam::IVec2::new(x, self.height as i32 - 1 - y),
+                        Some(Tile {
+                            id: gid.id() - first_gid,
+                            flip_x: gid.flipped_horizontally(),
+                            flip_y: gid.flipped_vertically(),
+                            flip_diagonal: gid.flipped_diagonally(),
+                        }),
+                    );
+                }
+            }
+        }
+
+        added
+    }
+}
+
+fn has_extension(path: &str, extension: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(extension))
+}
+
+fn decode_tiles(
+    data: &str,
+    encoding: Option<&str>,
+    compression: Option<&str>,
+) -> Result<Vec<Gid>, TiledError> {
+    match encoding {
+        Some("csv") => data
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                value
+                    .parse()
+                    .map(Gid)
+                    .map_err(|_| TiledError::InvalidData(format!("Invalid tile {value}")))
+            })
+            .collect(),
+        Some("base64") => {
+            let bytes = STANDARD
+                .decode(data.trim())
+                .map_err(|error| TiledError::InvalidData(error.to_string()))?;
+
+            let bytes = match compression.unwrap_or_default() {
+                "" => bytes,
+                "zlib" => miniz_oxide::inflate::decompress_to_vec_zlib(&bytes)
+                    .map_err(|error| TiledError::InvalidData(error.to_string()))?,
+                "gzip" => miniz_oxide::inflate::decompress_to_vec(gzip_payload(&bytes)?)
+                    .map_err(|error| TiledError::InvalidData(error.to_string()))?,
+                other => {
+                    return Err(TiledError::Unsupported(format!("{other} compression")));
+                }
+            };
+
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|c| Gid(u32::from_le_bytes([c[0], c[1], c[2], c[3]])))
+                .collect())
+        }
+        other => Err(TiledError::Unsupported(format!(
+            "{} encoding",
+            other.unwrap_or("unknown")
+        ))),
+    }
+}
+
+/// Skip the header of gzip data to get the raw deflate stream
+fn gzip_payload(bytes: &[u8]) -> Result<&[u8], TiledError> {
+    const FHCRC: u8 = 2;
+    const FEXTRA: u8 = 4;
+    const FNAME: u8 = 8;
+    const FCOMMENT: u8 = 16;
+
+    let invalid = || TiledError::InvalidData("Invalid gzip header".into());
+
+    if bytes.len() < 10 || bytes[0..3] != [0x1f, 0x8b, 8] {
+        return Err(invalid());
+    }
+
+    let flags = bytes[3];
+    let mut offset = 10;
+
+    if flags & FEXTRA != 0 {
+        let length = bytes.get(offset..offset + 2).ok_or_else(invalid)?;
+        offset += 2 + u16::from_le_bytes([length[0], length[1]]) as usize;
+    }
+
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let end = bytes
+                .get(offset..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0))
+                .ok_or_else(invalid)?;
+            offset += end + 1;
+        }
+    }
+
+    if flags & FHCRC != 0 {
+        offset += 2;
+    }
+
+    bytes.get(offset..).ok_or_else(invalid)
+}
+
+fn infer_property_value(value: &Value) -> PropertyValue {
+    match value {
+        Value::Bool(value) => PropertyValue::Bool(*value),
+        Value::Number(number) => number.as_i64().map_or_else(
+            || PropertyValue::Float(number.as_f64().unwrap_or_default()),
+            PropertyValue::Int,
+        ),
+        Value::Object(members) => PropertyValue::Class(Properties(
+            members
+                .iter()
+                .map(|(name, value)| (name.clone(), infer_property_value(value)))
+                .collect(),
+        )),
+        Value::String(value) => PropertyValue::String(value.clone()),
+        Value::Null | Value::Array(_) => PropertyValue::String(String::new()),
+    }
+}
+
+// JSON format
+
+#[derive(Deserialize)]
+struct RawProperty {
+    name: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    value: Value,
+}
+
+fn convert_properties(raw: Vec<RawProperty>) -> Properties {
+    Properties(
+        raw.into_iter()
+            .map(|property| {
+                let value = &property.value;
+
+                let value = match property.kind.as_str() {
+                    "int" => PropertyValue::Int(value.as_i64().unwrap_or_default()),
+                    "float" => PropertyValue::Float(value.as_f64().unwrap_or_default()),
+                    "bool" => PropertyValue::Bool(value.as_bool().unwrap_or_default()),
+                    "color" => PropertyValue::Color(value.as_str().unwrap_or_default().into()),
+                    "file" => PropertyValue::File(value.as_str().unwrap_or_default().into()),
+                    "object" => PropertyValue::Object(value.as_u64().unwrap_or_default() as u32),
+                    _ => infer_property_value(value),
+                };
+
+                (property.name, value)
+            })
+            .collect(),
+    )
+}
+
+#[derive(Deserialize)]
+struct RawMap {
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default)]
+    infinite: bool,
+    #[serde(default)]
+    layers: Vec<RawLayer>,
+    #[serde(default)]
+    tilesets: Vec<RawTileset>,
+    #[serde(default)]
+    properties: Vec<RawProperty>,
+}
+
+impl RawMap {
+    fn convert(self) -> Result<TiledMap, TiledError> {
+        Ok(TiledMap {
+            width: self.width,
+            height: self.height,
+            tile_width: self.tilewidth,
+            tile_height: self.tileheight,
+            infinite: self.infinite,
+            tilesets: self.tilesets.into_iter().map(RawTileset::convert).collect(),
+            layers: self
+                .layers
+                .into_iter()
+                .map(RawLayer::convert)
+                .collect::<Result<_, _>>()?,
+            properties: convert_properties(self.properties),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawData {
+    Tiles(Vec<u32>),
+    Encoded(String),
+}
+
+impl RawData {
+    fn decode(
+        self,
+        encoding: Option<&str>,
+        compression: Option<&str>,
+    ) -> Result<Vec<Gid>, TiledError> {
+        match self {
+            Self::Tiles(tiles) => Ok(tiles.into_iter().map(Gid).collect()),
+            Self::Encoded(data) => decode_tiles(&data, encoding, compression),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawChunk {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    data: RawData,
+}
+
+#[derive(Deserialize)]
+struct RawLayer {
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    class: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(default = "default_one")]
+    opacity: f32,
+    #[serde(default)]
+    offsetx: f32,
+    #[serde(default)]
+    offsety: f32,
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+    data: Option<RawData>,
+    chunks: Option<Vec<RawChunk>>,
+    encoding: Option<String>,
+    compression: Option<String>,
+    #[serde(default)]
+    objects: Vec<RawObject>,
+    #[serde(default)]
+    layers: Vec<Self>,
+    image: Option<String>,
+    #[serde(default)]
+    properties: Vec<RawProperty>,
+}
+
+impl RawLayer {
+    fn convert(self) -> Result<Layer, TiledError> {
+        let encoding = self.encoding.as_deref();
+        let compression = self.compression.as_deref();
+
+        let kind = match self.kind.as_str() {
+            "tilelayer" => LayerKind::Tiles(if let Some(chunks) = self.chunks {
+                chunks
+                    .into_iter()
+                    .map(|chunk| {
+                        TileChunk::new(
+                            chunk.x,
+                            chunk.y,
+                            chunk.width,
+                            chunk.height,
+                            chunk.data.decode(encoding, compression)?,
+                        )
+                    })
+                    .collect::<Result<_, TiledError>>()?
+            } else {
+                vec![TileChunk::new(
+                    0,
+                    0,
+                    self.width,
+                    self.height,
+                    self.data
+                        .ok_or_else(|| TiledError::InvalidData("Tile layer without data".into()))?
+                        .decode(encoding, compression)?,
+                )?]
+            }),
+            "objectgroup" => {
+                LayerKind::Objects(self.objects.into_iter().map(RawObject::convert).collect())
+            }
+            "group" => LayerKind::Group(
+                self.layers
+                    .into_iter()
+                    .map(Self::convert)
+                    .collect::<Result<_, _>>()?,
+            ),
+            "imagelayer" => LayerKind::Image(self.image.filter(|image| !image.is_empty())),
+            other => {
+                return Err(TiledError::InvalidData(format!(
+                    "Unknown layer type {other}"
+                )));
+            }
+        };
+
+        Ok(Layer {
+            id: self.id,
+            name: self.name,
+            class: self.class,
+            visible: self.visible,
+            opacity: self.opacity,
+            offset: Vec2::new(self.offsetx, self.offsety),
+            properties: convert_properties(self.properties),
+            kind,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct RawPoint {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct RawText {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct RawObject {
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default = "default_true")]
+    visible: bool,
+    gid: Option<u32>,
+    #[serde(default)]
+    ellipse: bool,
+    #[serde(default)]
+    point: bool,
+    polygon: Option<Vec<RawPoint>>,
+    polyline: Option<Vec<RawPoint>>,
+    text: Option<RawText>,
+    #[serde(default)]
+    properties: Vec<RawProperty>,
+}
+
+impl RawObject {
+    fn convert(self) -> Object {
+        let points = |points: Vec<RawPoint>| {
+            points
+                .into_iter()
+                .map(|point| Vec2::new(point.x, point.y))
+                .collect()
+        };
+
+        let shape = if self.gid.is_some() {
+            ObjectShape::Tile
+        } else if self.ellipse {
+            ObjectShape::Ellipse
+        } else if self.point {
+            ObjectShape::Point
+        } else if let Some(polygon) = self.polygon {
+            ObjectShape::Polygon(points(polygon))
+        } else if let Some(polyline) = self.polyline {
+            ObjectShape::Polyline(points(polyline))
+        } else if let Some(text) = self.text {
+            ObjectShape::Text(text.text)
+        } else {
+            ObjectShape::Rectangle
+        };
+
+        Object {
+            id: self.id,
+            name: self.name,
+            class: if self.class.is_empty() {
+                self.kind
+            } else {
+                self.class
+            },
+            position: Vec2::new(self.x, self.y),
+            size: Vec2::new(self.width, self.height),
+            rotation: self.rotation,
+            visible: self.visible,
+            gid: self.gid.map(Gid),
+            shape,
+            properties: convert_properties(self.properties),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawFrame {
+    tileid: u32,
+    duration: u64,
+}
+
+#[derive(Deserialize)]
+struct RawTile {
+    id: u32,
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    properties: Vec<RawProperty>,
+    #[serde(default)]
+    animation: Vec<RawFrame>,
+}
+
+#[derive(Deserialize)]
+struct RawTileset {
+    #[serde(default)]
+    firstgid: u32,
+    source: Option<String>,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    tilewidth: u32,
+    #[serde(default)]
+    tileheight: u32,
+    #[serde(default)]
+    tilecount: u32,
+    #[serde(default)]
+    columns: u32,
+    #[serde(default)]
+    spacing: u32,
+    #[serde(default)]
+    margin: u32,
+    image: Option<String>,
+    #[serde(default)]
+    imagewidth: u32,
+    #[serde(default)]
+    imageheight: u32,
+    #[serde(default)]
+    tiles: Vec<RawTile>,
+}
+
+impl RawTileset {
+    fn convert(self) -> Tileset {
+        Tileset {
+            first_gid: self.firstgid,
+            source: self.source,
+            name: self.name,
+            tile_width: self.tilewidth,
+            tile_height: self.tileheight,
+            tile_count: self.tilecount,
+            columns: self.columns,
+            spacing: self.spacing,
+            margin: self.margin,
+            image: self.image,
+            image_width: self.imagewidth,
+            image_height: self.imageheight,
+            tiles: self
+                .tiles
+                .into_iter()
+                .map(|tile| {
+                    (
+                        tile.id,
+                        TileData {
+                            class: if tile.class.is_empty() {
+                                tile.kind
+                            } else {
+                                tile.class
+                            },
+                            properties: convert_properties(tile.properties),
+                            animation: tile
+                                .animation
+                                .into_iter()
+                                .map(|frame| Frame {
+                                    tile_id: frame.tileid,
+                                    duration: Duration::from_millis(frame.duration),
+                                })
+                                .collect(),
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+// XML format
+
+fn attribute<T: FromStr>(node: Node, name: &str) -> Option<T> {
+    node.attribute(name).and_then(|value| value.parse().ok())
+}
+
+fn required_attribute<T: FromStr>(node: Node, name: &str) -> Result<T, TiledError> {
+    attribute(node, name).ok_or_else(|| {
+        TiledError::InvalidData(format!(
+            "Missing or invalid attribute {name} on {}",
+            node.tag_name().name()
+        ))
+    })
+}
+
+fn string_attribute(node: Node, name: &str) -> String {
+    node.attribute(name).unwrap_or_default().into()
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn parse_tmx_properties(node: Node) -> Result<Properties, TiledError> {
+    let Some(properties) = child(node, "properties") else {
+        return Ok(Properties::default());
+    };
+
+    Ok(Properties(
+        properties
+            .children()
+            .filter(|property| property.has_tag_name("property"))
+            .map(|property| {
+                let value = property
+                    .attribute("value")
+                    .or_else(|| property.text())
+                    .unwrap_or_default();
+
+                let invalid = || TiledError::InvalidData(format!("Invalid property value {value}"));
+
+                let value = match property.attribute("type").unwrap_or("string") {
+                    "int" => PropertyValue::Int(value.parse().map_err(|_| invalid())?),
+                    "float" => PropertyValue::Float(value.parse().map_err(|_| invalid())?),
+                    "bool" => PropertyValue::Bool(value.parse().map_err(|_| invalid())?),
+                    "color" => PropertyValue::Color(value.into()),
+                    "file" => PropertyValue::File(value.into()),
+                    "object" => PropertyValue::Object(value.parse().map_err(|_| invalid())?),
+                    "class" => PropertyValue::Class(parse_tmx_properties(property)?),
+                    _ => PropertyValue::String(value.into()),
+                };
+
+                Ok((string_attribute(property, "name"), value))
+            })
+            .collect::<Result<_, TiledError>>()?,
+    ))
+}
+
+fn parse_tmx_tileset(node: Node) -> Result<Tileset, TiledError> {
+    let image = child(node, "image");
+
+    Ok(Tileset {
+        first_gid: attribute(node, "firstgid").unwrap_or(0),
+        source: node.attribute("source").map(Into::into),
+        name: string_attribute(node, "name"),
+        tile_width: attribute(node, "tilewidth").unwrap_or(0),
+        tile_height: attribute(node, "tileheight").unwrap_or(0),
+        tile_count: attribute(node, "tilecount").unwrap_or(0),
+        columns: attribute(node, "columns").unwrap_or(0),
+        spacing: attribute(node, "spacing").unwrap_or(0),
+        margin: attribute(node, "margin").unwrap_or(0),
+        image: image.and_then(|image| image.attribute("source").map(Into::into)),
+        image_width: image
+            .and_then(|image| attribute(image, "width"))
+            .unwrap_or(0),
+        image_height: image
+            .and_then(|image| attribute(image, "height"))
+            .unwrap_or(0),
+        tiles: node
+            .children()
+            .filter(|tile| tile.has_tag_name("tile"))
+            .map(|tile| {
+                let class = tile
+                    .attribute("class")
+                    .or_else(|| tile.attribute("type"))
+                    .unwrap_or_default()
+                    .into();
+
+                let animation = child(tile, "animation").map_or_else(Vec::new, |animation| {
+                    animation
+                        .children()
+                        .filter(|frame| frame.has_tag_name("frame"))
+                        .filter_map(|frame| {
+                            Some(Frame {
+                                tile_id: attribute(frame, "tileid")?,
+                                duration: Duration::from_millis(attribute(frame, "duration")?),
+                            })
+                        })
+                        .collect()
+                });
+
+                Ok((
+                    required_attribute(tile, "id")?,
+                    TileData {
+                        class,
+                        properties: parse_tmx_properties(tile)?,
+                        animation,
+                    },
+                ))
+            })
+            .collect::<Result<_, TiledError>>()?,
+    })
+}
+
+fn parse_tmx_data(data: Node) -> Result<Vec<Gid>, TiledError> {
+    match data.attribute("encoding") {
+        None => Ok(data
+            .children()
+            .filter(|tile| tile.has_tag_name("tile"))
+            .map(|tile| Gid(attribute(tile, "gid").unwrap_or(0)))
+            .collect()),
+        encoding => decode_tiles(
+            data.text().unwrap_or_default(),
+            encoding,
+            data.attribute("compression"),
+        ),
+    }
+}
+
+fn parse_tmx_points(points: &str) -> Vec<Vec2> {
+    points
+        .split_whitespace()
+        .filter_map(|point| {
+            let (x, y) = point.split_once(',')?;
+            Some(Vec2::new(x.parse().ok()?, y.parse().ok()?))
+        })
+        .collect()
+}
+
+fn parse_tmx_object(node: Node) -> Result<Object, TiledError> {
+    let gid = attribute(node, "gid").map(Gid);
+
+    let shape = if gid.is_some() {
+        ObjectShape::Tile
+    } else if child(node, "ellipse").is_some() {
+        ObjectShape::Ellipse
+    } else if child(node, "point").is_some() {
+        ObjectShape::Point
+    } else if let Some(polygon) = child(node, "polygon") {
+        ObjectShape::Polygon(parse_tmx_points(polygon.attribute("points").unwrap_or("")))
+    } else if let Some(polyline) = child(node, "polyline") {
+        ObjectShape::Polyline(parse_tmx_points(polyline.attribute("points").unwrap_or("")))
+    } else if let Some(text) = child(node, "text") {
+        ObjectShape::Text(text.text().unwrap_or_default().into())
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    Ok(Object {
+        id: attribute(node, "id").unwrap_or(0),
+        name: string_attribute(node, "name"),
+        class: node
+            .attribute("class")
+            .or_else(|| node.attribute("type"))
+            .unwrap_or_default()
+            .into(),
+        position: Vec2::new(
+            attribute(node, "x").unwrap_or(0.),
+            attribute(node, "y").unwrap_or(0.),
+        ),
+        size: Vec2::new(
+            attribute(node, "width").unwrap_or(0.),
+            attribute(node, "height").unwrap_or(0.),
+        ),
+        rotation: attribute(node, "rotation").unwrap_or(0.),
+        visible: attribute(node, "visible").unwrap_or(1) != 0,
+        gid,
+        shape,
+        properties: parse_tmx_properties(node)?,
+    })
+}
+
+fn parse_tmx_layers(node: Node) -> Result<Vec<Layer>, TiledError> {
+    node.children()
+        .filter(|layer| {
+            ["layer", "objectgroup", "group", "imagelayer"]
+                .iter()
+                .any(|name| layer.has_tag_name(*name))
+        })
+        .map(|layer| {
+            let kind = match layer.tag_name().name() {
+                "layer" => {
+                    let data = child(layer, "data")
+                        .ok_or_else(|| TiledError::InvalidData("Tile layer without data".into()))?;
+
+                    let chunks = data
+                        .children()
+                        .filter(|chunk| chunk.has_tag_name("chunk"))
+                        .collect::<Vec<_>>();
+
+                    LayerKind::Tiles(if chunks.is_empty() {
+                        vec![TileChunk::new(
+                            0,
+                            0,
+                            required_attribute(layer, "width")?,
+                            required_attribute(layer, "height")?,
+                            parse_tmx_data(data)?,
+                        )?]
+                    } else {
+                        chunks
+                            .into_iter()
+                            .map(|chunk| {
+                                // Chunks inherit the encoding of their data element
+                                let tiles = match data.attribute("encoding") {
+                                    None => parse_tmx_data(chunk)?,
+                                    encoding => decode_tiles(
+                                        chunk.text().unwrap_or_default(),
+                                        encoding,
+                                        data.attribute("compression"),
+                                    )?,
+                                };
+
+                                TileChunk::new(
+                                    required_attribute(chunk, "x")?,
+                                    required_attribute(chunk, "y")?,
+                                    required_attribute(chunk, "width")?,
+                                    required_attribute(chunk, "height")?,
+                                    tiles,
+                                )
+                            })
+                            .collect::<Result<_, TiledError>>()?
+                    })
+                }
+                "objectgroup" => LayerKind::Objects(
+                    layer
+                        .children()
+                        .filter(|object| object.has_tag_name("object"))
+                        .map(parse_tmx_object)
+                        .collect::<Result<_, _>>()?,
+                ),
+                "group" => LayerKind::Group(parse_tmx_layers(layer)?),
+                _ => LayerKind::Image(
+                    child(layer, "image")
+                        .and_then(|image| image.attribute("source").map(Into::into)),
+                ),
+            };
+
+            Ok(Layer {
+                id: attribute(layer, "id").unwrap_or(0),
+                name: string_attribute(layer, "name"),
+                class: string_attribute(layer, "class"),
+                visible: attribute(layer, "visible").unwrap_or(1) != 0,
+                opacity: attribute(layer, "opacity").unwrap_or(1.),
+                offset: Vec2::new(
+                    attribute(layer, "offsetx").unwrap_or(0.),
+                    attribute(layer, "offsety").unwrap_or(0.),
+                ),
+                properties: parse_tmx_properties(layer)?,
+                kind,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::software::SoftwareCanvas2d;
+    use glam::{IVec2, UVec2};
+
+    const TERRAIN: &str = include_str!("../../resources/maps/terrain.tsx");
+
+    /// Tiles of the ground layer of every fixture map, the last row is flipped
+    #[rustfmt::skip]
+    const GROUND: [u32; 12] = [
+        1, 2, 3, 4,
+        5, 6, 0, 0,
+        1 | 0x8000_0000,
+        2 | 0x4000_0000,
+        3 | 0x2000_0000,
+        4 | 0xE000_0000,
+    ];
+
+    fn resolved(mut map: TiledMap) -> TiledMap {
+        map.resolve_tilesets(|source| {
+            assert_eq!(source, "terrain.tsx");
+            Ok(TERRAIN.into())
+        })
+        .unwrap();
+
+        map
+    }
+
+    fn ground(map: &TiledMap) -> &[TileChunk] {
+        match &map.layers[0].kind {
+            LayerKind::Tiles(chunks) => chunks,
+            _ => panic!("Error, first layer isn't a tile layer"),
+        }
+    }
+
+    fn assert_ground(map: &TiledMap) {
+        let chunks = ground(map);
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!((chunks[0].width, chunks[0].height), (4, 3));
+        assert_eq!(chunks[0].tiles, GROUND.map(Gid));
+    }
+
+    fn assert_objects(map: &TiledMap) {
+        let spawn = map.objects_with_class("player").next().unwrap();
+        let properties = &spawn.properties;
+
+        assert_eq!(spawn.shape, ObjectShape::Point);
+        assert_eq!(spawn.position, Vec2::new(8., 24.));
+        assert_eq!(properties.get_int("health"), Some(3));
+        assert_eq!(properties.get_float("speed"), Some(1.5));
+        assert_eq!(properties.get_bool("friendly"), Some(true));
+        assert_eq!(
+            properties.get("tint"),
+            Some(&PropertyValue::Color("#ff00ff00".into()))
+        );
+        assert_eq!(
+            properties.get("script"),
+            Some(&PropertyValue::File("player.lua".into()))
+        );
+        assert_eq!(properties.get_str("label"), Some("hero"));
+        assert_eq!(
+            properties
+                .get_class("stats")
+                .and_then(|stats| stats.get_int("level")),
+            Some(7)
+        );
+
+        let target = map.object_by_id(properties.get_object("target").unwrap());
+        assert_eq!(target.map(|door| door.class.as_str()), Some("door"));
+        assert_eq!(target.map(|door| door.size), Some(Vec2::new(16., 32.)));
+    }
+
+    #[test]
+    fn csv_data() {
+        let map =
+            resolved(TiledMap::from_tmx(include_str!("../../resources/maps/csv.tmx")).unwrap());
+
+        assert_ground(&map);
+    }
+
+    #[test]
+    fn base64_zlib_data() {
+        let map = TiledMap::from_tmj(include_str!("../../resources/maps/zlib.tmj")).unwrap();
+
+        assert_ground(&map);
+    }
+
+    #[test]
+    fn base64_gzip_data() {
+        let map =
+            resolved(TiledMap::from_tmx(include_str!("../../resources/maps/gzip.tmx")).unwrap());
+
+        assert_ground(&map);
+    }
+
+    #[test]
+    fn unsupported_compression() {
+        assert!(matches!(
+            decode_tiles("AAAA", Some("base64"), Some("zstd")),
+            Err(TiledError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn infinite_chunks() {
+        for map in [
+            TiledMap::from_tmj(include_str!("../../resources/maps/infinite.tmj")).unwrap(),
+            TiledMap::from_tmx(include_str!("../../resources/maps/infinite.tmx")).unwrap(),
+        ] {
+            let map = resolved(map);
+            assert!(map.infinite);
+
+            let chunks = ground(&map);
+            assert_eq!(chunks.len(), 2);
+
+            // Reassemble the rows of both chunks
+            for (index, &gid) in GROUND.iter().enumerate() {
+                let (x, y) = (index as i32 % 4 - 2, index as u32 / 4);
+                let chunk = &chunks[usize::from(x >= 0)];
+
+                assert_eq!((chunk.x, chunk.y), (if x < 0 { -2 } else { 0 }, 0));
+                assert_eq!(
+                    chunk.tiles[(y * chunk.width + (x - chunk.x) as u32) as usize],
+                    Gid(gid)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn flip_bits() {
+        let map = TiledMap::from_tmj(include_str!("../../resources/maps/zlib.tmj")).unwrap();
+        let tiles = &ground(&map)[0].tiles;
+
+        let flips = |gid: Gid| {
+            (
+                gid.id(),
+                gid.flipped_horizontally(),
+                gid.flipped_vertically(),
+                gid.flipped_diagonally(),
+            )
+        };
+
+        assert_eq!(flips(tiles[0]), (1, false, false, false));
+        assert_eq!(flips(tiles[8]), (1, true, false, false));
+        assert_eq!(flips(tiles[9]), (2, false, true, false));
+        assert_eq!(flips(tiles[10]), (3, false, false, true));
+        assert_eq!(flips(tiles[11]), (4, true, true, true));
+        assert!(tiles[6].is_empty());
+    }
+
+    #[test]
+    fn layer_data_without_width() {
+        let text = include_str!("../../resources/maps/zlib.tmj").replacen(
+            "\"type\": \"tilelayer\",\n   \"width\": 4,",
+            "\"type\": \"tilelayer\",",
+            1,
+        );
+
+        assert!(matches!(
+            TiledMap::from_tmj(&text),
+            Err(TiledError::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn tileset_contains_large_ranges() {
+        let map = TiledMap::from_tmj(include_str!("../../resources/maps/zlib.tmj")).unwrap();
+
+        let mut tileset = map.tilesets[0].clone();
+        tileset.first_gid = 2;
+        tileset.tile_count = u32::MAX;
+
+        assert!(!tileset.contains(Gid(1)));
+        assert!(tileset.contains(Gid(2)));
+        assert!(tileset.contains(Gid(0x0fff_ffff | 0x8000_0000)));
+    }
+
+    #[test]
+    fn fill_tilemap_flips() {
+        let map = TiledMap::from_tmj(include_str!("../../resources/maps/zlib.tmj")).unwrap();
+        let canvas = SoftwareCanvas2d::new(UVec2::ONE);
+        let texture = canvas.create_texture(56, 38, &[0; 56 * 38 * 4]);
+
+        let mut tilemap = Tilemap::new(Vec2::splat(16.), map.tilesets[0].grid_tileset(texture));
+        let layers = map.fill_tilemap(&mut tilemap, 0, Duration::from_millis(100));
+        assert_eq!(layers.len(), 1);
+
+        // The first row of the map is the top one
+        assert_eq!(
+            tilemap.tile(layers[0], IVec2::new(0, 2)),
+            Some(Tile::new(0))
+        );
+        assert_eq!(tilemap.tile(layers[0], IVec2::new(2, 1)), None);
+        assert_eq!(
+            tilemap.tile(layers[0], IVec2::new(3, 0)),
+            Some(Tile {
+                id: 3,
+                flip_x: true,
+                flip_y: true,
+                flip_diagonal: true,
+            })
+        );
+    }
+
+    #[test]
+    fn external_tsx_tileset() {
+        let mut map = TiledMap::from_tmx(include_str!("../../resources/maps/csv.tmx")).unwrap();
+        assert_eq!(map.tilesets[0].source.as_deref(), Some("terrain.tsx"));
+
+        map = resolved(map);
+        let tileset = &map.tilesets[0];
+
+        assert_eq!(tileset.source, None);
+        assert_eq!(tileset.first_gid, 1);
+        assert_eq!(tileset.name, "terrain");
+        assert_eq!((tileset.tile_count, tileset.columns), (6, 3));
+        assert_eq!((tileset.margin, tileset.spacing), (1, 2));
+        assert_eq!(tileset.image.as_deref(), Some("terrain.png"));
+        assert_eq!(map.tileset_for(Gid(6 | 0x8000_0000)), Some((0, 5)));
+        assert_eq!(map.tileset_for(Gid(7)), None);
+
+        let water = &tileset.tiles[&2];
+        assert_eq!(water.class, "water");
+        assert_eq!(water.properties.get_float("speed"), Some(0.5));
+        assert_eq!(
+            water.animation,
+            [
+                Frame {
+                    tile_id: 2,
+                    duration: Duration::from_millis(200)
+                },
+                Frame {
+                    tile_id: 5,
+                    duration: Duration::from_millis(300)
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn object_properties() {
+        assert_objects(&TiledMap::from_tmj(include_str!("../../resources/maps/zlib.tmj")).unwrap());
+        assert_objects(&TiledMap::from_tmx(include_str!("../../resources/maps/csv.tmx")).unwrap());
+    }
+
+    #[test]
+    fn grid_tileset_margin_and_spacing() {
+        let map = TiledMap::from_tmj(include_str!("../../resources/maps/zlib.tmj")).unwrap();
+        let canvas = SoftwareCanvas2d::new(UVec2::ONE);
+        let texture = canvas.create_texture(56, 38, &[0; 56 * 38 * 4]);
+
+        let grid = map.tilesets[0].grid_tileset(texture);
+        assert_eq!((grid.columns, grid.rows), (3, 2));
+
+        // Tile 4 is on the second column and row, one margin and one spacing away from the image corner
+        let rect = grid.tile_rect(4);
+        let pixels = Vec2::new(56., 38.);
+
+        assert!((rect.position * pixels).abs_diff_eq(Vec2::splat(19.), 1e-4));
+        assert!((rect.size * pixels).abs_diff_eq(Vec2::splat(16.), 1e-4));
+
+        let rect = grid.tile_rect(0);
+        assert!((rect.position * pixels).abs_diff_eq(Vec2::ONE, 1e-4));
+    }
+}
@@ -0,0 +1,40 @@
+use crate::dom;
+use js_sys::Uint8Array;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::Response;
+
+#[derive(Debug)]
+pub struct FetchError;
+
+/// Fetch the resource at the given url as bytes
+///
+/// # Errors
+///
+/// Returns Err if the request failed or the server didn't answer with a success status
+pub async fn fetch_bytes(url: &str) -> Result<Vec<u8>, FetchError> {
+    let response = JsFuture::from(dom::fetch(url))
+        .await
+        .map_err(|_| FetchError)?
+        .dyn_into::<Response>()
+        .map_err(|_| FetchError)?;
+
+    if !response.ok() {
+        return Err(FetchError);
+    }
+
+    let buffer = JsFuture::from(response.array_buffer().map_err(|_| FetchError)?)
+        .await
+        .map_err(|_| FetchError)?;
+
+    Ok(Uint8Array::new(&buffer).to_vec())
+}
+
+/// Fetch the resource at the given url as utf-8 text
+///
+/// # Errors
+///
+/// Returns Err if the request failed or the resource isn't valid utf-8
+pub async fn fetch_text(url: &str) -> Result<String, FetchError> {
+    String::from_utf8(fetch_bytes(url).await?).map_err(|_| FetchError)
+}
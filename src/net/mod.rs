@@ -1,4 +1,8 @@
+mod fetch;
 mod web_socket;
 
+pub use fetch::fetch_bytes;
+pub use fetch::fetch_text;
+pub use fetch::FetchError;
 pub use web_socket::WebSocket;
 pub use web_socket::WebSocketError;
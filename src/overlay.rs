@@ -0,0 +1,420 @@
+use crate::{dom::document, dom_stack::stack_node, render::canvas2d::Canvas2d};
+use glam::Vec2;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, VecDeque},
+    rc::Rc,
+};
+use wasm_bindgen::{prelude::Closure, JsCast};
+use web_sys::{Event, HtmlElement, HtmlInputElement, KeyboardEvent};
+
+const CONTAINER_CSS: &str =
+    "position:absolute;top:0;left:0;width:100%;height:100%;overflow:hidden;pointer-events:none;";
+const ELEMENT_CSS: &str = "position:absolute;left:0;top:0;pointer-events:auto;";
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct ElementId(u32);
+
+/// New value of an input element
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Text(String),
+    Number(f64),
+    Checked(bool),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum OverlayEvent {
+    Clicked(ElementId),
+    Changed(ElementId, Value),
+    /// Enter was pressed in a text input
+    Submitted(ElementId),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum InputKind {
+    Text,
+    Number,
+    Checkbox,
+}
+
+struct Element {
+    node: HtmlElement,
+    /// World position and pivot, the pivot being the point of the element placed there as a fraction of its size
+    anchor: Option<(Vec2, Vec2)>,
+    /// Panel the element was put in with `set_parent`
+    parent: Option<ElementId>,
+    _listeners: Vec<Closure<dyn FnMut(Event)>>,
+}
+
+/// A layer of DOM elements displayed above the canvas, for text heavy menus that are easier to build in HTML
+///
+/// Events of the elements are queued and read from the game loop with `poll_events`.
+/// Keyboard and mouse events on inputs and buttons of the overlay are not seen by the `input` module
+pub struct Overlay {
+    container: HtmlElement,
+    elements: BTreeMap<ElementId, Element>,
+    events: Rc<RefCell<VecDeque<OverlayEvent>>>,
+    next_id: u32,
+}
+
+impl Overlay {
+    /// Create an empty overlay and stack it on the dom, it should be created after the canvas to be above it
+    #[must_use]
+    pub fn new() -> Self {
+        let container = create_element("div");
+        container.style().set_css_text(CONTAINER_CSS);
+
+        stack_node(&container);
+
+        Self {
+            container,
+            elements: BTreeMap::new(),
+            events: Rc::new(RefCell::new(VecDeque::new())),
+            next_id: 0,
+        }
+    }
+
+    /// Take the events that happened since the last call, in order
+    #[must_use]
+    pub fn poll_events(&self) -> Vec<OverlayEvent> {
+        self.events.borrow_mut().drain(..).collect()
+    }
+
+    /// A block with the given css, other elements can be put in it with `set_parent`
+    pub fn panel(&mut self, css: &str) -> ElementId {
+        let node = create_element("div");
+        node.style().set_css_text(&format!("{ELEMENT_CSS}{css}"));
+
+        self.insert(node, Vec::new())
+    }
+
+    pub fn label(&mut self, text: &str) -> ElementId {
+        let node = create_element("span");
+        node.style().set_css_text(ELEMENT_CSS);
+        node.set_inner_text(text);
+
+        self.insert(node, Vec::new())
+    }
+
+    /// A button sending `OverlayEvent::Clicked`
+    pub fn button(&mut self, text: &str) -> ElementId {
+        let node = create_element("button");
+        node.style().set_css_text(ELEMENT_CSS);
+        node.set_inner_text(text);
+
+        let id = self.allocate_id();
+        let events = self.events.clone();
+
+        let listener = listen(&node, "click", move |_| {
+            events.borrow_mut().push_back(OverlayEvent::Clicked(id));
+        });
+
+        self.insert_with_id(id, node, vec![listener])
+    }
+
+    /// A single line text input sending `OverlayEvent::Changed` on every edit and `OverlayEvent::Submitted` on enter
+    pub fn text_input(&mut self, placeholder: &str) -> ElementId {
+        let input = create_input("text");
+        input.set_placeholder(placeholder);
+
+        let id = self.allocate_id();
+        let events = self.events.clone();
+
+        let submit_listener = listen(&input, "keydown", move |event| {
+            let enter = event
+                .dyn_ref::<KeyboardEvent>()
+                .is_some_and(|event| event.key() == "Enter");
+
+            if enter {
+                events.borrow_mut().push_back(OverlayEvent::Submitted(id));
+            }
+        });
+
+        let change_listener = self.change_listener(id, &input, "input", InputKind::Text);
+
+        self.insert_with_id(id, input.into(), vec![change_listener, submit_listener])
+    }
+
+    /// A checkbox sending `OverlayEvent::Changed` with `Value::Checked`
+    pub fn checkbox(&mut self, checked: bool) -> ElementId {
+        let input = create_input("checkbox");
+        input.set_checked(checked);
+
+        let id = self.allocate_id();
+        let listener = self.change_listener(id, &input, "change", InputKind::Checkbox);
+
+        self.insert_with_id(id, input.into(), vec![listener])
+    }
+
+    /// A slider sending `OverlayEvent::Changed` with `Value::Number` while it is moved
+    pub fn slider(&mut self, min: f64, max: f64, step: f64, value: f64) -> ElementId {
+        let input = create_input("range");
+        input.set_min(&min.to_string());
+        input.set_max(&max.to_string());
+        input.set_step(&step.to_string());
+        input.set_value_as_number(value);
+
+        let id = self.allocate_id();
+        let listener = self.change_listener(id, &input, "input", InputKind::Number);
+
+        self.insert_with_id(id, input.into(), vec![listener])
+    }
+
+    /// Remove an element and its children from the overlay
+    pub fn remove(&mut self, id: ElementId) {
+        let Some(element) = self.elements.remove(&id) else {
+            return;
+        };
+
+        element.node.remove();
+
+        let children = self
+            .elements
+            .iter()
+            .filter(|(_, child)| child.parent == Some(id))
+            .map(|(&child, _)| child)
+            .collect::<Vec<_>>();
+
+        for child in children {
+            self.remove(child);
+        }
+    }
+
+    /// Put an element inside a panel, it then follows the layout of the panel instead of being positioned.
+    /// Nothing happens if the panel is the element itself or one of its children
+    pub fn set_parent(&mut self, id: ElementId, parent: ElementId) {
+        let mut ancestor = Some(parent);
+
+        while let Some(current) = ancestor {
+            if current == id {
+                return;
+            }
+
+            ancestor = self
+                .elements
+                .get(&current)
+                .and_then(|element| element.parent);
+        }
+
+        let Some(parent_node) = self.elements.get(&parent).map(|parent| parent.node.clone()) else {
+            return;
+        };
+
+        let Some(element) = self.elements.get_mut(&id) else {
+            return;
+        };
+
+        element.anchor = None;
+        element.parent = Some(parent);
+
+        let style = element.node.style();
+        style.set_property("position", "static").unwrap();
+        style.remove_property("transform").unwrap();
+
+        parent_node.append_child(&element.node).unwrap();
+    }
+
+    /// Add css declarations to the style of an element
+    pub fn set_style(&self, id: ElementId, css: &str) {
+        if let Some(element) = self.elements.get(&id) {
+            let style = element.node.style();
+            style.set_css_text(&format!("{}{css}", style.css_text()));
+        }
+    }
+
+    pub fn set_class(&self, id: ElementId, class: &str) {
+        if let Some(element) = self.elements.get(&id) {
+            element.node.set_class_name(class);
+        }
+    }
+
+    /// Set the text of a label, button or panel
+    pub fn set_text(&self, id: ElementId, text: &str) {
+        if let Some(element) = self.elements.get(&id) {
+            element.node.set_inner_text(text);
+        }
+    }
+
+    /// Current value of an input, None for other elements
+    #[must_use]
+    pub fn value(&self, id: ElementId) -> Option<Value> {
+        let input = self.elements.get(&id)?.node.dyn_ref::<HtmlInputElement>()?;
+
+        Some(input_value(input, input_kind(input)))
+    }
+
+    /// Set the value of an input without sending a change event
+    pub fn set_value(&self, id: ElementId, value: &Value) {
+        let Some(input) = self
+            .elements
+            .get(&id)
+            .and_then(|element| element.node.dyn_ref::<HtmlInputElement>())
+        else {
+            return;
+        };
+
+        match value {
+            Value::Text(text) => input.set_value(text),
+            Value::Number(number) => input.set_value_as_number(*number),
+            Value::Checked(checked) => input.set_checked(*checked),
+        }
+    }
+
+    pub fn set_visible(&self, id: ElementId, visible: bool) {
+        if let Some(element) = self.elements.get(&id) {
+            element.node.set_hidden(!visible);
+        }
+    }
+
+    /// Place the top left corner of an element at the given screen position in pixels
+    pub fn set_screen_position(&mut self, id: ElementId, position: Vec2) {
+        if let Some(element) = self.elements.get_mut(&id) {
+            element.anchor = None;
+            set_transform(&element.node, position, Vec2::ZERO);
+        }
+    }
+
+    /// Keep an element at the given world position, it is moved by `update`.
+    /// The pivot is the point of the element placed there, (0, 0) being its top left corner and (1, 1) its bottom right one
+    pub fn anchor(&mut self, id: ElementId, world_position: Vec2, pivot: Vec2) {
+        if let Some(element) = self.elements.get_mut(&id) {
+            element.anchor = Some((world_position, pivot));
+        }
+    }
+
+    /// Move the anchored elements with the current view matrix of the canvas, call it after moving the camera
+    pub fn update(&self, canvas: &Canvas2d) {
+        for element in self.elements.values() {
+            if let Some((world_position, pivot)) = element.anchor {
+                set_transform(
+                    &element.node,
+                    canvas.world_to_screen_pos(world_position),
+                    pivot,
+                );
+            }
+        }
+    }
+
+    const fn allocate_id(&mut self) -> ElementId {
+        let id = ElementId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    fn insert(
+        &mut self,
+        node: HtmlElement,
+        listeners: Vec<Closure<dyn FnMut(Event)>>,
+    ) -> ElementId {
+        let id = self.allocate_id();
+        self.insert_with_id(id, node, listeners)
+    }
+
+    fn insert_with_id(
+        &mut self,
+        id: ElementId,
+        node: HtmlElement,
+        listeners: Vec<Closure<dyn FnMut(Event)>>,
+    ) -> ElementId {
+        self.container.append_child(&node).unwrap();
+
+        self.elements.insert(
+            id,
+            Element {
+                node,
+                anchor: None,
+                parent: None,
+                _listeners: listeners,
+            },
+        );
+
+        id
+    }
+
+    fn change_listener(
+        &self,
+        id: ElementId,
+        input: &HtmlInputElement,
+        event_type: &str,
+        kind: InputKind,
+    ) -> Closure<dyn FnMut(Event)> {
+        let events = self.events.clone();
+        let input_clone = input.clone();
+
+        listen(input, event_type, move |_| {
+            events
+                .borrow_mut()
+                .push_back(OverlayEvent::Changed(id, input_value(&input_clone, kind)));
+        })
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        self.container.remove();
+    }
+}
+
+fn create_element(tag: &str) -> HtmlElement {
+    document()
+        .create_element(tag)
+        .unwrap()
+        .dyn_into::<HtmlElement>()
+        .unwrap()
+}
+
+fn create_input(input_type: &str) -> HtmlInputElement {
+    let input = create_element("input")
+        .dyn_into::<HtmlInputElement>()
+        .unwrap();
+
+    input.set_type(input_type);
+    input.style().set_css_text(ELEMENT_CSS);
+
+    input
+}
+
+fn listen(
+    node: &HtmlElement,
+    event_type: &str,
+    listener: impl FnMut(Event) + 'static,
+) -> Closure<dyn FnMut(Event)> {
+    let closure = Closure::<dyn FnMut(Event)>::new(listener);
+
+    node.add_event_listener_with_callback(event_type, closure.as_ref().unchecked_ref())
+        .unwrap();
+
+    closure
+}
+
+fn input_kind(input: &HtmlInputElement) -> InputKind {
+    match input.type_().as_str() {
+        "checkbox" => InputKind::Checkbox,
+        "range" | "number" => InputKind::Number,
+        _ => InputKind::Text,
+    }
+}
+
+fn input_value(input: &HtmlInputElement, kind: InputKind) -> Value {
+    match kind {
+        InputKind::Text => Value::Text(input.value()),
+        InputKind::Number => Value::Number(input.value_as_number()),
+        InputKind::Checkbox => Value::Checked(input.checked()),
+    }
+}
+
+fn set_transform(node: &HtmlElement, position: Vec2, pivot: Vec2) {
+    node.style()
+        .set_property(
+            "transform",
+            &format!(
+                "translate({}px, {}px) translate({}%, {}%)",
+                position.x,
+                position.y,
+                -pivot.x * 100.,
+                -pivot.y * 100.
+            ),
+        )
+        .unwrap();
+}
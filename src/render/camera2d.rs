@@ -0,0 +1,177 @@
+use glam::{Mat3, UVec2, Vec2};
+
+/// A 2d camera producing view matrices for a `Canvas2d`.
+///
+/// Call `tick` at a fixed rate (for example from a `TickScheduler`) to advance following, zoom easing and shake,
+/// with the size of the canvas the camera is drawn on so that `bounds` match its aspect ratio
+pub struct Camera2d {
+    /// Center of the camera in world coordinates
+    pub position: Vec2,
+    /// Distance visible on the left and right of the center at a zoom of 1
+    pub view_radius: f32,
+    /// Zoom factor, 2 means everything appears twice as big
+    pub zoom: f32,
+    /// Rotation of the camera in radians, counterclockwise
+    pub rotation: f32,
+    /// Zoom the camera eases toward on every tick
+    pub target_zoom: f32,
+    /// Fraction of the remaining zoom difference covered on every tick, 1 means no easing
+    pub zoom_smoothness: f32,
+    /// Fraction of the remaining distance to the followed point covered on every tick, 1 means no smoothing
+    pub follow_smoothness: f32,
+    /// Half size of the zone around the camera center in which the followed target can move without moving the camera
+    pub deadzone: Vec2,
+    /// How far ahead of the followed target the camera looks, multiplied by the target velocity
+    pub look_ahead: f32,
+    /// World rectangle (min, max) the camera view must stay inside
+    pub bounds: Option<(Vec2, Vec2)>,
+    /// Maximum offset in world units applied by shake at full trauma
+    pub max_shake_offset: f32,
+    /// Maximum rotation in radians applied by shake at full trauma
+    pub max_shake_angle: f32,
+    /// Trauma removed on every tick
+    pub trauma_decay: f32,
+    /// Speed at which the shake noise changes on every tick
+    pub shake_frequency: f32,
+    follow_target: Option<Vec2>,
+    trauma: f32,
+    shake_time: f32,
+    /// Width over height of the canvas given to the last `tick`
+    aspect_ratio: f32,
+}
+
+impl Camera2d {
+    #[must_use]
+    pub const fn new(position: Vec2, view_radius: f32) -> Self {
+        Self {
+            position,
+            view_radius,
+            zoom: 1.,
+            rotation: 0.,
+            target_zoom: 1.,
+            zoom_smoothness: 0.1,
+            follow_smoothness: 0.1,
+            deadzone: Vec2::ZERO,
+            look_ahead: 0.,
+            bounds: None,
+            max_shake_offset: 0.,
+            max_shake_angle: 0.,
+            trauma_decay: 0.02,
+            shake_frequency: 0.5,
+            follow_target: None,
+            trauma: 0.,
+            shake_time: 0.,
+            aspect_ratio: 1.,
+        }
+    }
+
+    /// Set the point followed by the camera, `velocity` is used for look-ahead
+    pub fn follow(&mut self, target: Vec2, velocity: Vec2) {
+        self.follow_target = Some(target + velocity * self.look_ahead);
+    }
+
+    /// Stop following the last target, the camera stays where it is
+    pub const fn stop_follow(&mut self) {
+        self.follow_target = None;
+    }
+
+    /// Set the zoom and its target, skipping easing
+    pub const fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom;
+        self.target_zoom = zoom;
+    }
+
+    /// Add trauma to the camera, shake intensity grows with the square of the trauma which is capped to 1
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0., 1.);
+    }
+
+    #[must_use]
+    pub const fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Advance follow, zoom easing and shake by one tick, for a canvas of the given size
+    pub fn tick(&mut self, canvas_size: UVec2) {
+        self.aspect_ratio = aspect_ratio(canvas_size);
+        self.zoom += (self.target_zoom - self.zoom) * self.zoom_smoothness;
+
+        if let Some(target) = self.follow_target {
+            let offset = target - self.position;
+            let outside = offset - offset.clamp(-self.deadzone, self.deadzone);
+
+            self.position += outside * self.follow_smoothness;
+        }
+
+        self.position = self.clamped_position(self.position, self.aspect_ratio);
+
+        self.trauma = (self.trauma - self.trauma_decay).max(0.);
+        self.shake_time += self.shake_frequency;
+    }
+
+    /// Half of the world size visible by this camera on the canvas of the last `tick`, without taking rotation into account
+    #[must_use]
+    pub fn half_extent(&self) -> Vec2 {
+        self.half_extent_for(self.aspect_ratio)
+    }
+
+    fn half_extent_for(&self, aspect_ratio: f32) -> Vec2 {
+        let half_width = self.view_radius / self.zoom;
+
+        Vec2::new(half_width, half_width / aspect_ratio)
+    }
+
+    fn clamped_position(&self, position: Vec2, aspect_ratio: f32) -> Vec2 {
+        let Some((min, max)) = self.bounds else {
+            return position;
+        };
+
+        let half_extent = self.half_extent_for(aspect_ratio);
+        let mut clamped = position;
+
+        for axis in 0..2 {
+            let low = min[axis] + half_extent[axis];
+            let high = max[axis] - half_extent[axis];
+
+            clamped[axis] = if low > high {
+                f32::midpoint(min[axis], max[axis])
+            } else {
+                position[axis].clamp(low, high)
+            };
+        }
+
+        clamped
+    }
+
+    /// Computes the view matrix for a canvas of the given size, shake included
+    #[must_use]
+    pub fn view_matrix(&self, canvas_size: UVec2) -> Mat3 {
+        let aspect_ratio = aspect_ratio(canvas_size);
+
+        let shake = self.trauma * self.trauma;
+
+        let offset = Vec2::new(noise(self.shake_time, 1.), noise(self.shake_time, 2.))
+            * shake
+            * self.max_shake_offset;
+        let angle = noise(self.shake_time, 3.) * shake * self.max_shake_angle;
+
+        let position = self.clamped_position(self.position, aspect_ratio) + offset;
+        let half_extent = self.half_extent_for(aspect_ratio);
+
+        Mat3::from_scale(half_extent.recip())
+            * Mat3::from_angle(-(self.rotation + angle))
+            * Mat3::from_translation(-position)
+    }
+}
+
+fn aspect_ratio(canvas_size: UVec2) -> f32 {
+    canvas_size.x as f32 / canvas_size.y.max(1) as f32
+}
+
+/// Smooth noise in [-1, 1], `seed` selects an independent channel
+fn noise(time: f32, seed: f32) -> f32 {
+    let t = time + seed * 17.31;
+
+    ((t * 1.3).sin() * 0.5 + (t * 2.9 + seed).sin() * 0.3 + (t * 5.7 + seed * 3.).sin() * 0.2)
+        .clamp(-1., 1.)
+}
@@ -0,0 +1,86 @@
+use glam::{Mat4, UVec2, Vec3};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Projection {
+    /// Vertical field of view in radians
+    Perspective { fov_y: f32 },
+    /// Height of the visible area in world units, the width follows the aspect ratio
+    Orthographic { height: f32 },
+}
+
+/// A camera looking at a target, see `Canvas3d::set_camera`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Camera3d {
+    pub position: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+    pub projection: Projection,
+    /// Distance of the near clipping plane
+    pub near: f32,
+    /// Distance of the far clipping plane
+    pub far: f32,
+}
+
+impl Camera3d {
+    /// A perspective camera with a vertical field of view in radians
+    #[must_use]
+    pub const fn perspective(position: Vec3, target: Vec3, fov_y: f32) -> Self {
+        Self {
+            position,
+            target,
+            up: Vec3::Y,
+            projection: Projection::Perspective { fov_y },
+            near: 0.1,
+            far: 1000.,
+        }
+    }
+
+    /// An orthographic camera seeing `height` world units vertically, useful for 2.5D and isometric views
+    #[must_use]
+    pub const fn orthographic(position: Vec3, target: Vec3, height: f32) -> Self {
+        Self {
+            position,
+            target,
+            up: Vec3::Y,
+            projection: Projection::Orthographic { height },
+            near: 0.1,
+            far: 1000.,
+        }
+    }
+
+    /// Matrix converting from world coordinates to camera coordinates
+    #[must_use]
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::look_at_rh(self.position, self.target, self.up)
+    }
+
+    /// Matrix converting from camera coordinates to opengl coordinates for the given aspect ratio (width / height)
+    #[must_use]
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> Mat4 {
+        match self.projection {
+            Projection::Perspective { fov_y } => {
+                Mat4::perspective_rh_gl(fov_y, aspect_ratio, self.near, self.far)
+            }
+            Projection::Orthographic { height } => {
+                let half = height / 2.;
+
+                Mat4::orthographic_rh_gl(
+                    -half * aspect_ratio,
+                    half * aspect_ratio,
+                    -half,
+                    half,
+                    self.near,
+                    self.far,
+                )
+            }
+        }
+    }
+
+    /// Matrix converting from world coordinates to opengl coordinates on a canvas of the given size
+    #[must_use]
+    pub fn view_projection(&self, canvas_size: UVec2) -> Mat4 {
+        let aspect_ratio = canvas_size.x as f32 / canvas_size.y.max(1) as f32;
+
+        self.projection_matrix(aspect_ratio) * self.view_matrix()
+    }
+}
@@ -5,11 +5,27 @@ precision highp float;
 in vec4 vColor;
 in vec2 vTexcoord;
 
-uniform sampler2D uTexture;
+flat in int vTexture;
+
+// As many as MAX_BATCH_TEXTURES, samplers can only be indexed by constants
+uniform sampler2D uTextures[8];
 
 out vec4 outColor;
 
+vec4 sampleTexture() {
+    switch (vTexture) {
+        case 0: return texture(uTextures[0], vTexcoord);
+        case 1: return texture(uTextures[1], vTexcoord);
+        case 2: return texture(uTextures[2], vTexcoord);
+        case 3: return texture(uTextures[3], vTexcoord);
+        case 4: return texture(uTextures[4], vTexcoord);
+        case 5: return texture(uTextures[5], vTexcoord);
+        case 6: return texture(uTextures[6], vTexcoord);
+        default: return texture(uTextures[7], vTexcoord);
+    }
+}
+
 void main() {
-    outColor = texture(uTexture, vTexcoord) * vColor;
+    outColor = sampleTexture() * vColor;
     outColor.rgb *= outColor.a;
 }
@@ -1,42 +1,94 @@
-use super::webgl_util::{buffer_f32_slice, buffer_u16_indexes, compile_shader, link_program};
-use crate::dom::window;
-use glam::{Mat3, UVec2, Vec2, Vec3, Vec4};
+use super::{
+    camera2d::Camera2d,
+    gradient::{Gradient, GradientShape, MAX_SHADER_STOPS},
+    nine_slice::NineSlice,
+    polygon::{regular, triangulate},
+    software::SoftwareTexture,
+    vertex::{interleave, stride, Vertex, Vertex2d, VertexAttribute},
+    viewport2d::Viewport2d,
+    webgl_util::{buffer_f32_slice, buffer_u16_indexes, compile_shader, link_program},
+};
+use crate::{
+    dom::{is_worker, window},
+    font::FontStack,
+    video::Video,
+    worker,
+};
+use glam::{IVec2, Mat3, UVec2, Vec2, Vec3, Vec4};
 use js_sys::Object;
-use meshtext::{Face, MeshGenerator, MeshText, TextSection};
-use std::{cell::RefCell, f32::consts::TAU};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    f32::consts::TAU,
+    marker::PhantomData,
+    rc::Rc,
+};
 use wasm_bindgen::JsCast;
 use web_sys::{
-    HtmlCanvasElement, ImageBitmap, OffscreenCanvas, WebGl2RenderingContext, WebGlBuffer,
-    WebGlContextAttributes, WebGlTexture, WebGlUniformLocation,
+    HtmlCanvasElement, ImageBitmap, MediaStream, OffscreenCanvas, WebGl2RenderingContext,
+    WebGlBuffer, WebGlContextAttributes, WebGlFramebuffer, WebGlProgram, WebGlQuery, WebGlTexture,
+    WebGlUniformLocation,
 };
 
+/// A texture of a draw target, it can only be drawn on targets of the backend that created it
+#[derive(Clone)]
+pub enum Texture {
+    WebGl(WebGlTexture),
+    Software(Rc<SoftwareTexture>),
+}
+
+impl Texture {
+    /// Check if both are the same texture object
+    #[must_use]
+    pub fn is_same(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::WebGl(a), Self::WebGl(b)) => Object::is(a, b),
+            (Self::Software(a), Self::Software(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn webgl(&self) -> &WebGlTexture {
+        match self {
+            Self::WebGl(texture) => texture,
+            Self::Software(_) => panic!("Error, software textures can't be drawn on a Canvas2d"),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct TextureRect {
-    pub webgl_texture: WebGlTexture,
+    pub texture: Texture,
     pub position: Vec2,
     pub size: Vec2,
 }
 
 impl TextureRect {
-    const fn new(texture: WebGlTexture) -> Self {
+    pub(crate) const fn new(texture: Texture) -> Self {
         Self {
-            webgl_texture: texture,
+            texture,
             position: Vec2::ZERO,
             size: Vec2::ONE,
         }
     }
 }
 
+/// How drawn colors are combined with the colors already on the canvas
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum BlendMode {
+    /// Regular transparency
+    #[default]
+    Alpha,
+    /// Colors are added, useful for glows, fire and lights
+    Additive,
+    /// Colors are multiplied, useful for shadows and light maps
+    Multiply,
+}
+
 pub trait DrawTarget2d {
-    /// Draw on context from raw vertex data
-    fn draw_raw(
-        &mut self,
-        indexes: &[u16],
-        positions: &[f32],
-        colors: &[f32],
-        texcoords: &[f32],
-        texture: &WebGlTexture,
-    );
+    /// Draw triangles from their vertices, each index refers to a vertex of the slice
+    fn draw_vertices(&mut self, indexes: &[u16], vertices: &[Vertex2d], texture: &Texture);
 
     /// Draw a rectangle, color and texture are multiplied
     fn draw_rect(&mut self, position: Vec2, size: Vec2, color: Vec4, texture: &TextureRect) {
@@ -46,32 +98,21 @@ pub trait DrawTarget2d {
         let w = size.x;
         let h = size.y;
 
-        let r = color.x;
-        let g = color.y;
-        let b = color.z;
-        let a = color.w;
-
         let t_x = texture.position.x;
         let t_y = texture.position.y;
 
         let t_w = texture.size.x;
         let t_h = texture.size.y;
 
-        self.draw_raw(
+        self.draw_vertices(
             &[0, 1, 2, 1, 2, 3],
-            &[x, y, x + w, y, x, y + h, x + w, y + h],
-            &[r, g, b, a, r, g, b, a, r, g, b, a, r, g, b, a],
             &[
-                t_x,
-                t_y + t_h,
-                t_x + t_w,
-                t_y + t_h,
-                t_x,
-                t_y,
-                t_x + t_w,
-                t_y,
+                Vertex2d::new(Vec2::new(x, y), color, Vec2::new(t_x, t_y + t_h)),
+                Vertex2d::new(Vec2::new(x + w, y), color, Vec2::new(t_x + t_w, t_y + t_h)),
+                Vertex2d::new(Vec2::new(x, y + h), color, Vec2::new(t_x, t_y)),
+                Vertex2d::new(Vec2::new(x + w, y + h), color, Vec2::new(t_x + t_w, t_y)),
             ],
-            &texture.webgl_texture,
+            &texture.texture,
         );
     }
 
@@ -87,11 +128,6 @@ pub trait DrawTarget2d {
         let c_x = center.x;
         let c_y = center.y;
 
-        let r = color.x;
-        let g = color.y;
-        let b = color.z;
-        let a = color.w;
-
         let t_x = texture.position.x;
         let t_y = texture.position.y;
 
@@ -99,20 +135,13 @@ pub trait DrawTarget2d {
         let t_h = texture.size.y;
 
         let mut indexes = Vec::new();
-        let mut positions = Vec::new();
-        let mut colors = Vec::new();
-        let mut texcoords = Vec::new();
+        let mut vertices = Vec::new();
 
-        positions.push(c_x);
-        positions.push(c_y);
-
-        colors.push(r);
-        colors.push(g);
-        colors.push(b);
-        colors.push(a);
-
-        texcoords.push(t_x + t_w / 2.);
-        texcoords.push(t_y + t_h / 2.);
+        vertices.push(Vertex2d::new(
+            center,
+            color,
+            Vec2::new(t_x + t_w / 2., t_y + t_h / 2.),
+        ));
 
         let step_size = TAU / sides as f32;
 
@@ -123,33 +152,113 @@ pub trait DrawTarget2d {
 
             let (sin_y, cos_x) = (i as f32 * step_size).sin_cos();
 
-            positions.push(c_x + cos_x * radius);
-            positions.push(c_y + sin_y * radius);
+            vertices.push(Vertex2d::new(
+                Vec2::new(c_x + cos_x * radius, c_y + sin_y * radius),
+                color,
+                Vec2::new(t_x + t_w * (cos_x + 1.) / 2., t_y + t_h * (1. - sin_y) / 2.),
+            ));
+        }
 
-            colors.push(r);
-            colors.push(g);
-            colors.push(b);
-            colors.push(a);
+        self.draw_vertices(&indexes, &vertices, &texture.texture);
+    }
 
-            texcoords.push(t_x + t_w * (cos_x + 1.) / 2.);
-            texcoords.push(t_y + t_h * (1. - sin_y) / 2.);
+    /// Draw a polygon from its outline, it must not intersect itself. Color and texture are multiplied, the texture is stretched over the bounding box
+    fn draw_polygon(&mut self, points: &[Vec2], color: Vec4, texture: &TextureRect) {
+        draw_triangles(
+            self,
+            points,
+            &triangulate(points),
+            points,
+            |_| color,
+            texture,
+        );
+    }
+
+    /// Draw a polygon filled with a gradient using vertex colors, it must not intersect itself.
+    /// Linear gradients are exact, radial ones are approximated, see `Canvas2d::fill_gradient` for smooth ones
+    ///
+    /// # Panics
+    ///
+    /// Panics if a single triangle of the polygon is tessellated in more than 65535 vertices, which takes thousands of stops
+    fn draw_gradient_polygon(
+        &mut self,
+        points: &[Vec2],
+        gradient: &Gradient,
+        texture: &TextureRect,
+    ) {
+        let mut positions = Vec::new();
+        let mut indexes = Vec::new();
+
+        // Tessellated triangles can have hundreds of vertices, batches keep their indexes in range
+        for triangle in triangulate(points).chunks_exact(3) {
+            let (piece_positions, piece_indexes) = gradient.tessellate(points, triangle);
+
+            if positions.len() + piece_positions.len() > u16::MAX as usize {
+                draw_triangles(
+                    self,
+                    &positions,
+                    &indexes,
+                    points,
+                    |position| gradient.color_at(position),
+                    texture,
+                );
+
+                positions.clear();
+                indexes.clear();
+            }
+
+            let first = positions.len() as u16;
+
+            indexes.extend(piece_indexes.iter().map(|&index| first + index));
+            positions.extend(piece_positions);
         }
 
-        self.draw_raw(
-            &indexes,
+        draw_triangles(
+            self,
             &positions,
-            &colors,
-            &texcoords,
-            &texture.webgl_texture,
+            &indexes,
+            points,
+            |position| gradient.color_at(position),
+            texture,
         );
     }
 
+    /// Draw a rectangle filled with a gradient, texture and gradient are multiplied
+    fn draw_gradient_rect(
+        &mut self,
+        position: Vec2,
+        size: Vec2,
+        gradient: &Gradient,
+        texture: &TextureRect,
+    ) {
+        self.draw_gradient_polygon(&rect_points(position, size), gradient, texture);
+    }
+
+    /// Draw a regular polygon filled with a gradient, texture and gradient are multiplied
+    fn draw_gradient_regular(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        sides: u16,
+        gradient: &Gradient,
+        texture: &TextureRect,
+    ) {
+        self.draw_gradient_polygon(&regular(center, radius, sides), gradient, texture);
+    }
+
+    /// Draw a nine-slice at any size, color and texture are multiplied
+    fn draw_nine_slice(&mut self, position: Vec2, size: Vec2, color: Vec4, nine_slice: &NineSlice) {
+        nine_slice.draw(self, position, size, color);
+    }
+
+    /// Draw a text, each character is taken from the first font of the stack containing it.
+    /// Color and texture are multiplied, the texture is stretched over the whole text
     fn draw_text(
         &mut self,
         position: Vec2,
         height: f32,
         text: &str,
-        font: &mut MeshGenerator<Face<'_>>,
+        font: &mut FontStack,
         color: Vec4,
         texture: &TextureRect,
     ) {
@@ -159,68 +268,143 @@ pub trait DrawTarget2d {
         let t_w = texture.size.x;
         let t_h = texture.size.y;
 
-        let mesh: MeshText = font
-            .generate_section_2d(
-                text,
-                Some(&[
-                    height, 0., 0., // x
-                    0., height, 0., // y
-                    position.x, position.y, 0., // z
-                ]),
-            )
-            .unwrap(); // Ideally would use MeshTextIndexed, but it has errors with some characters
+        let positions = font.generate_vertices(position, height, text);
+
+        if positions.is_empty() {
+            return;
+        }
 
-        let x_min = mesh.bbox.min.x;
-        let x_max = mesh.bbox.max.x;
+        let (min, max) = positions.chunks_exact(2).fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), c| {
+                let v = Vec2::new(c[0], c[1]);
+                (min.min(v), max.max(v))
+            },
+        );
 
-        let y_min = mesh.bbox.min.y;
-        let y_max = mesh.bbox.max.y;
+        let x_min = min.x;
+        let y_min = min.y;
 
-        let x_diff = x_max - x_min;
-        let y_diff = y_max - y_min;
+        let x_diff = max.x - x_min;
+        let y_diff = max.y - y_min;
 
         let x_factor = t_w / x_diff;
 
-        let indices = (0..mesh.vertices.len() / 2)
+        let indices = (0..positions.len() / 2)
             .map(|i| i as u16)
             .collect::<Vec<_>>();
 
-        let colors = (0..mesh.vertices.len() / 2)
-            .flat_map(|_| color.to_array())
-            .collect::<Vec<_>>();
-
-        let texcoords = mesh
-            .vertices
+        let vertices = positions
             .chunks_exact(2)
-            .flat_map(|c| {
+            .map(|c| {
                 let x = c[0];
                 let y = c[1];
 
-                [
-                    (x - x_min) * x_factor + t_x,
-                    t_h * (1. - (y - y_min) / y_diff) + t_y,
-                ]
+                Vertex2d::new(
+                    Vec2::new(x, y),
+                    color,
+                    Vec2::new(
+                        (x - x_min) * x_factor + t_x,
+                        t_h * (1. - (y - y_min) / y_diff) + t_y,
+                    ),
+                )
             })
             .collect::<Vec<_>>();
 
-        self.draw_raw(
-            &indices,
-            &mesh.vertices,
-            &colors,
-            &texcoords,
-            &texture.webgl_texture,
-        );
+        self.draw_vertices(&indices, &vertices, &texture.texture);
     }
 }
 
-/// A utility struct for easily batching geometry together
+fn rect_points(position: Vec2, size: Vec2) -> [Vec2; 4] {
+    [
+        position,
+        position + Vec2::new(size.x, 0.),
+        position + size,
+        position + Vec2::new(0., size.y),
+    ]
+}
+
+/// Draw triangles with the texture stretched over the bounding box of the outline
+fn draw_triangles<T: DrawTarget2d + ?Sized>(
+    target: &mut T,
+    positions: &[Vec2],
+    indexes: &[u16],
+    outline: &[Vec2],
+    color: impl Fn(Vec2) -> Vec4,
+    texture: &TextureRect,
+) {
+    if indexes.is_empty() {
+        return;
+    }
+
+    let (min, max) = outline.iter().fold(
+        (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+        |(min, max), &p| (min.min(p), max.max(p)),
+    );
+
+    let size = (max - min).max(Vec2::splat(f32::EPSILON));
+
+    let vertices = positions
+        .iter()
+        .map(|&p| {
+            let relative = (p - min) / size;
+
+            Vertex2d::new(
+                p,
+                color(p),
+                Vec2::new(
+                    texture.position.x + texture.size.x * relative.x,
+                    texture.position.y + texture.size.y * (1. - relative.y),
+                ),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    target.draw_vertices(indexes, &vertices, &texture.texture);
+}
+
+/// Number of textures a single draw call can sample from, a batch is split once it uses more
+pub const MAX_BATCH_TEXTURES: usize = 8;
+
+/// A `Vertex2d` with the index of its texture in the batch
+#[derive(Clone, Copy)]
+struct BatchVertex2d {
+    vertex: Vertex2d,
+    texture: f32,
+}
+
+impl Vertex for BatchVertex2d {
+    const ATTRIBUTES: &'static [VertexAttribute] = &[
+        VertexAttribute {
+            name: "aPosition",
+            size: 2,
+        },
+        VertexAttribute {
+            name: "aColor",
+            size: 4,
+        },
+        VertexAttribute {
+            name: "aTexcoord",
+            size: 2,
+        },
+        VertexAttribute {
+            name: "aTexture",
+            size: 1,
+        },
+    ];
+
+    fn write(&self, data: &mut Vec<f32>) {
+        self.vertex.write(data);
+        data.push(self.texture);
+    }
+}
+
+/// A utility struct for easily batching geometry together, up to `MAX_BATCH_TEXTURES` different textures
 pub struct ObjectBuilder2d {
     index_counter: u16,
     indexes: Vec<u16>,
-    positions: Vec<f32>,
-    colors: Vec<f32>,
-    texcoords: Vec<f32>,
-    texture: Option<WebGlTexture>,
+    vertices: Vec<BatchVertex2d>,
+    textures: Vec<Texture>,
 }
 
 /// Object builder is used to create buffers that can be reused efficiently without having to reupload everything to the GPU every time
@@ -230,40 +414,36 @@ impl ObjectBuilder2d {
         Self {
             index_counter: 0,
             indexes: Vec::new(),
-            positions: Vec::new(),
-            colors: Vec::new(),
-            texcoords: Vec::new(),
-            texture: None,
+            vertices: Vec::new(),
+            textures: Vec::new(),
         }
     }
+
+    /// Check if geometry with the given texture can be added without exceeding `MAX_BATCH_TEXTURES`
+    #[must_use]
+    pub fn can_batch(&self, texture: &Texture) -> bool {
+        self.textures.len() < MAX_BATCH_TEXTURES
+            || self.textures.iter().any(|tex| tex.is_same(texture))
+    }
 }
 
 impl DrawTarget2d for ObjectBuilder2d {
-    fn draw_raw(
-        &mut self,
-        indexes: &[u16],
-        positions: &[f32],
-        colors: &[f32],
-        texcoords: &[f32],
-        texture: &WebGlTexture,
-    ) {
-        assert_eq!(
-            positions.len(),
-            texcoords.len(),
-            "Buffer length doesn't match"
-        );
-        assert_eq!(
-            positions.len() * 2,
-            colors.len(),
-            "Buffer length doesn't match"
-        );
-
-        assert!(
-            self.texture
-                .as_ref()
-                .is_none_or(|tex| Object::is(tex, texture)),
-            "All texture rect must share the same texture inside a given buffer"
-        );
+    /// # Panics
+    ///
+    /// Panics if the buffer would use more than `MAX_BATCH_TEXTURES` textures or 65536 vertices
+    fn draw_vertices(&mut self, indexes: &[u16], vertices: &[Vertex2d], texture: &Texture) {
+        let texture_index =
+            if let Some(index) = self.textures.iter().position(|tex| tex.is_same(texture)) {
+                index
+            } else {
+                assert!(
+                    self.textures.len() < MAX_BATCH_TEXTURES,
+                    "Error, buffers are limited to {MAX_BATCH_TEXTURES} textures"
+                );
+
+                self.textures.push(texture.clone());
+                self.textures.len() - 1
+            };
 
         let mut increment = 0;
 
@@ -277,13 +457,11 @@ impl DrawTarget2d for ObjectBuilder2d {
             .checked_add(increment + 1)
             .expect("Error, buffers are limited to 65536 vertices");
 
-        self.positions.extend_from_slice(positions);
-        self.colors.extend_from_slice(colors);
-        self.texcoords.extend_from_slice(texcoords);
-
-        if self.texture.is_none() {
-            self.texture = Some(texture.clone());
-        }
+        self.vertices
+            .extend(vertices.iter().map(|&vertex| BatchVertex2d {
+                vertex,
+                texture: texture_index as f32,
+            }));
     }
 }
 
@@ -292,42 +470,176 @@ impl DrawTarget2d for ObjectBuilder2d {
 pub struct BufferedObject2d {
     count: u16,
     index_buffer: WebGlBuffer,
-    position_buffer: WebGlBuffer,
-    color_buffer: WebGlBuffer,
-    texcoord_buffer: WebGlBuffer,
-    texture: WebGlTexture,
+    /// Interleaved `Vertex2d` attributes followed by the texture index
+    vertex_buffer: WebGlBuffer,
+    /// Bound to consecutive texture units
+    textures: Vec<WebGlTexture>,
+}
+
+/// An offscreen drawing surface, its content can be drawn like any other texture
+#[derive(Clone)]
+pub struct RenderTarget2d {
+    size: UVec2,
+    framebuffer: WebGlFramebuffer,
+    texture: TextureRect,
+}
+
+impl RenderTarget2d {
+    #[must_use]
+    pub const fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Get a texture rect on the whole content of this target
+    #[must_use]
+    pub fn texture(&self) -> TextureRect {
+        self.texture.clone()
+    }
+}
+
+/// A user shader drawing vertices of type `V`, see `Canvas2d::create_shader`
+pub struct Shader2d<V: Vertex> {
+    program: WebGlProgram,
+    attribute_locations: Vec<i32>,
+    view_matrix: Option<WebGlUniformLocation>,
+    vertex: PhantomData<V>,
+}
+
+struct GradientProgram {
+    program: WebGlProgram,
+    view_matrix: WebGlUniformLocation,
+    radial: WebGlUniformLocation,
+    start: WebGlUniformLocation,
+    end: WebGlUniformLocation,
+    stop_count: WebGlUniformLocation,
+    stop_offsets: WebGlUniformLocation,
+    stop_colors: WebGlUniformLocation,
+}
+
+impl GradientProgram {
+    fn new(gl: &WebGl2RenderingContext) -> Self {
+        let vert_shader = compile_shader(
+            gl,
+            WebGl2RenderingContext::VERTEX_SHADER,
+            include_str!("gradient2d.vert"),
+        );
+
+        let frag_shader = compile_shader(
+            gl,
+            WebGl2RenderingContext::FRAGMENT_SHADER,
+            include_str!("gradient2d.frag"),
+        );
+
+        let program = link_program(gl, &vert_shader, &frag_shader);
+
+        let location = |name: &str| {
+            gl.get_uniform_location(&program, name)
+                .unwrap_or_else(|| panic!("Can't get {name} location"))
+        };
+
+        gl.use_program(Some(&program));
+        gl.uniform1i(Some(&location("uTexture")), 0);
+
+        Self {
+            view_matrix: location("uViewMatrix"),
+            radial: location("uRadial"),
+            start: location("uStart"),
+            end: location("uEnd"),
+            stop_count: location("uStopCount"),
+            stop_offsets: location("uStopOffsets"),
+            stop_colors: location("uStopColors"),
+            program,
+        }
+    }
+}
+
+/// Counters of the work done by a `Canvas2d` during a frame, see `Canvas2d::stats`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    /// Flushes of the internal draw buffer that drew something
+    pub flushes: u32,
+    /// Vertices processed by the draw calls, shared vertices are counted each time they are used
+    pub vertices: u32,
+    /// Texture units bound to a different texture than in the previous draw call
+    pub texture_switches: u32,
+    /// Vertex and index buffers uploaded to the GPU
+    pub buffer_uploads: u32,
+    pub uploaded_bytes: u32,
+    /// Textures currently alive, including render targets
+    pub textures: u32,
+}
+
+/// Value of `TIME_ELAPSED_EXT` from `EXT_disjoint_timer_query_webgl2`
+const TIME_ELAPSED: u32 = 0x88BF;
+/// Value of `GPU_DISJOINT_EXT` from `EXT_disjoint_timer_query_webgl2`
+const GPU_DISJOINT: u32 = 0x8FBB;
+
+/// Measures the GPU time of each frame with timer queries, their results are only available a few frames later
+struct GpuTimer {
+    active: Option<WebGlQuery>,
+    pending: VecDeque<WebGlQuery>,
+    last_time: Option<f64>,
+}
+
+/// An offscreen render target of fixed size, presented scaled on the canvas
+struct VirtualScreen {
+    integer_scaling: bool,
+    target: RenderTarget2d,
 }
 
 /// An accelerated 2d drawing context backed by webgl2
+///
+/// Call `begin_frame` at the start of every frame, render statistics and GPU times are only collected between its calls
 pub struct Canvas2d {
     canvas: OffscreenCanvas,
+    /// The page canvas showing the offscreen one, None when created offscreen
+    element: Option<HtmlCanvasElement>,
     gl: WebGl2RenderingContext,
+    program: WebGlProgram,
+    gradient_program: GradientProgram,
     position_attribute_location: i32,
     color_attribute_location: i32,
     texcoord_attribute_location: i32,
+    texture_attribute_location: i32,
     view_matrix_uniform_location: WebGlUniformLocation,
     view_matrix: Mat3,
     direct_draw_builder: RefCell<ObjectBuilder2d>,
     white_texture: TextureRect,
+    virtual_screen: Option<VirtualScreen>,
+    render_target: Option<RenderTarget2d>,
+    viewport: Viewport2d,
+    blend_mode: BlendMode,
+    stats: Cell<RenderStats>,
+    frame_stats: RenderStats,
+    last_textures: RefCell<Vec<WebGlTexture>>,
+    gpu_timer: Option<GpuTimer>,
 }
 
 impl Canvas2d {
     #[must_use]
     pub fn new(canvas: &HtmlCanvasElement) -> Self {
+        let element = canvas.clone();
         let canvas = canvas.transfer_control_to_offscreen().unwrap();
 
-        Self::internal_new(canvas)
+        Self::internal_new(canvas, Some(element))
     }
 
     #[must_use]
     pub fn new_offscreen(size: UVec2) -> Self {
         let canvas = OffscreenCanvas::new(size.x, size.y).unwrap();
 
-        Self::internal_new(canvas)
+        Self::internal_new(canvas, None)
+    }
+
+    /// Draw on a canvas transferred to a worker, see `worker::receive_canvas`
+    #[must_use]
+    pub fn from_offscreen(canvas: OffscreenCanvas) -> Self {
+        Self::internal_new(canvas, None)
     }
 
     #[must_use]
-    fn internal_new(canvas: OffscreenCanvas) -> Self {
+    fn internal_new(canvas: OffscreenCanvas, element: Option<HtmlCanvasElement>) -> Self {
         let attrs = WebGlContextAttributes::new();
         attrs.set_antialias(true);
         attrs.set_alpha(false);
@@ -351,6 +663,8 @@ impl Canvas2d {
             include_str!("canvas2d.frag"),
         );
 
+        let gradient_program = GradientProgram::new(&webgl);
+
         let program = link_program(&webgl, &vert_shader, &frag_shader);
 
         webgl.use_program(Some(&program));
@@ -361,9 +675,11 @@ impl Canvas2d {
 
         let texcoord_attribute_location = webgl.get_attrib_location(&program, "aTexcoord");
 
-        let image_uniform_location = webgl
-            .get_uniform_location(&program, "uTexture")
-            .expect("Can't get texture location");
+        let texture_attribute_location = webgl.get_attrib_location(&program, "aTexture");
+
+        let textures_uniform_location = webgl
+            .get_uniform_location(&program, "uTextures")
+            .expect("Can't get textures location");
 
         let view_matrix_uniform_location = webgl
             .get_uniform_location(&program, "uViewMatrix")
@@ -371,8 +687,10 @@ impl Canvas2d {
 
         let white_texture = webgl.create_texture().expect("Can't create texture");
 
+        let units = (0..MAX_BATCH_TEXTURES as i32).collect::<Vec<_>>();
+
         webgl.active_texture(WebGl2RenderingContext::TEXTURE0);
-        webgl.uniform1i(Some(&image_uniform_location), 0);
+        webgl.uniform1iv_with_i32_array(Some(&textures_uniform_location), &units);
 
         webgl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&white_texture));
 
@@ -391,6 +709,8 @@ impl Canvas2d {
             )
             .expect("Can't upload data to texture");
 
+        webgl.enable(WebGl2RenderingContext::SCISSOR_TEST);
+
         webgl.enable(WebGl2RenderingContext::BLEND);
         webgl.blend_func(
             WebGl2RenderingContext::ONE,
@@ -400,17 +720,33 @@ impl Canvas2d {
         webgl.enable_vertex_attrib_array(position_attribute_location as u32);
         webgl.enable_vertex_attrib_array(color_attribute_location as u32);
         webgl.enable_vertex_attrib_array(texcoord_attribute_location as u32);
+        webgl.enable_vertex_attrib_array(texture_attribute_location as u32);
 
         Self {
             canvas,
+            element,
             gl: webgl,
+            program,
+            gradient_program,
             position_attribute_location,
             color_attribute_location,
             texcoord_attribute_location,
+            texture_attribute_location,
             view_matrix_uniform_location,
             view_matrix: Mat3::IDENTITY,
             direct_draw_builder: RefCell::new(ObjectBuilder2d::new()),
-            white_texture: TextureRect::new(white_texture),
+            white_texture: TextureRect::new(Texture::WebGl(white_texture)),
+            virtual_screen: None,
+            render_target: None,
+            viewport: Viewport2d::FULL,
+            blend_mode: BlendMode::Alpha,
+            stats: Cell::new(RenderStats {
+                textures: 1,
+                ..RenderStats::default()
+            }),
+            frame_stats: RenderStats::default(),
+            last_textures: RefCell::new(Vec::new()),
+            gpu_timer: None,
         }
     }
 
@@ -422,58 +758,59 @@ impl Canvas2d {
     /// Draw the given buffer on the canvas.
     /// It may be necessary to flush draw calls done without a buffer before drawing this buffer, it is however never needed to flush after drawing a buffer.
     pub fn draw_buffer(&self, buffer: &BufferedObject2d) {
+        self.draw_buffer_with(buffer, &self.view_matrix_uniform_location);
+    }
+
+    fn draw_buffer_with(
+        &self,
+        buffer: &BufferedObject2d,
+        view_matrix_location: &WebGlUniformLocation,
+    ) {
         self.gl.bind_buffer(
             WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
             Some(&buffer.index_buffer),
         );
 
-        self.gl.bind_buffer(
-            WebGl2RenderingContext::ARRAY_BUFFER,
-            Some(&buffer.position_buffer),
-        );
-        self.gl.vertex_attrib_pointer_with_i32(
-            self.position_attribute_location as u32,
-            2,
-            WebGl2RenderingContext::FLOAT,
-            false,
-            0,
-            0,
-        );
-
-        self.gl.bind_buffer(
-            WebGl2RenderingContext::ARRAY_BUFFER,
-            Some(&buffer.color_buffer),
-        );
-        self.gl.vertex_attrib_pointer_with_i32(
-            self.color_attribute_location as u32,
-            4,
-            WebGl2RenderingContext::FLOAT,
-            false,
-            0,
-            0,
-        );
-
-        self.gl.bind_buffer(
-            WebGl2RenderingContext::ARRAY_BUFFER,
-            Some(&buffer.texcoord_buffer),
-        );
-        self.gl.vertex_attrib_pointer_with_i32(
-            self.texcoord_attribute_location as u32,
-            2,
-            WebGl2RenderingContext::FLOAT,
-            false,
-            0,
-            0,
+        self.set_vertex_layout::<BatchVertex2d>(
+            &buffer.vertex_buffer,
+            &[
+                self.position_attribute_location,
+                self.color_attribute_location,
+                self.texcoord_attribute_location,
+                self.texture_attribute_location,
+            ],
         );
 
         self.gl.uniform_matrix3fv_with_f32_array(
-            Some(&self.view_matrix_uniform_location),
+            Some(view_matrix_location),
             false,
             &self.view_matrix.to_cols_array(),
         );
 
-        self.gl
-            .bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&buffer.texture));
+        let mut last_textures = self.last_textures.borrow_mut();
+        let mut texture_switches = 0;
+
+        for (unit, texture) in buffer.textures.iter().enumerate() {
+            self.gl
+                .active_texture(WebGl2RenderingContext::TEXTURE0 + unit as u32);
+            self.gl
+                .bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(texture));
+
+            match last_textures.get_mut(unit) {
+                Some(last) if Object::is(last, texture) => {}
+                Some(last) => {
+                    *last = texture.clone();
+                    texture_switches += 1;
+                }
+                None => {
+                    last_textures.push(texture.clone());
+                    texture_switches += 1;
+                }
+            }
+        }
+
+        // Textures are created and bound outside of draws on the first unit
+        self.gl.active_texture(WebGl2RenderingContext::TEXTURE0);
 
         self.gl.draw_elements_with_i32(
             WebGl2RenderingContext::TRIANGLES,
@@ -481,6 +818,114 @@ impl Canvas2d {
             WebGl2RenderingContext::UNSIGNED_SHORT,
             0,
         );
+
+        self.update_stats(|stats| {
+            stats.draw_calls += 1;
+            stats.vertices += buffer.count as u32;
+            stats.texture_switches += texture_switches;
+        });
+    }
+
+    /// Start a new frame, the counters of the previous one become available with `stats`.
+    /// It also delimits the frames measured by the GPU timer.
+    /// Nothing calls it automatically, call it once at the start of every frame, before `DebugOverlay::begin_frame`
+    pub fn begin_frame(&mut self) {
+        self.flush();
+
+        let stats = self.stats.get();
+
+        self.frame_stats = stats;
+        self.stats.set(RenderStats {
+            textures: stats.textures,
+            ..RenderStats::default()
+        });
+
+        if let Some(timer) = &mut self.gpu_timer {
+            if let Some(query) = timer.active.take() {
+                self.gl.end_query(TIME_ELAPSED);
+                timer.pending.push_back(query);
+            }
+
+            while let Some(query) = timer.pending.front() {
+                let available = self
+                    .gl
+                    .get_query_parameter(query, WebGl2RenderingContext::QUERY_RESULT_AVAILABLE)
+                    .as_bool()
+                    .unwrap_or(false);
+
+                if !available {
+                    break;
+                }
+
+                // Results are meaningless when the GPU was disjoint, like after a power state change
+                let disjoint = self
+                    .gl
+                    .get_parameter(GPU_DISJOINT)
+                    .ok()
+                    .and_then(|value| value.as_bool())
+                    .unwrap_or(false);
+
+                if !disjoint {
+                    timer.last_time = self
+                        .gl
+                        .get_query_parameter(query, WebGl2RenderingContext::QUERY_RESULT)
+                        .as_f64()
+                        .map(|nanoseconds| nanoseconds / 1_000_000.);
+                }
+
+                self.gl.delete_query(timer.pending.pop_front().as_ref());
+            }
+
+            timer.active = self.gl.create_query();
+
+            if let Some(query) = &timer.active {
+                self.gl.begin_query(TIME_ELAPSED, query);
+            }
+        }
+    }
+
+    /// Counters of the last frame, between the two last calls to `begin_frame`
+    #[must_use]
+    pub const fn stats(&self) -> RenderStats {
+        self.frame_stats
+    }
+
+    /// Measure the GPU time of frames with `EXT_disjoint_timer_query_webgl2`, see `gpu_time`.
+    /// Returns false if the extension isn't available, browsers often disable it
+    pub fn enable_gpu_timer(&mut self) -> bool {
+        if self.gpu_timer.is_some() {
+            return true;
+        }
+
+        let available = self
+            .gl
+            .get_extension("EXT_disjoint_timer_query_webgl2")
+            .ok()
+            .flatten()
+            .is_some();
+
+        if available {
+            self.gpu_timer = Some(GpuTimer {
+                active: None,
+                pending: VecDeque::new(),
+                last_time: None,
+            });
+        }
+
+        available
+    }
+
+    /// GPU time of the last measured frame in milliseconds, it lags a few frames behind.
+    /// None until the first result is available or if the GPU timer isn't enabled
+    #[must_use]
+    pub fn gpu_time(&self) -> Option<f64> {
+        self.gpu_timer.as_ref().and_then(|timer| timer.last_time)
+    }
+
+    fn update_stats(&self, update: impl FnOnce(&mut RenderStats)) {
+        let mut stats = self.stats.get();
+        update(&mut stats);
+        self.stats.set(stats);
     }
 
     /// Create the opengl buffers from the data inside the builder
@@ -488,48 +933,192 @@ impl Canvas2d {
     /// None is returned if the buffer was empty
     /// (Reusing it is more efficient since it can prevent reallocation of internal buffers)
     pub fn build_buffer(&self, buffer: &mut ObjectBuilder2d) -> Option<BufferedObject2d> {
-        let index_buffer: WebGlBuffer = self.gl.create_buffer().expect("Failed to create buffer");
-        buffer_u16_indexes(&self.gl, &index_buffer, &buffer.indexes);
-
-        let position_buffer = self.gl.create_buffer().expect("Failed to create buffer");
-        buffer_f32_slice(&self.gl, &position_buffer, &buffer.positions);
-
-        let color_buffer = self.gl.create_buffer().expect("Failed to create buffer");
-        buffer_f32_slice(&self.gl, &color_buffer, &buffer.colors);
+        if buffer.textures.is_empty() {
+            return None;
+        }
 
-        let texcoord_buffer = self.gl.create_buffer().expect("Failed to create buffer");
-        buffer_f32_slice(&self.gl, &texcoord_buffer, &buffer.texcoords);
+        let (index_buffer, vertex_buffer) = self.upload_vertices(&buffer.indexes, &buffer.vertices);
 
         let count = u16::try_from(buffer.indexes.len())
             .expect("Error, buffers are limited to 65536 vertices");
 
         buffer.index_counter = 0;
         buffer.indexes.clear();
-        buffer.positions.clear();
-        buffer.colors.clear();
-        buffer.texcoords.clear();
+        buffer.vertices.clear();
 
-        buffer.texture.take().map(|texture| BufferedObject2d {
+        Some(BufferedObject2d {
             count,
             index_buffer,
-            position_buffer,
-            color_buffer,
-            texcoord_buffer,
-            texture,
+            vertex_buffer,
+            textures: buffer
+                .textures
+                .drain(..)
+                .map(|texture| texture.webgl().clone())
+                .collect(),
         })
     }
 
+    /// Free the GPU memory of a buffer, it must not be drawn anymore
+    pub fn delete_buffer(&self, buffer: &BufferedObject2d) {
+        self.gl.delete_buffer(Some(&buffer.index_buffer));
+        self.gl.delete_buffer(Some(&buffer.vertex_buffer));
+    }
+
+    /// Upload indexes and interleaved vertices to new buffers
+    fn upload_vertices<V: Vertex>(
+        &self,
+        indexes: &[u16],
+        vertices: &[V],
+    ) -> (WebGlBuffer, WebGlBuffer) {
+        let index_buffer = self.gl.create_buffer().expect("Failed to create buffer");
+        buffer_u16_indexes(&self.gl, &index_buffer, indexes);
+
+        let data = interleave(vertices);
+
+        let vertex_buffer = self.gl.create_buffer().expect("Failed to create buffer");
+        buffer_f32_slice(&self.gl, &vertex_buffer, &data);
+
+        self.update_stats(|stats| {
+            stats.buffer_uploads += 2;
+            stats.uploaded_bytes += (indexes.len() * 2 + data.len() * 4) as u32;
+        });
+
+        (index_buffer, vertex_buffer)
+    }
+
+    /// Point the attributes at the given locations to an interleaved vertex buffer, in the order of `V::ATTRIBUTES`.
+    /// Negative locations are attributes the shader doesn't use
+    fn set_vertex_layout<V: Vertex>(&self, buffer: &WebGlBuffer, locations: &[i32]) {
+        self.gl
+            .bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(buffer));
+
+        let stride = stride::<V>();
+        let mut offset = 0;
+
+        for (attribute, &location) in V::ATTRIBUTES.iter().zip(locations) {
+            if location >= 0 {
+                self.gl.vertex_attrib_pointer_with_i32(
+                    location as u32,
+                    attribute.size,
+                    WebGl2RenderingContext::FLOAT,
+                    false,
+                    stride,
+                    offset,
+                );
+            }
+
+            offset += attribute.size * 4;
+        }
+    }
+
     /// Set the view matrix of this context, this is used to convert from world coordinates to opengl coordinates
     pub fn set_view_matrix(&mut self, view_matrix: Mat3) {
         self.flush();
         self.view_matrix = view_matrix;
     }
 
+    /// Get the current view matrix
+    #[must_use]
+    pub const fn view_matrix(&self) -> Mat3 {
+        self.view_matrix
+    }
+
+    /// Set how the next draw calls are blended with the canvas content
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        if blend_mode == self.blend_mode {
+            return;
+        }
+
+        self.flush();
+        self.blend_mode = blend_mode;
+
+        // Colors are premultiplied by the fragment shader
+        let (source, destination) = match blend_mode {
+            BlendMode::Alpha => (
+                WebGl2RenderingContext::ONE,
+                WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
+            ),
+            BlendMode::Additive => (WebGl2RenderingContext::ONE, WebGl2RenderingContext::ONE),
+            BlendMode::Multiply => (
+                WebGl2RenderingContext::DST_COLOR,
+                WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
+            ),
+        };
+
+        self.gl.blend_func(source, destination);
+    }
+
+    /// Get the current blend mode
+    #[must_use]
+    pub const fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Set the view matrix from the given camera
+    pub fn set_camera(&mut self, camera: &Camera2d) {
+        let view_matrix = camera.view_matrix(self.viewport_size());
+        self.set_view_matrix(view_matrix);
+    }
+
+    /// Restrict drawing and clearing to the given viewport and use its view matrix
+    pub fn set_viewport(&mut self, viewport: &Viewport2d) {
+        self.flush();
+        self.viewport = *viewport;
+        self.view_matrix = viewport.view_matrix;
+        self.apply_viewport();
+    }
+
+    /// Draw on the whole drawing surface again, the view matrix is kept
+    pub fn reset_viewport(&mut self) {
+        self.flush();
+        self.viewport = Viewport2d::FULL;
+        self.apply_viewport();
+    }
+
+    /// Get the size in pixels of the current viewport
+    #[must_use]
+    pub fn viewport_size(&self) -> UVec2 {
+        self.viewport.pixel_rect(self.size()).1
+    }
+
+    fn apply_viewport(&self) {
+        // Render targets are always drawn on entirely
+        if let Some(target) = &self.render_target {
+            self.set_gl_viewport(IVec2::ZERO, target.size);
+            return;
+        }
+
+        let (position, size) = self.viewport.pixel_rect(self.size());
+
+        self.set_gl_viewport(position, size);
+    }
+
+    fn set_gl_viewport(&self, position: IVec2, size: UVec2) {
+        self.gl
+            .viewport(position.x, position.y, size.x as i32, size.y as i32);
+        self.gl
+            .scissor(position.x, position.y, size.x as i32, size.y as i32);
+    }
+
+    /// Get the size of the drawing surface in pixels, this is the virtual resolution when one is set
+    #[must_use]
+    pub fn size(&self) -> UVec2 {
+        self.virtual_screen
+            .as_ref()
+            .map_or_else(|| self.canvas_size(), |screen| screen.target.size)
+    }
+
+    fn canvas_size(&self) -> UVec2 {
+        UVec2::new(self.canvas.width(), self.canvas.height())
+    }
+
     /// Set the view matrix so that world coordinates corresponds to pixels on the canvas
     pub fn pixel_perfect_view(&mut self) {
+        let size = self.viewport_size().as_vec2();
+
         self.view_matrix = Mat3::from_cols(
-            Vec3::new(2. / self.canvas.width() as f32, 0., 0.),
-            Vec3::new(0., 2. / self.canvas.height() as f32, 0.),
+            Vec3::new(2. / size.x, 0., 0.),
+            Vec3::new(0., 2. / size.y, 0.),
             Vec3::new(-1., -1., 1.),
         );
     }
@@ -537,7 +1126,8 @@ impl Canvas2d {
     /// Set the view matrix to a camera centered at `cam_pos` which can see at a distance `view_radius` on the left and right.
     /// Height view distance is adjusted so that there is no stretch on the vertical axis
     pub fn camera_view(&mut self, cam_pos: Vec2, view_radius: f32) {
-        let height_factor = self.canvas.width() as f32 / self.canvas.height() as f32;
+        let size = self.viewport_size().as_vec2();
+        let height_factor = size.x / size.y;
 
         self.view_matrix = Mat3::from_cols(
             Vec3::new(1. / view_radius, 0., 0.),
@@ -550,40 +1140,274 @@ impl Canvas2d {
         );
     }
 
+    /// Matrix converting from screen coordinates (top left origin, in pixels) to opengl coordinates of the given viewport
+    fn screen_to_ogl_matrix(&self, viewport: &Viewport2d) -> Mat3 {
+        let (surface_offset, surface_size) = self.presentation_rect();
+
+        let (viewport_position, viewport_size) = viewport.pixel_rect(self.size());
+        let scale = surface_size / self.size().as_vec2();
+
+        let offset = surface_offset + viewport_position.as_vec2() * scale;
+        let size = viewport_size.as_vec2() * scale;
+
+        let screen_height = self.canvas.height() as f32;
+        let top = screen_height - offset.y - size.y;
+
+        Mat3::from_cols(
+            Vec3::new(2. / size.x, 0., 0.),
+            Vec3::new(0., -2. / size.y, 0.),
+            Vec3::new(-1. - 2. * offset.x / size.x, 1. + 2. * top / size.y, 1.),
+        )
+    }
+
     /// Computes the world coordinates corresponding to the given screen coordinates with the current view matrix
     #[must_use]
     pub fn screen_to_world_pos(&self, screen_pos: Vec2) -> Vec2 {
-        let screen_to_ogl_matrix = Mat3::from_cols(
-            Vec3::new(2. / self.canvas.width() as f32, 0., 0.),
-            Vec3::new(0., -2. / self.canvas.height() as f32, 0.),
-            Vec3::new(-1., 1., 1.),
-        );
-
-        self.view_matrix
-            .inverse()
-            .transform_point2(screen_to_ogl_matrix.transform_point2(screen_pos))
+        self.view_matrix.inverse().transform_point2(
+            self.screen_to_ogl_matrix(&self.viewport)
+                .transform_point2(screen_pos),
+        )
     }
 
     /// Computes the screen coordinates corresponding to the given world coordinates with the current view matrix
     #[must_use]
     pub fn world_to_screen_pos(&self, world_pos: Vec2) -> Vec2 {
-        let screen_to_ogl_matrix = Mat3::from_cols(
-            Vec3::new(2. / self.canvas.width() as f32, 0., 0.),
-            Vec3::new(0., -2. / self.canvas.height() as f32, 0.),
-            Vec3::new(-1., 1., 1.),
-        );
-
-        screen_to_ogl_matrix
+        self.screen_to_ogl_matrix(&self.viewport)
             .inverse()
             .transform_point2(self.view_matrix.transform_point2(world_pos))
     }
 
+    /// Find the viewport displayed at the given screen coordinates.
+    /// Later viewports are considered on top of earlier ones, as they would be when drawn in order
+    #[must_use]
+    pub fn viewport_at(&self, viewports: &[Viewport2d], screen_pos: Vec2) -> Option<usize> {
+        let ogl_pos = self
+            .screen_to_ogl_matrix(&Viewport2d::FULL)
+            .transform_point2(screen_pos);
+        let surface_pos = (ogl_pos + 1.) / 2.;
+
+        viewports
+            .iter()
+            .rposition(|viewport| viewport.contains(surface_pos))
+    }
+
+    /// Computes the world coordinates corresponding to the given screen coordinates using the view matrix of the viewport displayed there.
+    /// Returns the index of this viewport with the world coordinates, or None if no viewport is displayed there
+    #[must_use]
+    pub fn viewport_screen_to_world_pos(
+        &self,
+        viewports: &[Viewport2d],
+        screen_pos: Vec2,
+    ) -> Option<(usize, Vec2)> {
+        self.viewport_at(viewports, screen_pos).map(|index| {
+            let viewport = &viewports[index];
+
+            (
+                index,
+                viewport.view_matrix.inverse().transform_point2(
+                    self.screen_to_ogl_matrix(viewport)
+                        .transform_point2(screen_pos),
+                ),
+            )
+        })
+    }
+
+    /// Render at a fixed resolution, the result is scaled to fit the canvas while preserving aspect ratio.
+    /// Remaining space is filled with black bars, with `integer_scaling` the scale factor is rounded down to an integer.
+    /// `present` must be called at the end of each frame to display the result
+    pub fn set_virtual_resolution(&mut self, size: UVec2, integer_scaling: bool) {
+        self.flush();
+        self.clear_virtual_resolution();
+
+        let target = self.internal_create_render_target(size, WebGl2RenderingContext::NEAREST);
+
+        self.virtual_screen = Some(VirtualScreen {
+            integer_scaling,
+            target,
+        });
+
+        self.bind_current_framebuffer();
+        self.apply_viewport();
+    }
+
+    /// Go back to rendering directly on the canvas at its full resolution
+    pub fn clear_virtual_resolution(&mut self) {
+        self.flush();
+
+        if let Some(screen) = self.virtual_screen.take() {
+            self.bind_current_framebuffer();
+            self.delete_render_target(&screen.target);
+
+            self.apply_viewport();
+        }
+    }
+
+    /// Position (from the bottom left corner) and size in pixels of the area of the canvas where the drawing surface is displayed
+    #[must_use]
+    pub fn presentation_rect(&self) -> (Vec2, Vec2) {
+        let canvas_size = self.canvas_size().as_vec2();
+
+        let Some(screen) = &self.virtual_screen else {
+            return (Vec2::ZERO, canvas_size);
+        };
+
+        let virtual_size = screen.target.size.as_vec2();
+
+        let mut scale = (canvas_size / virtual_size).min_element();
+
+        if screen.integer_scaling {
+            scale = scale.floor().max(1.);
+        }
+
+        let size = virtual_size * scale;
+        let offset = ((canvas_size - size) / 2.).floor();
+
+        (offset, size)
+    }
+
+    /// Display the virtual resolution drawing surface on the canvas, this should be called at the end of each frame in place of `flush`.
+    /// Without a virtual resolution this is the same as `flush`
+    pub fn present(&mut self) {
+        self.flush();
+
+        let Some(screen) = &self.virtual_screen else {
+            return;
+        };
+
+        let texture = screen.target.texture.clone();
+
+        let (offset, size) = self.presentation_rect();
+        let canvas_size = self.canvas_size();
+
+        self.gl
+            .bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, None);
+        self.set_gl_viewport(IVec2::ZERO, canvas_size);
+        self.clear(Vec4::new(0., 0., 0., 1.));
+
+        self.set_gl_viewport(offset.as_ivec2(), size.as_uvec2());
+
+        let view_matrix = std::mem::replace(&mut self.view_matrix, Mat3::IDENTITY);
+        let blend_mode = self.blend_mode;
+
+        self.set_blend_mode(BlendMode::Alpha);
+        self.draw_rect(Vec2::NEG_ONE, Vec2::splat(2.), Vec4::ONE, &texture);
+        self.flush();
+
+        self.set_blend_mode(blend_mode);
+        self.view_matrix = view_matrix;
+
+        self.bind_current_framebuffer();
+        self.apply_viewport();
+    }
+
+    /// Create an offscreen drawing surface with linear filtering
+    #[must_use]
+    pub fn create_render_target(&self, size: UVec2) -> RenderTarget2d {
+        self.internal_create_render_target(size, WebGl2RenderingContext::LINEAR)
+    }
+
+    /// Free the GPU memory used by a render target, it must not be the current one
+    pub fn delete_render_target(&self, target: &RenderTarget2d) {
+        self.gl.delete_framebuffer(Some(&target.framebuffer));
+        self.delete_texture(&target.texture);
+    }
+
+    /// Draw on the given render target instead of the canvas, None goes back to the canvas.
+    /// The view matrix is kept and the whole target is drawn on regardless of the viewport
+    pub fn set_render_target(&mut self, target: Option<&RenderTarget2d>) {
+        self.flush();
+        self.render_target = target.cloned();
+        self.bind_current_framebuffer();
+        self.apply_viewport();
+    }
+
+    fn bind_current_framebuffer(&self) {
+        let framebuffer = self.render_target.as_ref().map_or_else(
+            || {
+                self.virtual_screen
+                    .as_ref()
+                    .map(|screen| &screen.target.framebuffer)
+            },
+            |target| Some(&target.framebuffer),
+        );
+
+        self.gl
+            .bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, framebuffer);
+    }
+
+    fn internal_create_render_target(&self, size: UVec2, filter: u32) -> RenderTarget2d {
+        let texture = self.gl.create_texture().expect("Can't create texture");
+        self.update_stats(|stats| stats.textures += 1);
+
+        self.gl
+            .bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+
+        for (parameter, value) in [
+            (WebGl2RenderingContext::TEXTURE_MIN_FILTER, filter),
+            (WebGl2RenderingContext::TEXTURE_MAG_FILTER, filter),
+            (
+                WebGl2RenderingContext::TEXTURE_WRAP_S,
+                WebGl2RenderingContext::CLAMP_TO_EDGE,
+            ),
+            (
+                WebGl2RenderingContext::TEXTURE_WRAP_T,
+                WebGl2RenderingContext::CLAMP_TO_EDGE,
+            ),
+        ] {
+            self.gl
+                .tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, parameter, value as i32);
+        }
+
+        self.gl
+            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                WebGl2RenderingContext::TEXTURE_2D,
+                0,
+                WebGl2RenderingContext::RGBA as i32,
+                size.x as i32,
+                size.y as i32,
+                0,
+                WebGl2RenderingContext::RGBA,
+                WebGl2RenderingContext::UNSIGNED_BYTE,
+                None,
+            )
+            .expect("Can't allocate texture");
+
+        let framebuffer = self
+            .gl
+            .create_framebuffer()
+            .expect("Can't create framebuffer");
+        self.gl
+            .bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, Some(&framebuffer));
+        self.gl.framebuffer_texture_2d(
+            WebGl2RenderingContext::FRAMEBUFFER,
+            WebGl2RenderingContext::COLOR_ATTACHMENT0,
+            WebGl2RenderingContext::TEXTURE_2D,
+            Some(&texture),
+            0,
+        );
+
+        self.bind_current_framebuffer();
+
+        RenderTarget2d {
+            size,
+            framebuffer,
+            // Framebuffer textures are stored bottom row first, unlike uploaded images
+            texture: TextureRect {
+                texture: Texture::WebGl(texture),
+                position: Vec2::new(0., 1.),
+                size: Vec2::new(1., -1.),
+            },
+        }
+    }
+
     /// Upload the given image to GPU and return a texture rect on it
     #[must_use]
     pub fn create_texture(&self, image: &ImageBitmap) -> TextureRect {
-        let webgl_texture = self.gl.create_texture().expect("Can't create texture");
+        let texture = self.gl.create_texture().expect("Can't create texture");
+        self.update_stats(|stats| stats.textures += 1);
+
         self.gl
-            .bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&webgl_texture));
+            .bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
 
         self.gl.tex_parameteri(
             WebGl2RenderingContext::TEXTURE_2D,
@@ -619,10 +1443,92 @@ impl Canvas2d {
             )
             .expect("Can't upload image to gpu");
 
-        TextureRect::new(webgl_texture)
+        TextureRect::new(Texture::WebGl(texture))
+    }
+
+    /// Create a texture showing the current frame of a video, keep it updated with `update_video_texture`
+    #[must_use]
+    pub fn create_video_texture(&self, video: &Video) -> TextureRect {
+        let texture = self.gl.create_texture().expect("Can't create texture");
+        self.update_stats(|stats| stats.textures += 1);
+
+        self.gl
+            .bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+
+        for (parameter, value) in [
+            (
+                WebGl2RenderingContext::TEXTURE_MIN_FILTER,
+                WebGl2RenderingContext::LINEAR,
+            ),
+            (
+                WebGl2RenderingContext::TEXTURE_MAG_FILTER,
+                WebGl2RenderingContext::LINEAR,
+            ),
+            (
+                WebGl2RenderingContext::TEXTURE_WRAP_S,
+                WebGl2RenderingContext::CLAMP_TO_EDGE,
+            ),
+            (
+                WebGl2RenderingContext::TEXTURE_WRAP_T,
+                WebGl2RenderingContext::CLAMP_TO_EDGE,
+            ),
+        ] {
+            self.gl
+                .tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, parameter, value as i32);
+        }
+
+        video.take_new_frame();
+        self.upload_video_frame(video);
+
+        TextureRect::new(Texture::WebGl(texture))
     }
 
-    /// Clear canvas with the given color
+    /// Upload the current frame of a video to its texture, this should be called each frame before drawing it.
+    /// Nothing is uploaded if the frame didn't change since the last call, a video should only be shown by one texture
+    pub fn update_video_texture(&mut self, texture: &TextureRect, video: &Video) {
+        if !video.take_new_frame() {
+            return;
+        }
+
+        // Pending draws must keep the previous frame
+        self.flush();
+
+        self.gl.bind_texture(
+            WebGl2RenderingContext::TEXTURE_2D,
+            Some(texture.texture.webgl()),
+        );
+
+        self.upload_video_frame(video);
+    }
+
+    /// Upload the current frame of a video to the bound texture, it stays transparent until the video has data
+    fn upload_video_frame(&self, video: &Video) {
+        let uploaded = self
+            .gl
+            .tex_image_2d_with_u32_and_u32_and_html_video_element(
+                WebGl2RenderingContext::TEXTURE_2D,
+                0,
+                WebGl2RenderingContext::RGBA as i32,
+                WebGl2RenderingContext::RGBA,
+                WebGl2RenderingContext::UNSIGNED_BYTE,
+                video.element(),
+            )
+            .is_ok();
+
+        if uploaded {
+            self.update_stats(|stats| {
+                stats.uploaded_bytes += video.width() * video.height() * 4;
+            });
+        }
+    }
+
+    /// Free the GPU memory of a texture created by `create_texture`, it must not be drawn anymore
+    pub fn delete_texture(&self, texture: &TextureRect) {
+        self.gl.delete_texture(Some(texture.texture.webgl()));
+        self.update_stats(|stats| stats.textures = stats.textures.saturating_sub(1));
+    }
+
+    /// Clear the current viewport with the given color
     pub fn clear(&self, color: Vec4) {
         self.gl.clear_color(color.x, color.y, color.z, color.w);
         self.gl.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
@@ -634,12 +1540,29 @@ impl Canvas2d {
             self.canvas.set_width(size.x);
             self.canvas.set_height(size.y);
 
-            self.gl.viewport(0, 0, size.x as i32, size.y as i32);
+            if self.virtual_screen.is_none() && self.render_target.is_none() {
+                self.apply_viewport();
+            }
         }
     }
 
-    /// Set the size of the canvas to the window
+    /// Get a video stream of the canvas content, capturing up to the given frames per second.
+    /// None if the canvas isn't shown on the page, like when created offscreen or in a worker
+    #[must_use]
+    pub fn capture_stream(&self, frame_rate: f64) -> Option<MediaStream> {
+        self.element
+            .as_ref()?
+            .capture_stream_with_frame_request_rate(frame_rate)
+            .ok()
+    }
+
+    /// Set the size of the canvas to the window, in a worker to the size sent by the page
     pub fn fit_screen(&self) {
+        if is_worker() {
+            self.set_size(worker::screen_size());
+            return;
+        }
+
         let window = window();
 
         self.set_size(UVec2::new(
@@ -648,37 +1571,243 @@ impl Canvas2d {
         ));
     }
 
+    /// Fill a polygon with a gradient computed for every pixel, it must not intersect itself.
+    /// This is smoother than `draw_gradient_polygon` for radial gradients but isn't batched, only the first 8 stops are used
+    pub fn fill_gradient(&mut self, points: &[Vec2], gradient: &Gradient, texture: &TextureRect) {
+        self.flush();
+
+        let mut builder = ObjectBuilder2d::new();
+        draw_triangles(
+            &mut builder,
+            points,
+            &triangulate(points),
+            points,
+            |_| Vec4::ONE,
+            texture,
+        );
+
+        let Some(buffer) = self.build_buffer(&mut builder) else {
+            return;
+        };
+
+        let program = &self.gradient_program;
+
+        self.gl.use_program(Some(&program.program));
+
+        let (radial, start, end) = match gradient.shape {
+            GradientShape::Linear { start, end } => (false, start, end),
+            GradientShape::Radial { center, radius } => (true, center, Vec2::new(radius, 0.)),
+        };
+
+        let stops = &gradient.stops()[..gradient.stops().len().min(MAX_SHADER_STOPS)];
+
+        let offsets = stops.iter().map(|(offset, _)| *offset).collect::<Vec<_>>();
+        let colors = stops
+            .iter()
+            .flat_map(|(_, color)| color.to_array())
+            .collect::<Vec<_>>();
+
+        self.gl.uniform1i(Some(&program.radial), radial.into());
+        self.gl.uniform2f(Some(&program.start), start.x, start.y);
+        self.gl.uniform2f(Some(&program.end), end.x, end.y);
+        self.gl
+            .uniform1i(Some(&program.stop_count), stops.len() as i32);
+        self.gl
+            .uniform1fv_with_f32_array(Some(&program.stop_offsets), &offsets);
+        self.gl
+            .uniform4fv_with_f32_array(Some(&program.stop_colors), &colors);
+
+        self.draw_buffer_with(&buffer, &program.view_matrix);
+        self.delete_buffer(&buffer);
+
+        self.gl.use_program(Some(&self.program));
+    }
+
+    /// Fill a rectangle with a gradient computed for every pixel, see `fill_gradient`
+    pub fn fill_gradient_rect(
+        &mut self,
+        position: Vec2,
+        size: Vec2,
+        gradient: &Gradient,
+        texture: &TextureRect,
+    ) {
+        self.fill_gradient(&rect_points(position, size), gradient, texture);
+    }
+
+    /// Compile a shader drawing vertices of type `V`, the attributes of the vertex shader are matched by name with `V::ATTRIBUTES`.
+    /// The optional `uViewMatrix` mat3 uniform receives the view matrix and the `uTexture` sampler the drawn texture
+    ///
+    /// # Panics
+    ///
+    /// Panics if the shaders don't compile or link, with the error log of the driver
+    #[must_use]
+    pub fn create_shader<V: Vertex>(
+        &self,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Shader2d<V> {
+        let vert_shader = compile_shader(
+            &self.gl,
+            WebGl2RenderingContext::VERTEX_SHADER,
+            vertex_source,
+        );
+        let frag_shader = compile_shader(
+            &self.gl,
+            WebGl2RenderingContext::FRAGMENT_SHADER,
+            fragment_source,
+        );
+
+        let program = link_program(&self.gl, &vert_shader, &frag_shader);
+
+        self.gl.use_program(Some(&program));
+
+        if let Some(texture) = self.gl.get_uniform_location(&program, "uTexture") {
+            self.gl.uniform1i(Some(&texture), 0);
+        }
+
+        let shader = Shader2d {
+            attribute_locations: V::ATTRIBUTES
+                .iter()
+                .map(|attribute| self.gl.get_attrib_location(&program, attribute.name))
+                .collect(),
+            view_matrix: self.gl.get_uniform_location(&program, "uViewMatrix"),
+            program,
+            vertex: PhantomData,
+        };
+
+        self.gl.use_program(Some(&self.program));
+
+        shader
+    }
+
+    /// Set a float, vec2, vec3 or vec4 uniform of a shader, it keeps its value for the next draws
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are not between 1 and 4 values
+    pub fn set_shader_uniform<V: Vertex>(&self, shader: &Shader2d<V>, name: &str, values: &[f32]) {
+        let Some(location) = self.gl.get_uniform_location(&shader.program, name) else {
+            return;
+        };
+
+        self.gl.use_program(Some(&shader.program));
+
+        match values.len() {
+            1 => self.gl.uniform1fv_with_f32_array(Some(&location), values),
+            2 => self.gl.uniform2fv_with_f32_array(Some(&location), values),
+            3 => self.gl.uniform3fv_with_f32_array(Some(&location), values),
+            4 => self.gl.uniform4fv_with_f32_array(Some(&location), values),
+            _ => panic!("Error, uniforms have between 1 and 4 floats"),
+        }
+
+        self.gl.use_program(Some(&self.program));
+    }
+
+    /// Draw triangles with a user shader, pending draws are flushed first since this isn't batched
+    pub fn draw_with_shader<V: Vertex>(
+        &mut self,
+        shader: &Shader2d<V>,
+        indexes: &[u16],
+        vertices: &[V],
+        texture: &Texture,
+    ) {
+        self.flush();
+
+        if indexes.is_empty() {
+            return;
+        }
+
+        let (index_buffer, vertex_buffer) = self.upload_vertices(indexes, vertices);
+
+        self.gl.use_program(Some(&shader.program));
+
+        for &location in &shader.attribute_locations {
+            if location >= 0 {
+                self.gl.enable_vertex_attrib_array(location as u32);
+            }
+        }
+
+        self.set_vertex_layout::<V>(&vertex_buffer, &shader.attribute_locations);
+
+        self.gl.bind_buffer(
+            WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
+            Some(&index_buffer),
+        );
+
+        if let Some(location) = &shader.view_matrix {
+            self.gl.uniform_matrix3fv_with_f32_array(
+                Some(location),
+                false,
+                &self.view_matrix.to_cols_array(),
+            );
+        }
+
+        self.gl
+            .bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(texture.webgl()));
+
+        let mut last_textures = self.last_textures.borrow_mut();
+
+        if let Some(last) = last_textures.first_mut() {
+            *last = texture.webgl().clone();
+        } else {
+            last_textures.push(texture.webgl().clone());
+        }
+
+        drop(last_textures);
+
+        self.gl.draw_elements_with_i32(
+            WebGl2RenderingContext::TRIANGLES,
+            indexes.len() as i32,
+            WebGl2RenderingContext::UNSIGNED_SHORT,
+            0,
+        );
+
+        self.update_stats(|stats| {
+            stats.draw_calls += 1;
+            stats.vertices += indexes.len() as u32;
+        });
+
+        // Locations used by the built-in shader stay enabled, it doesn't enable them again
+        let built_in = [
+            self.position_attribute_location,
+            self.color_attribute_location,
+            self.texcoord_attribute_location,
+            self.texture_attribute_location,
+        ];
+
+        for &location in &shader.attribute_locations {
+            if location >= 0 && !built_in.contains(&location) {
+                self.gl.disable_vertex_attrib_array(location as u32);
+            }
+        }
+
+        self.gl.delete_buffer(Some(&index_buffer));
+        self.gl.delete_buffer(Some(&vertex_buffer));
+
+        self.gl.use_program(Some(&self.program));
+    }
+
     /// Flush the internal draw buffers, this should be called after drawing each frame to ensure changes are displayed
     pub fn flush(&mut self) {
         if let Some(buffer) = self.build_buffer(&mut self.direct_draw_builder.borrow_mut()) {
             self.draw_buffer(&buffer);
+            self.delete_buffer(&buffer);
+            self.update_stats(|stats| stats.flushes += 1);
         }
     }
 }
 
 impl DrawTarget2d for Canvas2d {
-    fn draw_raw(
-        &mut self,
-        indexes: &[u16],
-        positions: &[f32],
-        colors: &[f32],
-        texcoords: &[f32],
-        texture: &WebGlTexture,
-    ) {
-        // Flush when point count exceeds an u16 or when switching texture
+    fn draw_vertices(&mut self, indexes: &[u16], vertices: &[Vertex2d], texture: &Texture) {
+        // Flush when point count exceeds an u16 or when the batch can't take another texture
         if self.direct_draw_builder.borrow().indexes.len() + indexes.len() > u16::MAX as usize
-            || self
-                .direct_draw_builder
-                .borrow()
-                .texture
-                .as_ref()
-                .is_some_and(|tex| !Object::is(texture, tex))
+            || !self.direct_draw_builder.borrow().can_batch(texture)
         {
             self.flush();
         }
 
         self.direct_draw_builder
             .borrow_mut()
-            .draw_raw(indexes, positions, colors, texcoords, texture);
+            .draw_vertices(indexes, vertices, texture);
     }
 }
@@ -1,17 +1,20 @@
 #version 300 es
         
-in vec2 aPosition;
-in vec2 aTexcoord;
-in vec4 aColor;
+layout(location = 0) in vec2 aPosition;
+layout(location = 1) in vec2 aTexcoord;
+layout(location = 2) in vec4 aColor;
+layout(location = 3) in float aTexture;
 
 uniform mat3 uViewMatrix;
 
 out vec4 vColor;
 out vec2 vTexcoord;
+flat out int vTexture;
 
 void main() {
     gl_Position = vec4((uViewMatrix * vec3(aPosition, 1.)).xy, 1., 1.);
 
     vColor = aColor;
     vTexcoord = aTexcoord;
+    vTexture = int(aTexture + 0.5);
 }
@@ -0,0 +1,49 @@
+#version 300 es
+
+precision highp float;
+
+in vec3 vWorldPosition;
+in vec3 vNormal;
+in vec2 vTexcoord;
+
+uniform sampler2D uTexture;
+uniform vec4 uColor;
+uniform bool uLit;
+uniform float uSpecular;
+uniform float uShininess;
+
+uniform vec3 uCameraPosition;
+uniform vec3 uLightDirection;
+uniform vec3 uLightColor;
+uniform vec3 uAmbient;
+
+out vec4 outColor;
+
+void main() {
+    vec4 base = texture(uTexture, vTexcoord) * uColor;
+    vec3 color = base.rgb;
+
+    if (uLit) {
+        vec3 normal = normalize(vNormal);
+
+        // Back faces of double sided materials are lit from their side
+        if (!gl_FrontFacing) {
+            normal = -normal;
+        }
+
+        vec3 toLight = normalize(-uLightDirection);
+        vec3 toCamera = normalize(uCameraPosition - vWorldPosition);
+
+        float diffuse = max(dot(normal, toLight), 0.);
+        float specular = 0.;
+
+        if (diffuse > 0.) {
+            vec3 halfway = normalize(toLight + toCamera);
+            specular = pow(max(dot(normal, halfway), 0.), uShininess) * uSpecular;
+        }
+
+        color = base.rgb * (uAmbient + uLightColor * diffuse) + uLightColor * specular;
+    }
+
+    outColor = vec4(color * base.a, base.a);
+}
@@ -0,0 +1,456 @@
+use super::{
+    camera3d::Camera3d,
+    canvas2d::Texture,
+    mesh::MeshData,
+    webgl_util::{buffer_f32_slice, buffer_u32_indexes, compile_shader, link_program},
+};
+use glam::{Mat3, Mat4, UVec2, Vec2, Vec3, Vec4};
+use wasm_bindgen::JsCast;
+use web_sys::{
+    HtmlCanvasElement, ImageBitmap, OffscreenCanvas, WebGl2RenderingContext, WebGlBuffer,
+    WebGlContextAttributes, WebGlProgram, WebGlUniformLocation,
+};
+
+/// A mesh uploaded to the GPU, see `Canvas3d::create_mesh`
+pub struct Mesh {
+    count: i32,
+    index_buffer: WebGlBuffer,
+    position_buffer: WebGlBuffer,
+    normal_buffer: WebGlBuffer,
+    texcoord_buffer: WebGlBuffer,
+}
+
+/// How the surface of a mesh is colored
+#[derive(Clone)]
+pub struct Material {
+    /// Multiplied with the texture
+    pub color: Vec4,
+    /// Drawn white when None
+    pub texture: Option<Texture>,
+    /// When false the light is ignored and the surface has its plain color
+    pub lit: bool,
+    /// Intensity of the highlights
+    pub specular: f32,
+    /// Sharpness of the highlights
+    pub shininess: f32,
+    /// Draw the back faces of triangles too
+    pub double_sided: bool,
+}
+
+impl Material {
+    /// A lit material without highlights
+    #[must_use]
+    pub const fn new(color: Vec4, texture: Option<Texture>) -> Self {
+        Self {
+            color,
+            texture,
+            lit: true,
+            specular: 0.,
+            shininess: 32.,
+            double_sided: false,
+        }
+    }
+}
+
+/// Light coming from far away in a single direction, like the sun
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DirectionalLight {
+    /// Direction the light travels in
+    pub direction: Vec3,
+    pub color: Vec3,
+    /// Light reaching every surface, even the ones facing away
+    pub ambient: Vec3,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::new(-0.3, -1., -0.5),
+            color: Vec3::splat(0.8),
+            ambient: Vec3::splat(0.2),
+        }
+    }
+}
+
+struct MeshProgram {
+    program: WebGlProgram,
+    position_location: u32,
+    normal_location: u32,
+    texcoord_location: u32,
+    model_matrix: WebGlUniformLocation,
+    normal_matrix: WebGlUniformLocation,
+    view_projection: WebGlUniformLocation,
+    color: WebGlUniformLocation,
+    lit: WebGlUniformLocation,
+    specular: WebGlUniformLocation,
+    shininess: WebGlUniformLocation,
+    camera_position: WebGlUniformLocation,
+    light_direction: WebGlUniformLocation,
+    light_color: WebGlUniformLocation,
+    ambient: WebGlUniformLocation,
+}
+
+impl MeshProgram {
+    fn new(gl: &WebGl2RenderingContext) -> Self {
+        let vert_shader = compile_shader(
+            gl,
+            WebGl2RenderingContext::VERTEX_SHADER,
+            include_str!("canvas3d.vert"),
+        );
+
+        let frag_shader = compile_shader(
+            gl,
+            WebGl2RenderingContext::FRAGMENT_SHADER,
+            include_str!("canvas3d.frag"),
+        );
+
+        let program = link_program(gl, &vert_shader, &frag_shader);
+
+        let location = |name: &str| {
+            gl.get_uniform_location(&program, name)
+                .unwrap_or_else(|| panic!("Can't get {name} location"))
+        };
+
+        let attribute = |name: &str| gl.get_attrib_location(&program, name) as u32;
+
+        gl.use_program(Some(&program));
+        gl.uniform1i(Some(&location("uTexture")), 0);
+
+        Self {
+            position_location: attribute("aPosition"),
+            normal_location: attribute("aNormal"),
+            texcoord_location: attribute("aTexcoord"),
+            model_matrix: location("uModelMatrix"),
+            normal_matrix: location("uNormalMatrix"),
+            view_projection: location("uViewProjection"),
+            color: location("uColor"),
+            lit: location("uLit"),
+            specular: location("uSpecular"),
+            shininess: location("uShininess"),
+            camera_position: location("uCameraPosition"),
+            light_direction: location("uLightDirection"),
+            light_color: location("uLightColor"),
+            ambient: location("uAmbient"),
+            program,
+        }
+    }
+}
+
+/// A 3d drawing context backed by webgl2 with depth testing and a single directional light.
+/// It uses its own canvas, it can be stacked below or above the one of a `Canvas2d`
+pub struct Canvas3d {
+    canvas: OffscreenCanvas,
+    gl: WebGl2RenderingContext,
+    program: MeshProgram,
+    white_texture: Texture,
+    view_projection: Mat4,
+    camera_position: Vec3,
+    light: DirectionalLight,
+}
+
+impl Canvas3d {
+    #[must_use]
+    pub fn new(canvas: &HtmlCanvasElement) -> Self {
+        let canvas = canvas.transfer_control_to_offscreen().unwrap();
+
+        Self::internal_new(canvas)
+    }
+
+    #[must_use]
+    pub fn new_offscreen(size: UVec2) -> Self {
+        let canvas = OffscreenCanvas::new(size.x, size.y).unwrap();
+
+        Self::internal_new(canvas)
+    }
+
+    fn internal_new(canvas: OffscreenCanvas) -> Self {
+        let attrs = WebGlContextAttributes::new();
+        attrs.set_antialias(true);
+        attrs.set_alpha(false);
+        attrs.set_depth(true);
+
+        let gl = canvas
+            .get_context_with_context_options("webgl2", &attrs.into())
+            .unwrap()
+            .unwrap()
+            .dyn_into::<WebGl2RenderingContext>()
+            .unwrap();
+
+        let program = MeshProgram::new(&gl);
+
+        let white_texture = gl.create_texture().expect("Can't create texture");
+
+        gl.active_texture(WebGl2RenderingContext::TEXTURE0);
+        gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&white_texture));
+
+        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_u8_array_and_src_offset(
+            WebGl2RenderingContext::TEXTURE_2D,
+            0,
+            WebGl2RenderingContext::RGBA as i32,
+            1,
+            1,
+            0,
+            WebGl2RenderingContext::RGBA,
+            WebGl2RenderingContext::UNSIGNED_BYTE,
+            &[255, 255, 255, 255],
+            0,
+        )
+        .expect("Can't upload data to texture");
+
+        gl.enable(WebGl2RenderingContext::DEPTH_TEST);
+        gl.depth_func(WebGl2RenderingContext::LEQUAL);
+
+        gl.enable(WebGl2RenderingContext::BLEND);
+        gl.blend_func(
+            WebGl2RenderingContext::ONE,
+            WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
+        );
+
+        gl.enable_vertex_attrib_array(program.position_location);
+        gl.enable_vertex_attrib_array(program.normal_location);
+        gl.enable_vertex_attrib_array(program.texcoord_location);
+
+        Self {
+            canvas,
+            gl,
+            program,
+            white_texture: Texture::WebGl(white_texture),
+            view_projection: Mat4::IDENTITY,
+            camera_position: Vec3::ZERO,
+            light: DirectionalLight::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn size(&self) -> UVec2 {
+        UVec2::new(self.canvas.width(), self.canvas.height())
+    }
+
+    /// Resize the canvas, the camera must be set again for the new aspect ratio
+    pub fn set_size(&self, size: UVec2) {
+        if size != self.size() {
+            self.canvas.set_width(size.x);
+            self.canvas.set_height(size.y);
+            self.gl.viewport(0, 0, size.x as i32, size.y as i32);
+        }
+    }
+
+    /// Fill the canvas with the given color and reset the depth
+    pub fn clear(&self, color: Vec4) {
+        self.gl.clear_color(color.x, color.y, color.z, color.w);
+        self.gl.clear(
+            WebGl2RenderingContext::COLOR_BUFFER_BIT | WebGl2RenderingContext::DEPTH_BUFFER_BIT,
+        );
+    }
+
+    /// Draw the next meshes as seen from the camera
+    pub fn set_camera(&mut self, camera: &Camera3d) {
+        self.view_projection = camera.view_projection(self.size());
+        self.camera_position = camera.position;
+    }
+
+    pub const fn set_light(&mut self, light: DirectionalLight) {
+        self.light = light;
+    }
+
+    #[must_use]
+    pub const fn light(&self) -> DirectionalLight {
+        self.light
+    }
+
+    /// Upload the given image to GPU with mipmaps, the texture repeats outside of the [0, 1] coordinates
+    #[must_use]
+    pub fn create_texture(&self, image: &ImageBitmap) -> Texture {
+        let texture = self.gl.create_texture().expect("Can't create texture");
+
+        self.gl
+            .bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+
+        self.gl
+            .tex_image_2d_with_u32_and_u32_and_image_bitmap(
+                WebGl2RenderingContext::TEXTURE_2D,
+                0,
+                WebGl2RenderingContext::RGBA as i32,
+                WebGl2RenderingContext::RGBA,
+                WebGl2RenderingContext::UNSIGNED_BYTE,
+                image,
+            )
+            .expect("Can't upload image to gpu");
+
+        self.gl.generate_mipmap(WebGl2RenderingContext::TEXTURE_2D);
+
+        self.gl.tex_parameteri(
+            WebGl2RenderingContext::TEXTURE_2D,
+            WebGl2RenderingContext::TEXTURE_MIN_FILTER,
+            WebGl2RenderingContext::LINEAR_MIPMAP_LINEAR as i32,
+        );
+
+        self.gl.tex_parameteri(
+            WebGl2RenderingContext::TEXTURE_2D,
+            WebGl2RenderingContext::TEXTURE_MAG_FILTER,
+            WebGl2RenderingContext::LINEAR as i32,
+        );
+
+        Texture::WebGl(texture)
+    }
+
+    /// Free the GPU memory of a texture created by `create_texture`, it must not be drawn anymore
+    pub fn delete_texture(&self, texture: &Texture) {
+        self.gl.delete_texture(Some(texture.webgl()));
+    }
+
+    /// Upload a mesh to the GPU so that it can be drawn many times
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mesh doesn't have as many normals and texture coordinates as positions
+    #[must_use]
+    pub fn create_mesh(&self, data: &MeshData) -> Mesh {
+        assert!(
+            data.normals.len() == data.positions.len()
+                && data.texcoords.len() == data.positions.len(),
+            "Error, mesh attributes have different lengths"
+        );
+
+        let buffer = || self.gl.create_buffer().expect("Can't create buffer");
+
+        let mesh = Mesh {
+            count: data.indexes.len() as i32,
+            index_buffer: buffer(),
+            position_buffer: buffer(),
+            normal_buffer: buffer(),
+            texcoord_buffer: buffer(),
+        };
+
+        buffer_u32_indexes(&self.gl, &mesh.index_buffer, &data.indexes);
+        buffer_f32_slice(
+            &self.gl,
+            &mesh.position_buffer,
+            &data
+                .positions
+                .iter()
+                .flat_map(Vec3::to_array)
+                .collect::<Vec<_>>(),
+        );
+        buffer_f32_slice(
+            &self.gl,
+            &mesh.normal_buffer,
+            &data
+                .normals
+                .iter()
+                .flat_map(Vec3::to_array)
+                .collect::<Vec<_>>(),
+        );
+        buffer_f32_slice(
+            &self.gl,
+            &mesh.texcoord_buffer,
+            &data
+                .texcoords
+                .iter()
+                .flat_map(Vec2::to_array)
+                .collect::<Vec<_>>(),
+        );
+
+        mesh
+    }
+
+    /// Free the GPU memory of a mesh
+    pub fn delete_mesh(&self, mesh: Mesh) {
+        for buffer in [
+            mesh.index_buffer,
+            mesh.position_buffer,
+            mesh.normal_buffer,
+            mesh.texcoord_buffer,
+        ] {
+            self.gl.delete_buffer(Some(&buffer));
+        }
+    }
+
+    /// Draw a mesh placed in the world by the transform, with the current camera and light
+    pub fn draw_mesh(&self, mesh: &Mesh, transform: Mat4, material: &Material) {
+        let program = &self.program;
+
+        self.gl.use_program(Some(&program.program));
+
+        for (buffer, location, size) in [
+            (&mesh.position_buffer, program.position_location, 3),
+            (&mesh.normal_buffer, program.normal_location, 3),
+            (&mesh.texcoord_buffer, program.texcoord_location, 2),
+        ] {
+            self.gl
+                .bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(buffer));
+            self.gl.vertex_attrib_pointer_with_i32(
+                location,
+                size,
+                WebGl2RenderingContext::FLOAT,
+                false,
+                0,
+                0,
+            );
+        }
+
+        self.gl.bind_buffer(
+            WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
+            Some(&mesh.index_buffer),
+        );
+
+        // Normals stay perpendicular to the surface under non uniform scaling
+        let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+
+        self.gl.uniform_matrix4fv_with_f32_array(
+            Some(&program.model_matrix),
+            false,
+            &transform.to_cols_array(),
+        );
+        self.gl.uniform_matrix3fv_with_f32_array(
+            Some(&program.normal_matrix),
+            false,
+            &normal_matrix.to_cols_array(),
+        );
+        self.gl.uniform_matrix4fv_with_f32_array(
+            Some(&program.view_projection),
+            false,
+            &self.view_projection.to_cols_array(),
+        );
+
+        let color = material.color;
+        self.gl
+            .uniform4f(Some(&program.color), color.x, color.y, color.z, color.w);
+        self.gl
+            .uniform1i(Some(&program.lit), i32::from(material.lit));
+        self.gl
+            .uniform1f(Some(&program.specular), material.specular);
+        self.gl
+            .uniform1f(Some(&program.shininess), material.shininess);
+
+        let light = self.light;
+        self.gl.uniform3fv_with_f32_array(
+            Some(&program.camera_position),
+            &self.camera_position.to_array(),
+        );
+        self.gl
+            .uniform3fv_with_f32_array(Some(&program.light_direction), &light.direction.to_array());
+        self.gl
+            .uniform3fv_with_f32_array(Some(&program.light_color), &light.color.to_array());
+        self.gl
+            .uniform3fv_with_f32_array(Some(&program.ambient), &light.ambient.to_array());
+
+        if material.double_sided {
+            self.gl.disable(WebGl2RenderingContext::CULL_FACE);
+        } else {
+            self.gl.enable(WebGl2RenderingContext::CULL_FACE);
+        }
+
+        let texture = material.texture.as_ref().unwrap_or(&self.white_texture);
+
+        self.gl
+            .bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(texture.webgl()));
+
+        self.gl.draw_elements_with_i32(
+            WebGl2RenderingContext::TRIANGLES,
+            mesh.count,
+            WebGl2RenderingContext::UNSIGNED_INT,
+            0,
+        );
+    }
+}
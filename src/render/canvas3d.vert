@@ -0,0 +1,23 @@
+#version 300 es
+
+layout(location = 0) in vec3 aPosition;
+layout(location = 1) in vec3 aNormal;
+layout(location = 2) in vec2 aTexcoord;
+
+uniform mat4 uModelMatrix;
+uniform mat3 uNormalMatrix;
+uniform mat4 uViewProjection;
+
+out vec3 vWorldPosition;
+out vec3 vNormal;
+out vec2 vTexcoord;
+
+void main() {
+    vec4 worldPosition = uModelMatrix * vec4(aPosition, 1.);
+
+    gl_Position = uViewProjection * worldPosition;
+
+    vWorldPosition = worldPosition.xyz;
+    vNormal = uNormalMatrix * aNormal;
+    vTexcoord = aTexcoord;
+}
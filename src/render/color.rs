@@ -9,3 +9,681 @@ pub const fn rgb(r: f32, g: f32, b: f32) -> Vec4 {
 pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Vec4 {
     Vec4::new(r, g, b, a)
 }
+
+#[derive(Debug)]
+pub struct ColorError;
+
+/// Color from 8 bit channels
+#[must_use]
+pub fn rgba8(r: u8, g: u8, b: u8, a: u8) -> Vec4 {
+    Vec4::new(r as f32, g as f32, b as f32, a as f32) / 255.
+}
+
+/// Color from hue in degrees, saturation and value
+#[must_use]
+pub fn hsv(h: f32, s: f32, v: f32) -> Vec4 {
+    hsva(h, s, v, 1.)
+}
+
+/// Color from hue in degrees, saturation, value and alpha
+#[must_use]
+pub fn hsva(h: f32, s: f32, v: f32, a: f32) -> Vec4 {
+    let channel = |n: f32| {
+        let k = (n + h / 60.).rem_euclid(6.);
+        v - v * s * k.min(4. - k).clamp(0., 1.)
+    };
+
+    Vec4::new(channel(5.), channel(3.), channel(1.), a)
+}
+
+/// Convert a color to (hue in degrees, saturation, value, alpha)
+#[must_use]
+pub fn to_hsv(color: Vec4) -> Vec4 {
+    let max = color.x.max(color.y).max(color.z);
+    let min = color.x.min(color.y).min(color.z);
+
+    let s = if max > 0. { (max - min) / max } else { 0. };
+
+    Vec4::new(hue(color, max, min), s, max, color.w)
+}
+
+/// Color from hue in degrees, saturation and lightness
+#[must_use]
+pub fn hsl(h: f32, s: f32, l: f32) -> Vec4 {
+    hsla(h, s, l, 1.)
+}
+
+/// Color from hue in degrees, saturation, lightness and alpha
+#[must_use]
+pub fn hsla(h: f32, s: f32, l: f32, a: f32) -> Vec4 {
+    let amount = s * l.min(1. - l);
+
+    let channel = |n: f32| {
+        let k = (n + h / 30.).rem_euclid(12.);
+        l - amount * (k - 3.).min(9. - k).clamp(-1., 1.)
+    };
+
+    Vec4::new(channel(0.), channel(8.), channel(4.), a)
+}
+
+/// Convert a color to (hue in degrees, saturation, lightness, alpha)
+#[must_use]
+pub fn to_hsl(color: Vec4) -> Vec4 {
+    let max = color.x.max(color.y).max(color.z);
+    let min = color.x.min(color.y).min(color.z);
+
+    let l = f32::midpoint(max, min);
+
+    let s = if max - min <= 0. {
+        0.
+    } else {
+        (max - min) / (1. - (2. * l - 1.).abs())
+    };
+
+    Vec4::new(hue(color, max, min), s, l, color.w)
+}
+
+fn hue(color: Vec4, max: f32, min: f32) -> f32 {
+    let delta = max - min;
+
+    if delta <= 0. {
+        return 0.;
+    }
+
+    // max is exactly one of the channels
+    let h = if max <= color.x {
+        (color.y - color.z) / delta
+    } else if max <= color.y {
+        (color.z - color.x) / delta + 2.
+    } else {
+        (color.x - color.y) / delta + 4.
+    };
+
+    (h * 60.).rem_euclid(360.)
+}
+
+/// Convert a sRGB color (the space of images and css colors) to linear RGB, alpha is kept
+#[must_use]
+pub fn srgb_to_linear(color: Vec4) -> Vec4 {
+    let channel = |c: f32| {
+        if c <= 0.040_45 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    Vec4::new(
+        channel(color.x),
+        channel(color.y),
+        channel(color.z),
+        color.w,
+    )
+}
+
+/// Convert a linear RGB color to sRGB, alpha is kept
+#[must_use]
+pub fn linear_to_srgb(color: Vec4) -> Vec4 {
+    let channel = |c: f32| {
+        if c <= 0.003_130_8 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1. / 2.4) - 0.055
+        }
+    };
+
+    Vec4::new(
+        channel(color.x),
+        channel(color.y),
+        channel(color.z),
+        color.w,
+    )
+}
+
+/// Convert a sRGB color to (L, a, b, alpha) in the `OKLab` perceptual color space
+#[must_use]
+pub fn to_oklab(color: Vec4) -> Vec4 {
+    let c = srgb_to_linear(color);
+
+    let l = (0.412_221_46 * c.x + 0.536_332_55 * c.y + 0.051_445_995 * c.z).cbrt();
+    let m = (0.211_903_5 * c.x + 0.680_699_5 * c.y + 0.107_396_96 * c.z).cbrt();
+    let s = (0.088_302_46 * c.x + 0.281_718_85 * c.y + 0.629_978_7 * c.z).cbrt();
+
+    Vec4::new(
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+        color.w,
+    )
+}
+
+/// Convert (L, a, b, alpha) in the `OKLab` color space to a sRGB color, channels are clamped to [0, 1]
+#[must_use]
+pub fn from_oklab(lab: Vec4) -> Vec4 {
+    let l = (lab.x + 0.396_337_78 * lab.y + 0.215_803_76 * lab.z).powi(3);
+    let m = (lab.x - 0.105_561_346 * lab.y - 0.063_854_17 * lab.z).powi(3);
+    let s = (lab.x - 0.089_484_18 * lab.y - 1.291_485_5 * lab.z).powi(3);
+
+    let linear = Vec4::new(
+        4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+        -1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+        -0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+        lab.w,
+    );
+
+    linear_to_srgb(linear.clamp(Vec4::ZERO, Vec4::ONE))
+}
+
+/// Interpolate between two sRGB colors in the `OKLab` color space, giving more even gradients than a plain lerp
+#[must_use]
+pub fn lerp_perceptual(from: Vec4, to: Vec4, t: f32) -> Vec4 {
+    from_oklab(to_oklab(from).lerp(to_oklab(to), t))
+}
+
+/// Multiply the color channels by alpha
+#[must_use]
+pub fn premultiply(color: Vec4) -> Vec4 {
+    (color.truncate() * color.w).extend(color.w)
+}
+
+/// Divide the color channels by alpha, fully transparent colors become transparent black
+#[must_use]
+pub fn unpremultiply(color: Vec4) -> Vec4 {
+    if color.w <= 0. {
+        return Vec4::ZERO;
+    }
+
+    (color.truncate() / color.w).extend(color.w)
+}
+
+/// Parse a `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa` color, the `#` is optional
+///
+/// # Errors
+///
+/// Returns Err if the text isn't a valid hex color
+pub fn from_hex(text: &str) -> Result<Vec4, ColorError> {
+    let digits = text.trim().trim_start_matches('#');
+
+    if !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(ColorError);
+    }
+
+    let short = |i: usize| {
+        u8::from_str_radix(&digits[i..=i], 16)
+            .map(|v| v * 17)
+            .map_err(|_| ColorError)
+    };
+    let long = |i: usize| u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).map_err(|_| ColorError);
+
+    match digits.len() {
+        3 => Ok(rgba8(short(0)?, short(1)?, short(2)?, 255)),
+        4 => Ok(rgba8(short(0)?, short(1)?, short(2)?, short(3)?)),
+        6 => Ok(rgba8(long(0)?, long(1)?, long(2)?, 255)),
+        8 => Ok(rgba8(long(0)?, long(1)?, long(2)?, long(3)?)),
+        _ => Err(ColorError),
+    }
+}
+
+/// Format a color as `#rrggbb`, or `#rrggbbaa` when it isn't opaque
+#[must_use]
+pub fn to_hex(color: Vec4) -> String {
+    let [r, g, b, a] = (color.clamp(Vec4::ZERO, Vec4::ONE) * 255.)
+        .round()
+        .to_array()
+        .map(|c| c as u8);
+
+    if a == 255 {
+        format!("#{r:02x}{g:02x}{b:02x}")
+    } else {
+        format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+    }
+}
+
+/// Parse a hex color or a css color name
+///
+/// # Errors
+///
+/// Returns Err if the text is neither a hex color nor a css color name
+pub fn parse(text: &str) -> Result<Vec4, ColorError> {
+    let text = text.trim();
+
+    if text.starts_with('#') {
+        from_hex(text)
+    } else {
+        from_css_name(text).ok_or(ColorError)
+    }
+}
+
+/// Get a css named color, names are case insensitive
+#[must_use]
+pub fn from_css_name(name: &str) -> Option<Vec4> {
+    let name = name.to_ascii_lowercase();
+
+    if name == "transparent" {
+        return Some(Vec4::ZERO);
+    }
+
+    CSS_COLORS
+        .binary_search_by_key(&name.as_str(), |(name, _)| name)
+        .ok()
+        .map(|index| {
+            let [r, g, b] = CSS_COLORS[index].1;
+            rgba8(r, g, b, 255)
+        })
+}
+
+/// Css named colors sorted by name
+const CSS_COLORS: [(&str, [u8; 3]); 148] = [
+    ("aliceblue", [0xf0, 0xf8, 0xff]),
+    ("antiquewhite", [0xfa, 0xeb, 0xd7]),
+    ("aqua", [0x00, 0xff, 0xff]),
+    ("aquamarine", [0x7f, 0xff, 0xd4]),
+    ("azure", [0xf0, 0xff, 0xff]),
+    ("beige", [0xf5, 0xf5, 0xdc]),
+    ("bisque", [0xff, 0xe4, 0xc4]),
+    ("black", [0x00, 0x00, 0x00]),
+    ("blanchedalmond", [0xff, 0xeb, 0xcd]),
+    ("blue", [0x00, 0x00, 0xff]),
+    ("blueviolet", [0x8a, 0x2b, 0xe2]),
+    ("brown", [0xa5, 0x2a, 0x2a]),
+    ("burlywood", [0xde, 0xb8, 0x87]),
+    ("cadetblue", [0x5f, 0x9e, 0xa0]),
+    ("chartreuse", [0x7f, 0xff, 0x00]),
+    ("chocolate", [0xd2, 0x69, 0x1e]),
+    ("coral", [0xff, 0x7f, 0x50]),
+    ("cornflowerblue", [0x64, 0x95, 0xed]),
+    ("cornsilk", [0xff, 0xf8, 0xdc]),
+    ("crimson", [0xdc, 0x14, 0x3c]),
+    ("cyan", [0x00, 0xff, 0xff]),
+    ("darkblue", [0x00, 0x00, 0x8b]),
+    ("darkcyan", [0x00, 0x8b, 0x8b]),
+    ("darkgoldenrod", [0xb8, 0x86, 0x0b]),
+    ("darkgray", [0xa9, 0xa9, 0xa9]),
+    ("darkgreen", [0x00, 0x64, 0x00]),
+    ("darkgrey", [0xa9, 0xa9, 0xa9]),
+    ("darkkhaki", [0xbd, 0xb7, 0x6b]),
+    ("darkmagenta", [0x8b, 0x00, 0x8b]),
+    ("darkolivegreen", [0x55, 0x6b, 0x2f]),
+    ("darkorange", [0xff, 0x8c, 0x00]),
+    ("darkorchid", [0x99, 0x32, 0xcc]),
+    ("darkred", [0x8b, 0x00, 0x00]),
+    ("darksalmon", [0xe9, 0x96, 0x7a]),
+    ("darkseagreen", [0x8f, 0xbc, 0x8f]),
+    ("darkslateblue", [0x48, 0x3d, 0x8b]),
+    ("darkslategray", [0x2f, 0x4f, 0x4f]),
+    ("darkslategrey", [0x2f, 0x4f, 0x4f]),
+    ("darkturquoise", [0x00, 0xce, 0xd1]),
+    ("darkviolet", [0x94, 0x00, 0xd3]),
+    ("deeppink", [0xff, 0x14, 0x93]),
+    ("deepskyblue", [0x00, 0xbf, 0xff]),
+    ("dimgray", [0x69, 0x69, 0x69]),
+    ("dimgrey", [0x69, 0x69, 0x69]),
+    ("dodgerblue", [0x1e, 0x90, 0xff]),
+    ("firebrick", [0xb2, 0x22, 0x22]),
+    ("floralwhite", [0xff, 0xfa, 0xf0]),
+    ("forestgreen", [0x22, 0x8b, 0x22]),
+    ("fuchsia", [0xff, 0x00, 0xff]),
+    ("gainsboro", [0xdc, 0xdc, 0xdc]),
+    ("ghostwhite", [0xf8, 0xf8, 0xff]),
+    ("gold", [0xff, 0xd7, 0x00]),
+    ("goldenrod", [0xda, 0xa5, 0x20]),
+    ("gray", [0x80, 0x80, 0x80]),
+    ("green", [0x00, 0x80, 0x00]),
+    ("greenyellow", [0xad, 0xff, 0x2f]),
+    ("grey", [0x80, 0x80, 0x80]),
+    ("honeydew", [0xf0, 0xff, 0xf0]),
+    ("hotpink", [0xff, 0x69, 0xb4]),
+    ("indianred", [0xcd, 0x5c, 0x5c]),
+    ("indigo", [0x4b, 0x00, 0x82]),
+    ("ivory", [0xff, 0xff, 0xf0]),
+    ("khaki", [0xf0, 0xe6, 0x8c]),
+    ("lavender", [0xe6, 0xe6, 0xfa]),
+    ("lavenderblush", [0xff, 0xf0, 0xf5]),
+    ("lawngreen", [0x7c, 0xfc, 0x00]),
+    ("lemonchiffon", [0xff, 0xfa, 0xcd]),
+    ("lightblue", [0xad, 0xd8, 0xe6]),
+    ("lightcoral", [0xf0, 0x80, 0x80]),
+    ("lightcyan", [0xe0, 0xff, 0xff]),
+    ("lightgoldenrodyellow", [0xfa, 0xfa, 0xd2]),
+    ("lightgray", [0xd3, 0xd3, 0xd3]),
+    ("lightgreen", [0x90, 0xee, 0x90]),
+    ("lightgrey", [0xd3, 0xd3, 0xd3]),
+    ("lightpink", [0xff, 0xb6, 0xc1]),
+    ("lightsalmon", [0xff, 0xa0, 0x7a]),
+    ("lightseagreen", [0x20, 0xb2, 0xaa]),
+    ("lightskyblue", [0x87, 0xce, 0xfa]),
+    ("lightslategray", [0x77, 0x88, 0x99]),
+    ("lightslategrey", [0x77, 0x88, 0x99]),
+    ("lightsteelblue", [0xb0, 0xc4, 0xde]),
+    ("lightyellow", [0xff, 0xff, 0xe0]),
+    ("lime", [0x00, 0xff, 0x00]),
+    ("limegreen", [0x32, 0xcd, 0x32]),
+    ("linen", [0xfa, 0xf0, 0xe6]),
+    ("magenta", [0xff, 0x00, 0xff]),
+    ("maroon", [0x80, 0x00, 0x00]),
+    ("mediumaquamarine", [0x66, 0xcd, 0xaa]),
+    ("mediumblue", [0x00, 0x00, 0xcd]),
+    ("mediumorchid", [0xba, 0x55, 0xd3]),
+    ("mediumpurple", [0x93, 0x70, 0xdb]),
+    ("mediumseagreen", [0x3c, 0xb3, 0x71]),
+    ("mediumslateblue", [0x7b, 0x68, 0xee]),
+    ("mediumspringgreen", [0x00, 0xfa, 0x9a]),
+    ("mediumturquoise", [0x48, 0xd1, 0xcc]),
+    ("mediumvioletred", [0xc7, 0x15, 0x85]),
+    ("midnightblue", [0x19, 0x19, 0x70]),
+    ("mintcream", [0xf5, 0xff, 0xfa]),
+    ("mistyrose", [0xff, 0xe4, 0xe1]),
+    ("moccasin", [0xff, 0xe4, 0xb5]),
+    ("navajowhite", [0xff, 0xde, 0xad]),
+    ("navy", [0x00, 0x00, 0x80]),
+    ("oldlace", [0xfd, 0xf5, 0xe6]),
+    ("olive", [0x80, 0x80, 0x00]),
+    ("olivedrab", [0x6b, 0x8e, 0x23]),
+    ("orange", [0xff, 0xa5, 0x00]),
+    ("orangered", [0xff, 0x45, 0x00]),
+    ("orchid", [0xda, 0x70, 0xd6]),
+    ("palegoldenrod", [0xee, 0xe8, 0xaa]),
+    ("palegreen", [0x98, 0xfb, 0x98]),
+    ("paleturquoise", [0xaf, 0xee, 0xee]),
+    ("palevioletred", [0xdb, 0x70, 0x93]),
+    ("papayawhip", [0xff, 0xef, 0xd5]),
+    ("peachpuff", [0xff, 0xda, 0xb9]),
+    ("peru", [0xcd, 0x85, 0x3f]),
+    ("pink", [0xff, 0xc0, 0xcb]),
+    ("plum", [0xdd, 0xa0, 0xdd]),
+    ("powderblue", [0xb0, 0xe0, 0xe6]),
+    ("purple", [0x80, 0x00, 0x80]),
+    ("rebeccapurple", [0x66, 0x33, 0x99]),
+    ("red", [0xff, 0x00, 0x00]),
+    ("rosybrown", [0xbc, 0x8f, 0x8f]),
+    ("royalblue", [0x41, 0x69, 0xe1]),
+    ("saddlebrown", [0x8b, 0x45, 0x13]),
+    ("salmon", [0xfa, 0x80, 0x72]),
+    ("sandybrown", [0xf4, 0xa4, 0x60]),
+    ("seagreen", [0x2e, 0x8b, 0x57]),
+    ("seashell", [0xff, 0xf5, 0xee]),
+    ("sienna", [0xa0, 0x52, 0x2d]),
+    ("silver", [0xc0, 0xc0, 0xc0]),
+    ("skyblue", [0x87, 0xce, 0xeb]),
+    ("slateblue", [0x6a, 0x5a, 0xcd]),
+    ("slategray", [0x70, 0x80, 0x90]),
+    ("slategrey", [0x70, 0x80, 0x90]),
+    ("snow", [0xff, 0xfa, 0xfa]),
+    ("springgreen", [0x00, 0xff, 0x7f]),
+    ("steelblue", [0x46, 0x82, 0xb4]),
+    ("tan", [0xd2, 0xb4, 0x8c]),
+    ("teal", [0x00, 0x80, 0x80]),
+    ("thistle", [0xd8, 0xbf, 0xd8]),
+    ("tomato", [0xff, 0x63, 0x47]),
+    ("turquoise", [0x40, 0xe0, 0xd0]),
+    ("violet", [0xee, 0x82, 0xee]),
+    ("wheat", [0xf5, 0xde, 0xb3]),
+    ("white", [0xff, 0xff, 0xff]),
+    ("whitesmoke", [0xf5, 0xf5, 0xf5]),
+    ("yellow", [0xff, 0xff, 0x00]),
+    ("yellowgreen", [0x9a, 0xcd, 0x32]),
+];
+
+/// A list of colors with optional names
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Palette {
+    pub colors: Vec<Vec4>,
+    /// Names of the colors, empty strings for unnamed ones
+    pub names: Vec<String>,
+}
+
+impl Palette {
+    #[must_use]
+    pub fn new(colors: Vec<Vec4>) -> Self {
+        let names = vec![String::new(); colors.len()];
+
+        Self { colors, names }
+    }
+
+    /// Parse a .hex palette, one `rrggbb` color per line
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if a line isn't a valid color
+    pub fn from_hex_file(text: &str) -> Result<Self, ColorError> {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(from_hex)
+            .collect::<Result<_, _>>()
+            .map(Self::new)
+    }
+
+    /// Parse a GIMP palette (.gpl)
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if the header is missing or a color line is invalid
+    pub fn from_gpl(text: &str) -> Result<Self, ColorError> {
+        let mut lines = text.lines().map(str::trim);
+
+        if lines.next() != Some("GIMP Palette") {
+            return Err(ColorError);
+        }
+
+        let mut palette = Self::default();
+
+        for line in lines {
+            if line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with("Name:")
+                || line.starts_with("Columns:")
+            {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+
+            let mut channel = || {
+                parts
+                    .next()
+                    .and_then(|part| part.parse::<u8>().ok())
+                    .ok_or(ColorError)
+            };
+
+            let color = rgba8(channel()?, channel()?, channel()?, 255);
+            let name = parts.collect::<Vec<_>>().join(" ");
+
+            palette.colors.push(color);
+            palette.names.push(name);
+        }
+
+        Ok(palette)
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<Vec4> {
+        self.colors.get(index).copied()
+    }
+
+    /// Get a color by name, case insensitive
+    #[must_use]
+    pub fn by_name(&self, name: &str) -> Option<Vec4> {
+        self.names
+            .iter()
+            .position(|n| n.eq_ignore_ascii_case(name))
+            .and_then(|index| self.get(index))
+    }
+
+    /// Index of the perceptually closest color of the palette
+    #[must_use]
+    pub fn nearest(&self, color: Vec4) -> Option<usize> {
+        let lab = to_oklab(color).truncate();
+
+        self.colors
+            .iter()
+            .map(|c| to_oklab(*c).truncate().distance_squared(lab))
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Colors covering every hue sector, grays and the extremes
+    const SAMPLES: [Vec4; 10] = [
+        rgb(0., 0., 0.),
+        rgb(1., 1., 1.),
+        rgb(0.5, 0.5, 0.5),
+        rgb(1., 0., 0.),
+        rgb(0.2, 0.8, 0.1),
+        rgb(0.1, 0.3, 0.9),
+        rgb(0.9, 0.9, 0.1),
+        rgb(0.1, 0.9, 0.9),
+        rgb(0.7, 0.2, 0.6),
+        rgba(0.25, 0.5, 0.75, 0.4),
+    ];
+
+    fn assert_close(a: Vec4, b: Vec4) {
+        assert!(a.abs_diff_eq(b, 1e-4), "{a} != {b}");
+    }
+
+    #[test]
+    fn hsv_round_trip() {
+        for color in SAMPLES {
+            let [h, s, v, a] = to_hsv(color).to_array();
+            assert_close(hsva(h, s, v, a), color);
+        }
+
+        assert_close(to_hsv(rgb(0., 0., 1.)), Vec4::new(240., 1., 1., 1.));
+        assert_close(hsv(120., 1., 0.5), rgb(0., 0.5, 0.));
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        for color in SAMPLES {
+            let [h, s, l, a] = to_hsl(color).to_array();
+            assert_close(hsla(h, s, l, a), color);
+        }
+
+        assert_close(to_hsl(rgb(1., 0., 0.)), Vec4::new(0., 1., 0.5, 1.));
+        assert_close(hsl(300., 1., 0.75), rgb(1., 0.5, 1.));
+    }
+
+    #[test]
+    fn oklab_round_trip() {
+        for color in SAMPLES {
+            assert_close(from_oklab(to_oklab(color)), color);
+        }
+
+        // White has no chroma and a lightness of 1
+        assert_close(to_oklab(Vec4::ONE), Vec4::new(1., 0., 0., 1.));
+    }
+
+    #[test]
+    fn linear_round_trip() {
+        for color in SAMPLES {
+            assert_close(linear_to_srgb(srgb_to_linear(color)), color);
+        }
+    }
+
+    #[test]
+    fn hex_formats() {
+        assert_close(from_hex("#f80").unwrap(), rgba8(255, 136, 0, 255));
+        assert_close(from_hex("#f808").unwrap(), rgba8(255, 136, 0, 136));
+        assert_close(from_hex("#12ab3c").unwrap(), rgba8(0x12, 0xab, 0x3c, 255));
+        assert_close(from_hex("12AB3C80").unwrap(), rgba8(0x12, 0xab, 0x3c, 0x80));
+
+        for invalid in [
+            "",
+            "#",
+            "#12",
+            "#12345",
+            "#1234567",
+            "#ggg",
+            "#é12",
+            "#+f+f+f",
+            "#+fff+ff+f",
+            "+fff",
+        ] {
+            assert!(from_hex(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        for text in ["#000000", "#ffffff", "#12ab3c", "#12ab3c80"] {
+            assert_eq!(to_hex(from_hex(text).unwrap()), text);
+        }
+    }
+
+    #[test]
+    fn css_names() {
+        assert!(CSS_COLORS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        for (name, [r, g, b]) in CSS_COLORS {
+            assert_eq!(from_css_name(name), Some(rgba8(r, g, b, 255)));
+        }
+
+        assert_eq!(
+            from_css_name("RebeccaPurple"),
+            Some(rgba8(102, 51, 153, 255))
+        );
+        assert_eq!(from_css_name("transparent"), Some(Vec4::ZERO));
+        assert_eq!(from_css_name("notacolor"), None);
+
+        assert_close(parse(" teal ").unwrap(), rgba8(0, 128, 128, 255));
+        assert_close(parse("#008080").unwrap(), rgba8(0, 128, 128, 255));
+        assert!(parse("#teal").is_err());
+    }
+
+    #[test]
+    fn gpl_palette() {
+        let palette = Palette::from_gpl(
+            "GIMP Palette\nName: Test\nColumns: 2\n# comment\n\n  0   0   0 Black\n255 128   0\tDeep orange\n 10  20  30\n",
+        )
+        .unwrap();
+
+        assert_eq!(palette.len(), 3);
+        assert_eq!(palette.names, ["Black", "Deep orange", ""]);
+        assert_eq!(
+            palette.by_name("deep ORANGE"),
+            Some(rgba8(255, 128, 0, 255))
+        );
+        assert_eq!(palette.get(2), Some(rgba8(10, 20, 30, 255)));
+        assert_eq!(palette.nearest(rgb(0.9, 0.5, 0.1)), Some(1));
+
+        assert!(Palette::from_gpl("Name: Test\n0 0 0\n").is_err());
+        assert!(Palette::from_gpl("GIMP Palette\n0 0\n").is_err());
+        assert!(Palette::from_gpl("GIMP Palette\n0 0 256 Overflow\n").is_err());
+        assert!(Palette::from_gpl("GIMP Palette\nred green blue\n").is_err());
+    }
+
+    #[test]
+    fn hex_palette() {
+        let palette = Palette::from_hex_file("000000\r\nff8000\n\n  0a141e  \n").unwrap();
+
+        assert_eq!(
+            palette.colors,
+            [
+                rgba8(0, 0, 0, 255),
+                rgba8(255, 128, 0, 255),
+                rgba8(10, 20, 30, 255)
+            ]
+        );
+        assert_eq!(palette.names, ["", "", ""]);
+        assert_eq!(palette.nearest(rgb(0.9, 0.5, 0.1)), Some(1));
+
+        assert!(Palette::from_hex_file("000000\nzzzzzz\n").is_err());
+        assert!(Palette::from_hex_file("000000\n00000\n").is_err());
+        assert!(Palette::new(Vec::new()).nearest(Vec4::ONE).is_none());
+    }
+}
@@ -0,0 +1,334 @@
+use super::{
+    canvas2d::{Canvas2d, DrawTarget2d, RenderStats},
+    vertex::Vertex2d,
+};
+use crate::{
+    dom::performance,
+    font::FontStack,
+    input::{self, Key},
+};
+use glam::{Vec2, Vec3, Vec4};
+use std::{collections::VecDeque, f32::consts::TAU};
+
+/// Number of frames kept for the frame time graph
+const HISTORY: usize = 120;
+/// Frame time at the top of the graph in milliseconds
+const GRAPH_MAX_MS: f32 = 50.;
+const CIRCLE_SEGMENTS: u16 = 32;
+
+enum Primitive {
+    Line {
+        start: Vec2,
+        end: Vec2,
+        width: f32,
+        color: Vec4,
+    },
+    Rect {
+        position: Vec2,
+        size: Vec2,
+        color: Vec4,
+    },
+    Circle {
+        center: Vec2,
+        radius: f32,
+        color: Vec4,
+    },
+    Text {
+        position: Vec2,
+        height: f32,
+        text: String,
+        color: Vec4,
+    },
+}
+
+/// Debug overlay showing frame statistics and debug shapes above the scene.
+/// Shapes are recorded in world coordinates during the frame and drawn by `draw`, nothing is recorded while disabled
+///
+/// Render statistics come from the canvas, they stay at zero unless `Canvas2d::begin_frame` is called every frame
+pub struct DebugOverlay {
+    pub enabled: bool,
+    /// Key toggling the overlay, checked by `begin_frame`
+    pub toggle_key: Option<Key>,
+    /// Height of the statistics text in pixels
+    pub text_height: f32,
+    frame_times: VecDeque<f32>,
+    last_frame: Option<f64>,
+    ticks: u32,
+    primitives: Vec<Primitive>,
+}
+
+impl DebugOverlay {
+    /// Create a disabled overlay toggled with the given key
+    #[must_use]
+    pub fn new(toggle_key: Option<Key>) -> Self {
+        Self {
+            enabled: false,
+            toggle_key,
+            text_height: 16.,
+            frame_times: VecDeque::with_capacity(HISTORY),
+            last_frame: None,
+            ticks: 0,
+            primitives: Vec::new(),
+        }
+    }
+
+    /// Call at the start of every frame to measure frame times and check the toggle key
+    pub fn begin_frame(&mut self) {
+        if self.toggle_key.is_some_and(input::is_key_pressed) {
+            self.enabled = !self.enabled;
+        }
+
+        let now = performance().now();
+
+        if let Some(last_frame) = self.last_frame {
+            if self.frame_times.len() == HISTORY {
+                self.frame_times.pop_front();
+            }
+
+            self.frame_times.push_back((now - last_frame) as f32);
+        }
+
+        self.last_frame = Some(now);
+        self.primitives.clear();
+    }
+
+    /// Record the number of ticks run this frame, as returned by `TickScheduler::tick_count`
+    pub const fn set_tick_count(&mut self, ticks: u32) {
+        self.ticks = ticks;
+    }
+
+    pub fn line(&mut self, start: Vec2, end: Vec2, width: f32, color: Vec4) {
+        if self.enabled {
+            self.primitives.push(Primitive::Line {
+                start,
+                end,
+                width,
+                color,
+            });
+        }
+    }
+
+    /// Outline of a rectangle, the lines are one pixel wide
+    pub fn rect(&mut self, position: Vec2, size: Vec2, color: Vec4) {
+        if self.enabled {
+            self.primitives.push(Primitive::Rect {
+                position,
+                size,
+                color,
+            });
+        }
+    }
+
+    /// Outline of a circle, the lines are one pixel wide
+    pub fn circle(&mut self, center: Vec2, radius: f32, color: Vec4) {
+        if self.enabled {
+            self.primitives.push(Primitive::Circle {
+                center,
+                radius,
+                color,
+            });
+        }
+    }
+
+    pub fn text(&mut self, position: Vec2, height: f32, text: &str, color: Vec4) {
+        if self.enabled {
+            self.primitives.push(Primitive::Text {
+                position,
+                height,
+                text: text.to_string(),
+                color,
+            });
+        }
+    }
+
+    /// Average frame time over the history in milliseconds
+    #[must_use]
+    pub fn average_frame_time(&self) -> f32 {
+        if self.frame_times.is_empty() {
+            return 0.;
+        }
+
+        self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32
+    }
+
+    /// Draw the recorded shapes with the current view matrix, then the statistics in the top left corner.
+    /// Call it after drawing the scene, render statistics are the ones of the last frame, see `Canvas2d::begin_frame`
+    pub fn draw(&mut self, canvas: &mut Canvas2d, font: &mut FontStack) {
+        if !self.enabled {
+            return;
+        }
+
+        let stats = canvas.stats();
+        let gpu_time = canvas.gpu_time();
+
+        // Lines keep a width of one pixel whatever the zoom
+        let pixel = canvas.screen_to_world_pos(Vec2::ONE) - canvas.screen_to_world_pos(Vec2::ZERO);
+        let pixel = pixel.x.abs();
+
+        for primitive in std::mem::take(&mut self.primitives) {
+            match primitive {
+                Primitive::Line {
+                    start,
+                    end,
+                    width,
+                    color,
+                } => draw_line(canvas, start, end, width, color),
+                Primitive::Rect {
+                    position,
+                    size,
+                    color,
+                } => {
+                    let corners = [
+                        position,
+                        position + Vec2::new(size.x, 0.),
+                        position + size,
+                        position + Vec2::new(0., size.y),
+                    ];
+
+                    draw_loop(canvas, &corners, pixel, color);
+                }
+                Primitive::Circle {
+                    center,
+                    radius,
+                    color,
+                } => {
+                    let points = (0..CIRCLE_SEGMENTS)
+                        .map(|i| {
+                            center
+                                + Vec2::from_angle(i as f32 * TAU / CIRCLE_SEGMENTS as f32) * radius
+                        })
+                        .collect::<Vec<_>>();
+
+                    draw_loop(canvas, &points, pixel, color);
+                }
+                Primitive::Text {
+                    position,
+                    height,
+                    text,
+                    color,
+                } => {
+                    let white_texture = canvas.white_texture();
+                    canvas.draw_text(position, height, &text, font, color, &white_texture);
+                }
+            }
+        }
+
+        let view_matrix = canvas.view_matrix();
+
+        canvas.flush();
+        canvas.pixel_perfect_view();
+        self.draw_stats(canvas, font, stats, gpu_time);
+        canvas.set_view_matrix(view_matrix);
+    }
+
+    fn draw_stats(
+        &self,
+        canvas: &mut Canvas2d,
+        font: &mut FontStack,
+        stats: RenderStats,
+        gpu_time: Option<f64>,
+    ) {
+        let white_texture = canvas.white_texture();
+        let top = canvas.viewport_size().as_vec2().y;
+
+        let frame_time = self.average_frame_time();
+        let fps = if frame_time > 0. {
+            1000. / frame_time
+        } else {
+            0.
+        };
+
+        let gpu_time = gpu_time.map_or_else(String::new, |time| format!("  gpu {time:.2} ms"));
+
+        let lines = [
+            format!("{fps:.0} fps  {frame_time:.2} ms{gpu_time}"),
+            format!("{} draw calls  {} flushes", stats.draw_calls, stats.flushes),
+            format!("{} texture switches", stats.texture_switches),
+            format!("{} vertices", stats.vertices),
+            format!("{} textures", stats.textures),
+            format!("{} ticks", self.ticks),
+        ];
+
+        let line_height = self.text_height * 1.2;
+        let graph_size = Vec2::new(HISTORY as f32 * 2., self.text_height * 3.);
+        let margin = 4.;
+
+        let panel_size = Vec2::new(
+            graph_size.x + margin * 2.,
+            line_height * lines.len() as f32 + graph_size.y + margin * 3.,
+        );
+        let panel_position = Vec2::new(0., top - panel_size.y);
+
+        canvas.draw_rect(
+            panel_position,
+            panel_size,
+            Vec4::new(0., 0., 0., 0.7),
+            &white_texture,
+        );
+
+        for (index, line) in lines.iter().enumerate() {
+            canvas.draw_text(
+                Vec2::new(
+                    margin,
+                    top - margin - line_height * (index + 1) as f32 + self.text_height * 0.25,
+                ),
+                self.text_height,
+                line,
+                font,
+                Vec4::ONE,
+                &white_texture,
+            );
+        }
+
+        let graph_position = panel_position + Vec2::splat(margin);
+
+        // Lines at 60 and 30 fps
+        for ms in [1000. / 60., 1000. / 30.] {
+            canvas.draw_rect(
+                graph_position + Vec2::new(0., graph_size.y * ms / GRAPH_MAX_MS),
+                Vec2::new(graph_size.x, 1.),
+                Vec4::new(1., 1., 1., 0.3),
+                &white_texture,
+            );
+        }
+
+        for (index, &ms) in self.frame_times.iter().enumerate() {
+            let color = if ms > 1000. / 30. {
+                Vec3::new(1., 0.3, 0.3)
+            } else if ms > 1000. / 55. {
+                Vec3::new(1., 0.8, 0.3)
+            } else {
+                Vec3::new(0.3, 1., 0.4)
+            };
+
+            canvas.draw_rect(
+                graph_position + Vec2::new(index as f32 * 2., 0.),
+                Vec2::new(2., graph_size.y * (ms / GRAPH_MAX_MS).min(1.)),
+                color.extend(1.),
+                &white_texture,
+            );
+        }
+    }
+}
+
+fn draw_line(canvas: &mut Canvas2d, start: Vec2, end: Vec2, width: f32, color: Vec4) {
+    let normal = (end - start).perp().normalize_or_zero() * width / 2.;
+    let white_texture = canvas.white_texture();
+
+    let vertices = [start - normal, start + normal, end - normal, end + normal]
+        .map(|position| Vertex2d::new(position, color, white_texture.position));
+
+    canvas.draw_vertices(&[0, 1, 2, 1, 2, 3], &vertices, &white_texture.texture);
+}
+
+fn draw_loop(canvas: &mut Canvas2d, points: &[Vec2], width: f32, color: Vec4) {
+    for (index, &point) in points.iter().enumerate() {
+        draw_line(
+            canvas,
+            point,
+            points[(index + 1) % points.len()],
+            width,
+            color,
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use glam::{Vec2, Vec4};
+
+/// Maximum number of stops used by `Canvas2d::fill_gradient`, extra stops are ignored there
+pub const MAX_SHADER_STOPS: usize = 8;
+
+/// Number of subdivisions of a radial gradient radius when it is approximated by vertex colors
+const RADIAL_STEPS: f32 = 16.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientShape {
+    /// Colors change along the line from `start` (offset 0) to `end` (offset 1)
+    Linear { start: Vec2, end: Vec2 },
+    /// Colors change with the distance to `center`, offset 1 being at `radius`
+    Radial { center: Vec2, radius: f32 },
+}
+
+/// A color gradient in world coordinates, made of (offset, color) stops
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub shape: GradientShape,
+    stops: Vec<(f32, Vec4)>,
+}
+
+impl Gradient {
+    /// Create a gradient, stops are sorted by offset
+    #[must_use]
+    pub fn new(shape: GradientShape, mut stops: Vec<(f32, Vec4)>) -> Self {
+        assert!(!stops.is_empty(), "A gradient needs at least one stop");
+
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        Self { shape, stops }
+    }
+
+    #[must_use]
+    pub fn linear(start: Vec2, end: Vec2, stops: Vec<(f32, Vec4)>) -> Self {
+        Self::new(GradientShape::Linear { start, end }, stops)
+    }
+
+    #[must_use]
+    pub fn radial(center: Vec2, radius: f32, stops: Vec<(f32, Vec4)>) -> Self {
+        Self::new(GradientShape::Radial { center, radius }, stops)
+    }
+
+    #[must_use]
+    pub fn stops(&self) -> &[(f32, Vec4)] {
+        &self.stops
+    }
+
+    /// Offset of the given point along the gradient, not clamped
+    #[must_use]
+    pub fn offset(&self, point: Vec2) -> f32 {
+        match self.shape {
+            GradientShape::Linear { start, end } => {
+                let axis = end - start;
+                (point - start).dot(axis) / axis.length_squared().max(f32::EPSILON)
+            }
+            GradientShape::Radial { center, radius } => {
+                point.distance(center) / radius.max(f32::EPSILON)
+            }
+        }
+    }
+
+    /// Color of the gradient at the given offset, the first and last stops extend beyond them
+    #[must_use]
+    pub fn color_at_offset(&self, offset: f32) -> Vec4 {
+        let next = self.stops.partition_point(|(stop, _)| *stop <= offset);
+
+        if next == 0 {
+            return self.stops[0].1;
+        }
+
+        let (start_offset, start) = self.stops[next - 1];
+
+        let Some(&(end_offset, end)) = self.stops.get(next) else {
+            return start;
+        };
+
+        start.lerp(end, (offset - start_offset) / (end_offset - start_offset))
+    }
+
+    /// Color of the gradient at the given point
+    #[must_use]
+    pub fn color_at(&self, point: Vec2) -> Vec4 {
+        self.color_at_offset(self.offset(point))
+    }
+
+    /// Split the given triangles so that interpolating the gradient colors between their vertices looks right.
+    /// Linear gradients are split exactly at their stops, radial gradients are subdivided
+    ///
+    /// # Panics
+    ///
+    /// Panics if the result has more than 65535 vertices, a radial gradient turns each triangle in up to 768 vertices
+    #[must_use]
+    pub fn tessellate(&self, positions: &[Vec2], indexes: &[u16]) -> (Vec<Vec2>, Vec<u16>) {
+        let mut out_positions = Vec::new();
+        let mut out_indexes = Vec::new();
+
+        for triangle in indexes.chunks_exact(3) {
+            let triangle = [
+                positions[triangle[0] as usize],
+                positions[triangle[1] as usize],
+                positions[triangle[2] as usize],
+            ];
+
+            let pieces = match self.shape {
+                GradientShape::Linear { .. } => self.split_at_stops(triangle.to_vec()),
+                GradientShape::Radial { radius, .. } => {
+                    subdivide(triangle, radius / RADIAL_STEPS, 4)
+                }
+            };
+
+            // Pieces are convex, a fan is enough
+            for piece in pieces {
+                let count = u16::try_from(out_positions.len() + piece.len())
+                    .expect("Error, tessellated gradients are limited to 65535 vertices");
+                let first = count - piece.len() as u16;
+
+                for i in 1..piece.len() as u16 - 1 {
+                    out_indexes.extend_from_slice(&[first, first + i, first + i + 1]);
+                }
+
+                out_positions.extend(piece);
+            }
+        }
+
+        (out_positions, out_indexes)
+    }
+
+    /// Cut a convex polygon along the lines where the gradient reaches a stop
+    fn split_at_stops(&self, polygon: Vec<Vec2>) -> Vec<Vec<Vec2>> {
+        let mut pieces = vec![polygon];
+
+        for &(stop, _) in &self.stops {
+            pieces = pieces
+                .into_iter()
+                .flat_map(|piece| <[_; 2]>::from(self.split(&piece, stop)))
+                .filter(|piece| piece.len() >= 3)
+                .collect();
+        }
+
+        pieces
+    }
+
+    /// Clip a convex polygon into the parts below and above the given offset
+    fn split(&self, polygon: &[Vec2], stop: f32) -> (Vec<Vec2>, Vec<Vec2>) {
+        let mut below = Vec::new();
+        let mut above = Vec::new();
+
+        for (i, &a) in polygon.iter().enumerate() {
+            let b = polygon[(i + 1) % polygon.len()];
+
+            let side_a = self.offset(a) - stop;
+            let side_b = self.offset(b) - stop;
+
+            if side_a <= 0. {
+                below.push(a);
+            }
+
+            if side_a >= 0. {
+                above.push(a);
+            }
+
+            if (side_a < 0. && side_b > 0.) || (side_a > 0. && side_b < 0.) {
+                let crossing = a.lerp(b, side_a / (side_a - side_b));
+
+                below.push(crossing);
+                above.push(crossing);
+            }
+        }
+
+        (below, above)
+    }
+}
+
+/// Split a triangle in four until its edges are shorter than `max_edge`
+fn subdivide(triangle: [Vec2; 3], max_edge: f32, depth: u32) -> Vec<Vec<Vec2>> {
+    let [a, b, c] = triangle;
+
+    let longest = a.distance(b).max(b.distance(c)).max(c.distance(a));
+
+    if depth == 0 || longest <= max_edge {
+        return vec![triangle.to_vec()];
+    }
+
+    let ab = a.midpoint(b);
+    let bc = b.midpoint(c);
+    let ca = c.midpoint(a);
+
+    [[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]
+        .into_iter()
+        .flat_map(|triangle| subdivide(triangle, max_edge, depth - 1))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::{canvas2d::DrawTarget2d, software::SoftwareCanvas2d};
+    use glam::UVec2;
+
+    #[test]
+    fn linear_split_at_stops() {
+        let gradient = Gradient::linear(
+            Vec2::ZERO,
+            Vec2::new(4., 0.),
+            vec![(0.25, Vec4::ZERO), (0.75, Vec4::ONE)],
+        );
+
+        let points = [Vec2::ZERO, Vec2::new(4., 0.), Vec2::new(4., 4.)];
+        let (positions, indexes) = gradient.tessellate(&points, &[0, 1, 2]);
+
+        // No triangle crosses a stop, so vertex colors interpolate exactly
+        for triangle in indexes.chunks_exact(3) {
+            let offsets = triangle
+                .iter()
+                .map(|&index| gradient.offset(positions[index as usize]))
+                .collect::<Vec<_>>();
+
+            for stop in [0.25, 0.75] {
+                assert!(
+                    offsets.iter().all(|&offset| offset <= stop + 1e-5)
+                        || offsets.iter().all(|&offset| offset >= stop - 1e-5)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn radial_many_sides() {
+        let gradient = Gradient::radial(
+            Vec2::splat(32.),
+            32.,
+            vec![(0., Vec4::ONE), (1., Vec4::new(0., 0., 0., 1.))],
+        );
+
+        let mut canvas = SoftwareCanvas2d::new(UVec2::splat(64));
+        canvas.pixel_perfect_view();
+
+        // Far more than 65535 tessellated vertices in total
+        let texture = canvas.white_texture();
+        canvas.draw_gradient_regular(Vec2::splat(32.), 32., 200, &gradient, &texture);
+
+        assert!(canvas.pixel(32, 32)[0] > 240);
+        assert!(canvas.pixel(32, 1)[0] < 20);
+        assert_eq!(canvas.pixel(0, 0), [0; 4]);
+    }
+
+    #[test]
+    fn linear_many_stops() {
+        let stops = (0..=300)
+            .map(|i| (i as f32 / 300., Vec4::new((i % 2) as f32, 0., 1., 1.)))
+            .collect();
+        let gradient = Gradient::linear(Vec2::new(0., 32.), Vec2::new(64., 32.), stops);
+
+        let mut canvas = SoftwareCanvas2d::new(UVec2::splat(64));
+        canvas.pixel_perfect_view();
+
+        // Points along the left and right edges make triangles crossing every stop,
+        // batches of them are split by vertex count
+        let rows = (0..40).map(|i| i as f32 * 64. / 39.);
+        let points = rows
+            .clone()
+            .map(|y| Vec2::new(64., y))
+            .chain(rows.rev().map(|y| Vec2::new(0., y)))
+            .collect::<Vec<_>>();
+
+        let texture = canvas.white_texture();
+        canvas.draw_gradient_polygon(&points, &gradient, &texture);
+
+        // The fan of thin triangles leaves a few pixels uncovered by the rasterizer
+        let covered = canvas
+            .pixels()
+            .chunks_exact(4)
+            .filter(|pixel| pixel[2..] == [255, 255])
+            .count();
+
+        assert!(covered > 64 * 64 * 99 / 100, "{covered}");
+    }
+
+    #[test]
+    #[should_panic(expected = "limited to 65535 vertices")]
+    fn tessellate_limit() {
+        let gradient = Gradient::radial(Vec2::ZERO, 1., vec![(0., Vec4::ONE)]);
+        let points = [Vec2::ZERO, Vec2::X, Vec2::Y];
+
+        let _ = gradient.tessellate(&points, &[0, 1, 2].repeat(100));
+    }
+}
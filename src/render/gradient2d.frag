@@ -0,0 +1,57 @@
+#version 300 es
+            
+precision highp float;
+
+const int MAX_STOPS = 8;
+
+in vec4 vColor;
+in vec2 vTexcoord;
+in vec2 vPosition;
+
+uniform sampler2D uTexture;
+
+// Linear gradients go from uStart to uEnd, radial gradients are centered on uStart with a radius of uEnd.x
+uniform bool uRadial;
+uniform vec2 uStart;
+uniform vec2 uEnd;
+
+uniform int uStopCount;
+uniform float uStopOffsets[MAX_STOPS];
+uniform vec4 uStopColors[MAX_STOPS];
+
+out vec4 outColor;
+
+float gradientOffset() {
+    if (uRadial) {
+        return distance(vPosition, uStart) / uEnd.x;
+    }
+
+    vec2 axis = uEnd - uStart;
+
+    return dot(vPosition - uStart, axis) / dot(axis, axis);
+}
+
+vec4 gradientColor(float offset) {
+    vec4 color = uStopColors[0];
+
+    for (int i = 1; i < MAX_STOPS; i++) {
+        if (i >= uStopCount || offset < uStopOffsets[i - 1]) {
+            break;
+        }
+
+        if (offset >= uStopOffsets[i]) {
+            color = uStopColors[i];
+        } else {
+            float factor = (offset - uStopOffsets[i - 1]) / (uStopOffsets[i] - uStopOffsets[i - 1]);
+            color = mix(uStopColors[i - 1], uStopColors[i], factor);
+            break;
+        }
+    }
+
+    return color;
+}
+
+void main() {
+    outColor = texture(uTexture, vTexcoord) * vColor * gradientColor(gradientOffset());
+    outColor.rgb *= outColor.a;
+}
@@ -0,0 +1,19 @@
+#version 300 es
+        
+layout(location = 0) in vec2 aPosition;
+layout(location = 1) in vec2 aTexcoord;
+layout(location = 2) in vec4 aColor;
+
+uniform mat3 uViewMatrix;
+
+out vec4 vColor;
+out vec2 vTexcoord;
+out vec2 vPosition;
+
+void main() {
+    gl_Position = vec4((uViewMatrix * vec3(aPosition, 1.)).xy, 1., 1.);
+
+    vColor = aColor;
+    vTexcoord = aTexcoord;
+    vPosition = aPosition;
+}
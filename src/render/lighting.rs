@@ -0,0 +1,352 @@
+use super::{
+    canvas2d::{BlendMode, Canvas2d, DrawTarget2d, RenderTarget2d, TextureRect},
+    vertex::Vertex2d,
+};
+use glam::{Mat3, UVec2, Vec2, Vec3, Vec4};
+use std::f32::consts::TAU;
+
+/// Number of rings used to approximate the falloff of a light
+const FALLOFF_RINGS: u16 = 8;
+/// Number of segments used for a full circle of light
+const CIRCLE_SEGMENTS: u16 = 48;
+
+/// Cone of a spot light
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spot {
+    /// Direction of the cone center in radians, 0 pointing to the right
+    pub direction: f32,
+    /// Half angle of the fully lit part of the cone in radians
+    pub angle: f32,
+    /// Angle in radians over which the light fades on each side of the cone
+    pub softness: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    pub position: Vec2,
+    pub color: Vec3,
+    pub intensity: f32,
+    /// Distance at which the light reaches zero
+    pub radius: f32,
+    /// Exponent of the falloff, 1 is linear and higher values concentrate the light near its center
+    pub falloff: f32,
+    /// Restrict the light to a cone, None for a point light
+    pub spot: Option<Spot>,
+    pub casts_shadows: bool,
+    /// Radius of the light source, shadows get soft edges when it's above 0 and `Lighting::shadow_samples` is above 1
+    pub source_radius: f32,
+}
+
+impl Light {
+    #[must_use]
+    pub const fn point(position: Vec2, radius: f32, color: Vec3) -> Self {
+        Self {
+            position,
+            color,
+            intensity: 1.,
+            radius,
+            falloff: 1.,
+            spot: None,
+            casts_shadows: true,
+            source_radius: 0.,
+        }
+    }
+
+    #[must_use]
+    pub const fn spot(
+        position: Vec2,
+        radius: f32,
+        color: Vec3,
+        direction: f32,
+        angle: f32,
+    ) -> Self {
+        Self {
+            spot: Some(Spot {
+                direction,
+                angle,
+                softness: 0.1,
+            }),
+            ..Self::point(position, radius, color)
+        }
+    }
+
+    /// Draw the light shape, colors are added so it must be drawn with an additive blend mode
+    fn draw<T: DrawTarget2d + ?Sized>(
+        &self,
+        target: &mut T,
+        intensity: f32,
+        texture: &TextureRect,
+    ) {
+        let (start, arc, fade) = self.spot.map_or((0., TAU, None), |spot| {
+            let half_arc = (spot.angle + spot.softness).min(TAU / 2.);
+            (spot.direction - half_arc, half_arc * 2., Some(spot))
+        });
+
+        let segments = ((arc / TAU * CIRCLE_SEGMENTS as f32).ceil() as u16).max(4);
+        let ring_size = segments + 1;
+
+        let mut indexes = Vec::new();
+        let mut vertices = Vec::new();
+
+        let color = self.color * self.intensity * intensity;
+        let center_texcoord = texture.position + texture.size / 2.;
+
+        let mut push_vertex = |position: Vec2, brightness: f32| {
+            vertices.push(Vertex2d::new(
+                position,
+                (color * brightness).extend(1.),
+                center_texcoord,
+            ));
+        };
+
+        push_vertex(self.position, 1.);
+
+        for ring in 1..=FALLOFF_RINGS {
+            let distance = ring as f32 / FALLOFF_RINGS as f32;
+            let brightness = (1. - distance).powf(self.falloff);
+
+            for segment in 0..ring_size {
+                let angle = start + arc * segment as f32 / segments as f32;
+
+                let angular_fade = fade.map_or(1., |spot| {
+                    let outside = (angle - spot.direction).abs() - spot.angle;
+
+                    if outside <= 0. {
+                        1.
+                    } else {
+                        (1. - outside / spot.softness.max(f32::EPSILON)).max(0.)
+                    }
+                });
+
+                push_vertex(
+                    self.position + Vec2::from_angle(angle) * self.radius * distance,
+                    brightness * angular_fade,
+                );
+            }
+        }
+
+        let vertex = |ring: u16, segment: u16| 1 + (ring - 1) * ring_size + segment;
+
+        for segment in 0..segments {
+            indexes.extend_from_slice(&[0, vertex(1, segment), vertex(1, segment + 1)]);
+
+            for ring in 2..=FALLOFF_RINGS {
+                let inner = vertex(ring - 1, segment);
+                let outer = vertex(ring, segment);
+
+                indexes.extend_from_slice(&[inner, outer, inner + 1, inner + 1, outer, outer + 1]);
+            }
+        }
+
+        target.draw_vertices(&indexes, &vertices, &texture.texture);
+    }
+}
+
+/// A polygon blocking light
+#[derive(Clone, Debug, PartialEq)]
+pub struct Occluder {
+    pub points: Vec<Vec2>,
+}
+
+impl Occluder {
+    #[must_use]
+    pub const fn new(points: Vec<Vec2>) -> Self {
+        Self { points }
+    }
+
+    /// An axis aligned rectangle occluder
+    #[must_use]
+    pub fn rect(position: Vec2, size: Vec2) -> Self {
+        Self::new(vec![
+            position,
+            position + Vec2::new(size.x, 0.),
+            position + size,
+            position + Vec2::new(0., size.y),
+        ])
+    }
+
+    /// Draw in black the area hidden from `source` by this occluder, up to `range` from the source
+    fn draw_shadow<T: DrawTarget2d + ?Sized>(
+        &self,
+        target: &mut T,
+        source: Vec2,
+        range: f32,
+        texture: &TextureRect,
+    ) {
+        let texcoord = texture.position + texture.size / 2.;
+
+        let project = |point: Vec2| {
+            let offset = point - source;
+            source + offset.normalize_or_zero() * (offset.length() + range * 2.)
+        };
+
+        let mut vertices = Vec::new();
+
+        for (index, &a) in self.points.iter().enumerate() {
+            let b = self.points[(index + 1) % self.points.len()];
+
+            // The middle ray keeps the volume covering the whole shadow for edges seen under a wide angle
+            for point in [a, b, project(b), project((a + b) / 2.), project(a)] {
+                vertices.push(Vertex2d::new(point, Vec4::new(0., 0., 0., 1.), texcoord));
+            }
+        }
+
+        let indexes = (0..self.points.len() as u16)
+            .flat_map(|edge| {
+                let first = edge * 5;
+                [0, 1, 2, 0, 2, 3, 0, 3, 4].map(|i| first + i)
+            })
+            .collect::<Vec<_>>();
+
+        target.draw_vertices(&indexes, &vertices, &texture.texture);
+    }
+}
+
+/// A lighting layer for a `Canvas2d`.
+/// Draw the scene, then call `render` to multiply it by the light map built from the ambient light and lights
+pub struct Lighting {
+    /// Light present everywhere, including inside shadows
+    pub ambient: Vec3,
+    pub lights: Vec<Light>,
+    pub occluders: Vec<Occluder>,
+    /// Resolution of the light map relative to the viewport, lower values are faster and blurrier
+    pub resolution_scale: f32,
+    /// Number of shadow passes per light, 1 gives hard shadows
+    pub shadow_samples: u32,
+    light_map: Option<RenderTarget2d>,
+    shadow_map: Option<RenderTarget2d>,
+}
+
+impl Lighting {
+    #[must_use]
+    pub const fn new(ambient: Vec3) -> Self {
+        Self {
+            ambient,
+            lights: Vec::new(),
+            occluders: Vec::new(),
+            resolution_scale: 0.5,
+            shadow_samples: 1,
+            light_map: None,
+            shadow_map: None,
+        }
+    }
+
+    /// Get the light map built by the last call to `render`
+    #[must_use]
+    pub fn light_map(&self) -> Option<TextureRect> {
+        self.light_map.as_ref().map(RenderTarget2d::texture)
+    }
+
+    /// Build the light map with the current view matrix and multiply the current viewport by it
+    pub fn render(&mut self, canvas: &mut Canvas2d) {
+        self.render_light_map(canvas);
+
+        let Some(light_map) = &self.light_map else {
+            return;
+        };
+
+        let view_matrix = canvas.view_matrix();
+        let blend_mode = canvas.blend_mode();
+
+        canvas.set_view_matrix(Mat3::IDENTITY);
+        canvas.set_blend_mode(BlendMode::Multiply);
+        canvas.draw_rect(
+            Vec2::NEG_ONE,
+            Vec2::splat(2.),
+            Vec4::ONE,
+            &light_map.texture(),
+        );
+        canvas.flush();
+
+        canvas.set_blend_mode(blend_mode);
+        canvas.set_view_matrix(view_matrix);
+    }
+
+    /// Build the light map with the current view matrix without applying it, it can then be used with `light_map`
+    pub fn render_light_map(&mut self, canvas: &mut Canvas2d) {
+        let size = (canvas.viewport_size().as_vec2() * self.resolution_scale)
+            .ceil()
+            .max(Vec2::ONE)
+            .as_uvec2();
+
+        let light_map = Self::target(canvas, &mut self.light_map, size);
+        let shadow_map = Self::target(canvas, &mut self.shadow_map, size);
+
+        let white_texture = canvas.white_texture();
+        let blend_mode = canvas.blend_mode();
+
+        canvas.set_render_target(Some(&light_map));
+        canvas.clear(self.ambient.extend(1.));
+
+        for light in &self.lights {
+            let casts_shadows = light.casts_shadows && !self.occluders.is_empty();
+
+            if !casts_shadows {
+                canvas.set_blend_mode(BlendMode::Additive);
+                light.draw(canvas, 1., &white_texture);
+                continue;
+            }
+
+            let samples = if light.source_radius > 0. {
+                self.shadow_samples.max(1)
+            } else {
+                1
+            };
+
+            for sample in 0..samples {
+                let source = if samples == 1 {
+                    light.position
+                } else {
+                    light.position
+                        + Vec2::from_angle(sample as f32 * TAU / samples as f32)
+                            * light.source_radius
+                };
+
+                // Each pass is drawn alone so that shadows only hide the light casting them
+                canvas.set_render_target(Some(&shadow_map));
+                canvas.clear(Vec4::new(0., 0., 0., 1.));
+
+                canvas.set_blend_mode(BlendMode::Additive);
+                light.draw(canvas, 1. / samples as f32, &white_texture);
+
+                canvas.set_blend_mode(BlendMode::Alpha);
+
+                for occluder in &self.occluders {
+                    occluder.draw_shadow(canvas, source, light.radius, &white_texture);
+                }
+
+                canvas.set_render_target(Some(&light_map));
+                canvas.set_blend_mode(BlendMode::Additive);
+
+                let view_matrix = canvas.view_matrix();
+
+                canvas.set_view_matrix(Mat3::IDENTITY);
+                canvas.draw_rect(
+                    Vec2::NEG_ONE,
+                    Vec2::splat(2.),
+                    Vec4::ONE,
+                    &shadow_map.texture(),
+                );
+                canvas.set_view_matrix(view_matrix);
+            }
+        }
+
+        canvas.set_render_target(None);
+        canvas.set_blend_mode(blend_mode);
+    }
+
+    /// Get the given render target, creating it again when the size changed
+    fn target(
+        canvas: &Canvas2d,
+        target: &mut Option<RenderTarget2d>,
+        size: UVec2,
+    ) -> RenderTarget2d {
+        if let Some(existing) = target.take_if(|existing| existing.size() != size) {
+            canvas.delete_render_target(&existing);
+        }
+
+        target
+            .get_or_insert_with(|| canvas.create_render_target(size))
+            .clone()
+    }
+}
@@ -0,0 +1,640 @@
+//! Triangle meshes for `Canvas3d` and their loading from OBJ and binary glTF files
+//!
+//! Parsing is pure rust, the files can be fetched with `net::fetch_text` and `net::fetch_bytes`.
+//! Texture coordinates have v going down like images, OBJ coordinates are flipped to match glTF ones
+
+use glam::{Mat4, Quat, Vec2, Vec3};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug)]
+pub enum MeshError {
+    Json(serde_json::Error),
+    /// The file is well formed but its content doesn't describe a valid mesh
+    InvalidData(String),
+    /// The file uses a feature this loader doesn't handle, like sparse accessors
+    Unsupported(String),
+}
+
+impl From<serde_json::Error> for MeshError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+/// Indexed triangles with a normal and a texture coordinate per vertex
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct MeshData {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub texcoords: Vec<Vec2>,
+    /// Three indexes per triangle, counter clockwise for front faces
+    pub indexes: Vec<u32>,
+}
+
+impl MeshData {
+    /// A cube of size 1 centered on the origin, each face has the whole texture
+    #[must_use]
+    pub fn cube() -> Self {
+        let mut mesh = Self::default();
+
+        for normal in [
+            Vec3::X,
+            Vec3::NEG_X,
+            Vec3::Y,
+            Vec3::NEG_Y,
+            Vec3::Z,
+            Vec3::NEG_Z,
+        ] {
+            // Two axes of the face, chosen so that their cross product is the normal
+            let up = if normal.y == 0. {
+                Vec3::Y
+            } else {
+                Vec3::NEG_Z * normal.y
+            };
+            let right = up.cross(normal);
+
+            let first = mesh.positions.len() as u32;
+
+            for (x, y) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+                mesh.positions.push((normal + right * x + up * y) * 0.5);
+                mesh.normals.push(normal);
+                mesh.texcoords.push(Vec2::new(x + 1., 1. - y) * 0.5);
+            }
+
+            mesh.indexes
+                .extend([0, 1, 2, 0, 2, 3].map(|index| first + index));
+        }
+
+        mesh
+    }
+
+    /// Parse a Wavefront OBJ file, faces with more than three vertices are split in triangles.
+    /// Normals are computed when the file has none, materials and groups are ignored
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if a line can't be parsed or an index is out of range
+    pub fn from_obj(text: &str) -> Result<Self, MeshError> {
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut texcoords = Vec::new();
+
+        let mut mesh = Self::default();
+        // Vertices already added to the mesh, by (position, texcoord, normal) indexes
+        let mut vertices = BTreeMap::new();
+        let mut has_normals = true;
+
+        for (number, line) in text.lines().enumerate() {
+            let invalid = || MeshError::InvalidData(format!("Invalid line {}", number + 1));
+
+            let mut words = line.split_whitespace();
+
+            let floats = |words: std::str::SplitWhitespace| {
+                words
+                    .map(str::parse::<f32>)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| invalid())
+            };
+
+            match words.next() {
+                Some("v") => {
+                    let values = floats(words)?;
+                    positions.push(Vec3::from_slice(values.get(..3).ok_or_else(invalid)?));
+                }
+                Some("vn") => {
+                    let values = floats(words)?;
+                    normals.push(Vec3::from_slice(values.get(..3).ok_or_else(invalid)?));
+                }
+                Some("vt") => {
+                    let values = floats(words)?;
+                    let uv = Vec2::from_slice(values.get(..2).ok_or_else(invalid)?);
+                    texcoords.push(Vec2::new(uv.x, 1. - uv.y));
+                }
+                Some("f") => {
+                    let mut face = Vec::new();
+
+                    for vertex in words {
+                        let mut parts = vertex.split('/');
+
+                        let mut index = |count: usize| -> Result<Option<usize>, MeshError> {
+                            match parts.next() {
+                                None | Some("") => Ok(None),
+                                Some(part) => {
+                                    let index = part.parse::<i64>().map_err(|_| invalid())?;
+                                    // Negative indexes count from the last element
+                                    let index = if index < 0 {
+                                        count as i64 + index
+                                    } else {
+                                        index - 1
+                                    };
+
+                                    usize::try_from(index)
+                                        .ok()
+                                        .filter(|&index| index < count)
+                                        .map(Some)
+                                        .ok_or_else(invalid)
+                                }
+                            }
+                        };
+
+                        let key = (
+                            index(positions.len())?.ok_or_else(invalid)?,
+                            index(texcoords.len())?,
+                            index(normals.len())?,
+                        );
+
+                        has_normals &= key.2.is_some();
+
+                        let id = *vertices.entry(key).or_insert_with(|| {
+                            mesh.positions.push(positions[key.0]);
+                            mesh.texcoords
+                                .push(key.1.map_or(Vec2::ZERO, |index| texcoords[index]));
+                            mesh.normals
+                                .push(key.2.map_or(Vec3::ZERO, |index| normals[index]));
+
+                            mesh.positions.len() as u32 - 1
+                        });
+
+                        face.push(id);
+                    }
+
+                    if face.len() < 3 {
+                        return Err(invalid());
+                    }
+
+                    for i in 1..face.len() - 1 {
+                        mesh.indexes.extend([face[0], face[i], face[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if !has_normals {
+            mesh.compute_normals();
+        }
+
+        Ok(mesh)
+    }
+
+    /// Parse a binary glTF (.glb) file, the triangles of every mesh in the default scene are merged with the transforms of their nodes.
+    /// Materials, skins and animations are ignored
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if the file isn't a valid glb or uses sparse accessors, external buffers or non triangle primitives
+    pub fn from_glb(bytes: &[u8]) -> Result<Self, MeshError> {
+        let invalid = |message: &str| MeshError::InvalidData(message.into());
+
+        let read_u32 = |offset: usize| {
+            bytes
+                .get(offset..offset + 4)
+                .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+                .ok_or_else(|| invalid("Truncated file"))
+        };
+
+        if bytes.get(..4) != Some(b"glTF") || read_u32(4)? != 2 {
+            return Err(invalid("Not a glTF 2 binary file"));
+        }
+
+        let mut json = None;
+        let mut binary: &[u8] = &[];
+        let mut offset = 12;
+
+        while offset + 8 <= bytes.len() {
+            let length = read_u32(offset)? as usize;
+            let content = bytes
+                .get(offset + 8..offset + 8 + length)
+                .ok_or_else(|| invalid("Truncated chunk"))?;
+
+            match read_u32(offset + 4)? {
+                GLB_JSON => json = Some(content),
+                GLB_BIN => binary = content,
+                _ => {}
+            }
+
+            offset += 8 + length;
+        }
+
+        let gltf: RawGltf = serde_json::from_slice(json.ok_or_else(|| invalid("Missing json"))?)?;
+
+        let mut mesh = Self::default();
+
+        // Without scenes, every mesh is drawn as is
+        let Some(scene) = gltf.scenes.get(gltf.scene.unwrap_or(0)) else {
+            for index in 0..gltf.meshes.len() {
+                gltf.append_mesh(&mut mesh, index, Mat4::IDENTITY, binary)?;
+            }
+
+            return Ok(mesh);
+        };
+
+        let mut stack = scene
+            .nodes
+            .iter()
+            .copied()
+            .map(|node| (node, Mat4::IDENTITY))
+            .collect::<Vec<_>>();
+
+        // Nodes form trees, a node reached twice means the children have a cycle
+        let mut visited = vec![false; gltf.nodes.len()];
+
+        while let Some((index, parent)) = stack.pop() {
+            let node = gltf
+                .nodes
+                .get(index)
+                .ok_or_else(|| invalid("Invalid node index"))?;
+
+            if std::mem::replace(&mut visited[index], true) {
+                return Err(invalid("Node used more than once"));
+            }
+
+            let transform = parent * node.transform();
+
+            if let Some(mesh_index) = node.mesh {
+                gltf.append_mesh(&mut mesh, mesh_index, transform, binary)?;
+            }
+
+            stack.extend(node.children.iter().map(|&child| (child, transform)));
+        }
+
+        Ok(mesh)
+    }
+
+    /// Replace the normals by smooth normals, the average of the normals of the faces around each vertex weighted by their area
+    pub fn compute_normals(&mut self) {
+        self.normals = vec![Vec3::ZERO; self.positions.len()];
+
+        for triangle in self.indexes.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| self.positions[triangle[i] as usize]);
+            let normal = (b - a).cross(c - a);
+
+            for &index in triangle {
+                self.normals[index as usize] += normal;
+            }
+        }
+
+        for normal in &mut self.normals {
+            *normal = normal.normalize_or_zero();
+        }
+    }
+}
+
+const GLB_JSON: u32 = 0x4E4F_534A;
+const GLB_BIN: u32 = 0x004E_4942;
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_BYTE: u32 = 5121;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const TRIANGLES: u32 = 4;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawGltf {
+    scene: Option<usize>,
+    #[serde(default)]
+    scenes: Vec<RawScene>,
+    #[serde(default)]
+    nodes: Vec<RawNode>,
+    #[serde(default)]
+    meshes: Vec<RawMesh>,
+    #[serde(default)]
+    accessors: Vec<RawAccessor>,
+    #[serde(default)]
+    buffer_views: Vec<RawBufferView>,
+}
+
+#[derive(Deserialize)]
+struct RawScene {
+    #[serde(default)]
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct RawNode {
+    mesh: Option<usize>,
+    #[serde(default)]
+    children: Vec<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<[f32; 3]>,
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+}
+
+impl RawNode {
+    fn transform(&self) -> Mat4 {
+        self.matrix.map_or_else(
+            || {
+                Mat4::from_scale_rotation_translation(
+                    self.scale.map_or(Vec3::ONE, Vec3::from_array),
+                    self.rotation.map_or(Quat::IDENTITY, Quat::from_array),
+                    self.translation.map_or(Vec3::ZERO, Vec3::from_array),
+                )
+            },
+            |matrix| Mat4::from_cols_array(&matrix),
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct RawMesh {
+    primitives: Vec<RawPrimitive>,
+}
+
+#[derive(Deserialize)]
+struct RawPrimitive {
+    attributes: BTreeMap<String, usize>,
+    indices: Option<usize>,
+    mode: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawAccessor {
+    buffer_view: Option<usize>,
+    #[serde(default)]
+    byte_offset: usize,
+    component_type: u32,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawBufferView {
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+impl RawGltf {
+    fn append_mesh(
+        &self,
+        mesh: &mut MeshData,
+        index: usize,
+        transform: Mat4,
+        binary: &[u8],
+    ) -> Result<(), MeshError> {
+        let raw = self
+            .meshes
+            .get(index)
+            .ok_or_else(|| MeshError::InvalidData("Invalid mesh index".into()))?;
+
+        let normal_matrix = transform.inverse().transpose();
+
+        for primitive in &raw.primitives {
+            if primitive.mode.unwrap_or(TRIANGLES) != TRIANGLES {
+                return Err(MeshError::Unsupported("Non triangle primitives".into()));
+            }
+
+            let position_accessor = *primitive
+                .attributes
+                .get("POSITION")
+                .ok_or_else(|| MeshError::InvalidData("Primitive without positions".into()))?;
+
+            let positions = self.read_floats(position_accessor, 3, binary)?;
+            let count = positions.len() / 3;
+
+            let normals = match primitive.attributes.get("NORMAL") {
+                Some(&accessor) => Some(self.read_floats(accessor, 3, binary)?),
+                None => None,
+            };
+
+            let texcoords = match primitive.attributes.get("TEXCOORD_0") {
+                Some(&accessor) => self.read_floats(accessor, 2, binary)?,
+                None => vec![0.; count * 2],
+            };
+
+            let indexes = match primitive.indices {
+                Some(accessor) => self.read_indexes(accessor, binary)?,
+                None => (0..count as u32).collect(),
+            };
+
+            if texcoords.len() != count * 2
+                || normals
+                    .as_ref()
+                    .is_some_and(|normals| normals.len() != count * 3)
+                || indexes.iter().any(|&index| index as usize >= count)
+            {
+                return Err(MeshError::InvalidData("Mismatched attribute counts".into()));
+            }
+
+            let first = mesh.positions.len() as u32;
+
+            mesh.positions.extend(
+                positions
+                    .chunks_exact(3)
+                    .map(|position| transform.transform_point3(Vec3::from_slice(position))),
+            );
+            mesh.texcoords
+                .extend(texcoords.chunks_exact(2).map(Vec2::from_slice));
+            mesh.indexes
+                .extend(indexes.iter().map(|index| first + index));
+
+            if let Some(normals) = normals {
+                mesh.normals.extend(normals.chunks_exact(3).map(|normal| {
+                    normal_matrix
+                        .transform_vector3(Vec3::from_slice(normal))
+                        .normalize_or_zero()
+                }));
+            } else {
+                let mut part = MeshData {
+                    positions: mesh.positions[first as usize..].to_vec(),
+                    indexes,
+                    ..MeshData::default()
+                };
+
+                part.compute_normals();
+                mesh.normals.extend(part.normals);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Bytes of each element of an accessor
+    fn elements<'a>(
+        &self,
+        accessor: &RawAccessor,
+        element_size: usize,
+        binary: &'a [u8],
+    ) -> Result<Vec<&'a [u8]>, MeshError> {
+        if accessor.sparse.is_some() {
+            return Err(MeshError::Unsupported("Sparse accessors".into()));
+        }
+
+        let view = accessor
+            .buffer_view
+            .and_then(|view| self.buffer_views.get(view))
+            .ok_or_else(|| MeshError::Unsupported("Accessors without buffer view".into()))?;
+
+        if view.buffer != 0 {
+            return Err(MeshError::Unsupported("External buffers".into()));
+        }
+
+        let data = binary
+            .get(view.byte_offset..view.byte_offset + view.byte_length)
+            .ok_or_else(|| MeshError::InvalidData("Buffer view out of range".into()))?;
+
+        let stride = view.byte_stride.unwrap_or(element_size);
+
+        (0..accessor.count)
+            .map(|i| {
+                let start = accessor.byte_offset + i * stride;
+
+                data.get(start..start + element_size)
+                    .ok_or_else(|| MeshError::InvalidData("Accessor out of range".into()))
+            })
+            .collect()
+    }
+
+    fn read_floats(
+        &self,
+        index: usize,
+        components: usize,
+        binary: &[u8],
+    ) -> Result<Vec<f32>, MeshError> {
+        let accessor = self.accessor(index)?;
+
+        let expected = if components == 3 { "VEC3" } else { "VEC2" };
+
+        if accessor.component_type != FLOAT || accessor.kind != expected {
+            return Err(MeshError::Unsupported(format!(
+                "Attribute of type {} {}",
+                accessor.kind, accessor.component_type
+            )));
+        }
+
+        Ok(self
+            .elements(accessor, components * 4, binary)?
+            .into_iter()
+            .flat_map(|element| {
+                element
+                    .chunks_exact(4)
+                    .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            })
+            .collect())
+    }
+
+    fn read_indexes(&self, index: usize, binary: &[u8]) -> Result<Vec<u32>, MeshError> {
+        let accessor = self.accessor(index)?;
+
+        let size = match accessor.component_type {
+            UNSIGNED_BYTE => 1,
+            UNSIGNED_SHORT => 2,
+            UNSIGNED_INT => 4,
+            other => return Err(MeshError::InvalidData(format!("Index type {other}"))),
+        };
+
+        Ok(self
+            .elements(accessor, size, binary)?
+            .into_iter()
+            .map(|bytes| match *bytes {
+                [byte] => u32::from(byte),
+                [a, b] => u32::from(u16::from_le_bytes([a, b])),
+                [a, b, c, d] => u32::from_le_bytes([a, b, c, d]),
+                _ => unreachable!(),
+            })
+            .collect())
+    }
+
+    fn accessor(&self, index: usize) -> Result<&RawAccessor, MeshError> {
+        self.accessors
+            .get(index)
+            .ok_or_else(|| MeshError::InvalidData("Invalid accessor index".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn obj_quads_are_fanned_and_get_normals() {
+        let mesh = MeshData::from_obj(include_str!("../../resources/meshes/quad.obj")).unwrap();
+
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.indexes, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.texcoords, [Vec2::ZERO; 4]);
+
+        for normal in &mesh.normals {
+            assert!(normal.abs_diff_eq(Vec3::Z, 1e-6), "{normal}");
+        }
+    }
+
+    #[test]
+    fn obj_negative_indexes_share_vertices() {
+        let mesh = MeshData::from_obj(include_str!("../../resources/meshes/negative.obj")).unwrap();
+
+        assert_eq!(
+            mesh.positions,
+            [Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::new(1., 1., 0.)]
+        );
+        // v is flipped
+        assert_eq!(mesh.texcoords, [Vec2::Y, Vec2::ONE, Vec2::ZERO, Vec2::X]);
+        assert_eq!(mesh.normals, [Vec3::Z; 4]);
+        assert_eq!(mesh.indexes, [0, 1, 2, 1, 3, 2]);
+    }
+
+    #[test]
+    fn obj_errors() {
+        for text in [
+            "v 1 2",
+            "v 0 0 0\nf 1 2 3",
+            "v 0 0 0\nv 1 0 0\nf 1 2",
+            "f -1 1 1",
+        ] {
+            assert!(
+                matches!(MeshData::from_obj(text), Err(MeshError::InvalidData(_))),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn glb_node_transforms() {
+        let bytes = include_bytes!("../../resources/meshes/triangle.glb");
+        let mesh = MeshData::from_glb(bytes).unwrap();
+
+        // Scaled by the child node then translated by its parent
+        assert_eq!(
+            mesh.positions,
+            [Vec3::X, Vec3::new(3., 0., 0.), Vec3::new(1., 2., 0.)]
+        );
+        assert_eq!(mesh.indexes, [0, 1, 2]);
+
+        for normal in &mesh.normals {
+            assert!(normal.abs_diff_eq(Vec3::Z, 1e-6), "{normal}");
+        }
+    }
+
+    #[test]
+    fn glb_node_cycle() {
+        let bytes = include_bytes!("../../resources/meshes/cycle.glb");
+
+        assert!(matches!(
+            MeshData::from_glb(bytes),
+            Err(MeshError::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn glb_errors() {
+        let bytes = include_bytes!("../../resources/meshes/triangle.glb");
+
+        assert!(matches!(
+            MeshData::from_glb(b"glTF"),
+            Err(MeshError::InvalidData(_))
+        ));
+        assert!(matches!(
+            MeshData::from_glb(&bytes[..bytes.len() - 4]),
+            Err(MeshError::InvalidData(_))
+        ));
+    }
+}
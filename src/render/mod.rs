@@ -1,3 +1,19 @@
+pub mod camera2d;
+pub mod camera3d;
 pub mod canvas2d;
+pub mod canvas3d;
 pub mod color;
+pub mod debug;
+pub mod gradient;
+pub mod lighting;
+pub mod mesh;
+pub mod nine_slice;
+pub mod particles;
+pub mod polygon;
+pub mod recorder;
+pub mod snapshot;
+pub mod software;
+pub mod tilemap;
+pub mod vertex;
+pub mod viewport2d;
 mod webgl_util;
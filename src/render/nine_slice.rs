@@ -0,0 +1,379 @@
+use super::canvas2d::{DrawTarget2d, TextureRect};
+use glam::{Vec2, Vec4};
+
+/// How the edges and center of a nine-slice fill their area
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SliceMode {
+    #[default]
+    Stretch,
+    /// Repeat the slice at its scaled size, the last repetition is cut
+    Tile,
+}
+
+/// Distances from each side of a rectangle
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Insets {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl Insets {
+    #[must_use]
+    pub const fn new(left: f32, right: f32, top: f32, bottom: f32) -> Self {
+        Self {
+            left,
+            right,
+            top,
+            bottom,
+        }
+    }
+
+    /// The same inset on every side
+    #[must_use]
+    pub const fn uniform(inset: f32) -> Self {
+        Self::new(inset, inset, inset, inset)
+    }
+}
+
+#[derive(Debug)]
+pub struct NinePatchError;
+
+/// A texture split in nine parts by its border insets, the corners keep their size while the edges and center
+/// fill the remaining space so that frames can be drawn at any size
+#[derive(Clone)]
+pub struct NineSlice {
+    pub texture: TextureRect,
+    /// Size of the texture rect in pixels
+    pub pixel_size: Vec2,
+    /// Borders of the texture in pixels
+    pub insets: Insets,
+    /// Space between the sides and the content in pixels, see `content_rect`
+    pub padding: Insets,
+    pub edge_mode: SliceMode,
+    pub center_mode: SliceMode,
+    /// World units per texture pixel for the borders and tiles
+    pub scale: f32,
+}
+
+impl NineSlice {
+    /// Create a stretched nine-slice, the padding is the same as the insets
+    #[must_use]
+    pub const fn new(texture: TextureRect, pixel_size: Vec2, insets: Insets) -> Self {
+        Self {
+            texture,
+            pixel_size,
+            insets,
+            padding: insets,
+            edge_mode: SliceMode::Stretch,
+            center_mode: SliceMode::Stretch,
+            scale: 1.,
+        }
+    }
+
+    /// Create a nine-slice from an Android style .9.png texture and its RGBA pixels, see `image::pixels`.
+    /// Black pixels on the top and left lines mark the stretched area, on the bottom and right lines the content area.
+    /// The marker lines are removed from the texture rect
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if the image is too small, the size doesn't match the pixels or there is no stretch marker
+    pub fn from_nine_patch(
+        texture: TextureRect,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<Self, NinePatchError> {
+        if width < 3 || height < 3 || pixels.len() != (width * height * 4) as usize {
+            return Err(NinePatchError);
+        }
+
+        let is_marker = |x: u32, y: u32| {
+            let index = ((y * width + x) * 4) as usize;
+            pixels[index..index + 4] == [0, 0, 0, 255]
+        };
+
+        // Range of marked pixels along a line, relative to the image without its marker lines
+        let marked = |count: u32, pixel: &dyn Fn(u32) -> bool| {
+            let mut marks = (1..count - 1).filter(|&i| pixel(i));
+            let first = marks.next()?;
+            let last = marks.last().unwrap_or(first);
+
+            Some((first - 1, count - 2 - last))
+        };
+
+        let (left, right) = marked(width, &|x| is_marker(x, 0)).ok_or(NinePatchError)?;
+        let (top, bottom) = marked(height, &|y| is_marker(0, y)).ok_or(NinePatchError)?;
+
+        let insets = Insets::new(left as f32, right as f32, top as f32, bottom as f32);
+
+        let padding = match (
+            marked(width, &|x| is_marker(x, height - 1)),
+            marked(height, &|y| is_marker(width - 1, y)),
+        ) {
+            (Some((left, right)), Some((top, bottom))) => {
+                Insets::new(left as f32, right as f32, top as f32, bottom as f32)
+            }
+            (Some((left, right)), None) => {
+                Insets::new(left as f32, right as f32, insets.top, insets.bottom)
+            }
+            (None, Some((top, bottom))) => {
+                Insets::new(insets.left, insets.right, top as f32, bottom as f32)
+            }
+            (None, None) => insets,
+        };
+
+        let size = Vec2::new(width as f32, height as f32);
+        let pixel = texture.size / size;
+
+        let texture = TextureRect {
+            texture: texture.texture,
+            position: texture.position + pixel,
+            size: texture.size - pixel * 2.,
+        };
+
+        Ok(Self {
+            padding,
+            ..Self::new(texture, size - 2., insets)
+        })
+    }
+
+    /// Area left for the content of a nine-slice drawn at the given position and size, as (position, size)
+    #[must_use]
+    pub fn content_rect(&self, position: Vec2, size: Vec2) -> (Vec2, Vec2) {
+        let factor = self.border_factor(size);
+
+        let min = Vec2::new(self.padding.left, self.padding.bottom) * factor;
+        let max = Vec2::new(self.padding.right, self.padding.top) * factor;
+
+        (position + min, (size - min - max).max(Vec2::ZERO))
+    }
+
+    /// Scale from pixels to world units of the borders, reduced when the size is too small to fit them
+    fn border_factor(&self, size: Vec2) -> Vec2 {
+        let borders = Vec2::new(
+            self.insets.left + self.insets.right,
+            self.insets.top + self.insets.bottom,
+        ) * self.scale;
+
+        let fit = (size / borders.max(Vec2::splat(f32::EPSILON))).min(Vec2::ONE);
+
+        fit * self.scale
+    }
+
+    /// Draw the nine-slice, color and texture are multiplied
+    pub(crate) fn draw<T: DrawTarget2d + ?Sized>(
+        &self,
+        target: &mut T,
+        position: Vec2,
+        size: Vec2,
+        color: Vec4,
+    ) {
+        let factor = self.border_factor(size);
+        let insets = self.insets;
+
+        let columns = [
+            (insets.left, insets.left * factor.x),
+            (
+                self.pixel_size.x - insets.left - insets.right,
+                size.x - (insets.left + insets.right) * factor.x,
+            ),
+            (insets.right, insets.right * factor.x),
+        ];
+
+        // Rows go from the top of the texture, which is the top of the drawn rect
+        let rows = [
+            (insets.top, insets.top * factor.y),
+            (
+                self.pixel_size.y - insets.top - insets.bottom,
+                size.y - (insets.top + insets.bottom) * factor.y,
+            ),
+            (insets.bottom, insets.bottom * factor.y),
+        ];
+
+        let pixel = self.texture.size / self.pixel_size;
+
+        let mut source_y = 0.;
+        let mut offset_y = 0.;
+
+        for (row, &(source_height, height)) in rows.iter().enumerate() {
+            let mut source_x = 0.;
+            let mut offset_x = 0.;
+
+            for (column, &(source_width, width)) in columns.iter().enumerate() {
+                let mode = match (row, column) {
+                    (1, 1) => self.center_mode,
+                    (1, _) | (_, 1) => self.edge_mode,
+                    _ => SliceMode::Stretch,
+                };
+
+                // Edges only tile along their length
+                let tile_x = mode == SliceMode::Tile && column == 1;
+                let tile_y = mode == SliceMode::Tile && row == 1;
+
+                for (x, w, s_x, s_w) in segments(
+                    offset_x,
+                    width,
+                    source_x,
+                    source_width,
+                    self.tile_size(tile_x),
+                ) {
+                    for (y, h, s_y, s_h) in segments(
+                        offset_y,
+                        height,
+                        source_y,
+                        source_height,
+                        self.tile_size(tile_y),
+                    ) {
+                        target.draw_rect(
+                            Vec2::new(position.x + x, position.y + size.y - y - h),
+                            Vec2::new(w, h),
+                            color,
+                            &TextureRect {
+                                texture: self.texture.texture.clone(),
+                                position: self.texture.position + Vec2::new(s_x, s_y) * pixel,
+                                size: Vec2::new(s_w, s_h) * pixel,
+                            },
+                        );
+                    }
+                }
+
+                source_x += source_width;
+                offset_x += width;
+            }
+
+            source_y += source_height;
+            offset_y += height;
+        }
+    }
+
+    const fn tile_size(&self, tile: bool) -> Option<f32> {
+        if tile {
+            Some(self.scale)
+        } else {
+            None
+        }
+    }
+}
+
+/// Maximum number of repetitions along a span, tiles are enlarged to not go over it
+const MAX_TILES: f32 = 256.;
+
+/// Split a span along an axis into (offset, length, source offset, source length) parts.
+/// With a scale, the source is repeated at that many world units per pixel, otherwise it is stretched
+fn segments(
+    offset: f32,
+    length: f32,
+    source_offset: f32,
+    source_length: f32,
+    tile_scale: Option<f32>,
+) -> Vec<(f32, f32, f32, f32)> {
+    if length <= 0. || source_length <= 0. {
+        return Vec::new();
+    }
+
+    let Some(scale) = tile_scale else {
+        return vec![(offset, length, source_offset, source_length)];
+    };
+
+    let tile = (source_length * scale).max(length / MAX_TILES);
+    let count = (length / tile).ceil() as usize;
+
+    (0..count)
+        .map(|i| {
+            let start = i as f32 * tile;
+            let part = (length - start).min(tile);
+
+            (
+                offset + start,
+                part,
+                source_offset,
+                source_length * part / tile,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::software::SoftwareCanvas2d;
+    use glam::UVec2;
+
+    const MARKER: [u8; 4] = [0, 0, 0, 255];
+
+    /// A transparent image with markers at the given pixels
+    fn image(width: u32, height: u32, markers: &[(u32, u32)]) -> Vec<u8> {
+        let mut pixels = vec![0; (width * height * 4) as usize];
+
+        for &(x, y) in markers {
+            let index = ((y * width + x) * 4) as usize;
+            pixels[index..index + 4].copy_from_slice(&MARKER);
+        }
+
+        pixels
+    }
+
+    fn texture() -> TextureRect {
+        SoftwareCanvas2d::new(UVec2::ONE).white_texture()
+    }
+
+    #[test]
+    fn nine_patch_markers() {
+        // Stretch between x 2 and 3 and at y 2, content from x 2 to 3 on the bottom line
+        let pixels = image(6, 5, &[(2, 0), (3, 0), (0, 2), (2, 4)]);
+        let slice = NineSlice::from_nine_patch(texture(), 6, 5, &pixels).unwrap();
+
+        assert_eq!(slice.pixel_size, Vec2::new(4., 3.));
+        assert_eq!(slice.insets, Insets::new(1., 1., 1., 1.));
+        // The missing right line keeps the vertical insets
+        assert_eq!(slice.padding, Insets::new(1., 2., 1., 1.));
+
+        // The marker lines are cropped
+        assert!(slice
+            .texture
+            .position
+            .abs_diff_eq(Vec2::new(1. / 6., 1. / 5.), 1e-6));
+        assert!(slice
+            .texture
+            .size
+            .abs_diff_eq(Vec2::new(4. / 6., 3. / 5.), 1e-6));
+    }
+
+    #[test]
+    fn nine_patch_content_markers() {
+        let pixels = image(5, 5, &[(1, 0), (0, 3), (3, 4), (4, 1), (4, 2)]);
+        let slice = NineSlice::from_nine_patch(texture(), 5, 5, &pixels).unwrap();
+
+        assert_eq!(slice.insets, Insets::new(0., 2., 2., 0.));
+        assert_eq!(slice.padding, Insets::new(2., 0., 0., 1.));
+    }
+
+    #[test]
+    fn malformed_nine_patches() {
+        // Too small, wrong pixel count, no stretch marker on the top or left line
+        assert!(NineSlice::from_nine_patch(texture(), 2, 5, &image(2, 5, &[])).is_err());
+        assert!(NineSlice::from_nine_patch(texture(), 5, 5, &image(5, 4, &[(2, 0)])).is_err());
+        assert!(NineSlice::from_nine_patch(texture(), 5, 5, &image(5, 5, &[(2, 0)])).is_err());
+        assert!(NineSlice::from_nine_patch(texture(), 5, 5, &image(5, 5, &[(0, 2)])).is_err());
+
+        // Corners and translucent pixels aren't markers
+        let mut pixels = image(5, 5, &[(0, 0), (4, 0), (0, 4), (2, 0)]);
+        pixels[(2 * 5 * 4)..(2 * 5 * 4 + 4)].copy_from_slice(&[0, 0, 0, 128]);
+        assert!(NineSlice::from_nine_patch(texture(), 5, 5, &pixels).is_err());
+    }
+
+    #[test]
+    fn tiny_tiles_are_capped() {
+        let parts = segments(0., 1000., 0., 4., Some(0.));
+        assert_eq!(parts.len(), 256);
+
+        let total = parts.iter().map(|&(_, length, _, _)| length).sum::<f32>();
+        assert!((total - 1000.).abs() < 1e-2);
+
+        let parts = segments(0., 10., 2., 4., Some(1.));
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[2], (8., 2., 2., 2.));
+    }
+}
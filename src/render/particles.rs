@@ -0,0 +1,519 @@
+use super::{
+    canvas2d::{BlendMode, Canvas2d, DrawTarget2d, TextureRect},
+    vertex::Vertex2d,
+};
+use glam::{Mat2, Vec2, Vec4};
+use std::ops::{Add, Mul};
+
+/// A value changing over the lifetime of a particle, keys are (fraction of lifetime, value) pairs sorted by time
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve<T> {
+    keys: Vec<(f32, T)>,
+}
+
+impl<T: Copy + Add<Output = T> + Mul<f32, Output = T>> Curve<T> {
+    /// A curve keeping the same value
+    #[must_use]
+    pub fn constant(value: T) -> Self {
+        Self {
+            keys: vec![(0., value)],
+        }
+    }
+
+    /// A curve going linearly from `start` to `end`
+    #[must_use]
+    pub fn linear(start: T, end: T) -> Self {
+        Self {
+            keys: vec![(0., start), (1., end)],
+        }
+    }
+
+    /// A curve interpolating linearly between the given keys, they are sorted by time
+    #[must_use]
+    pub fn from_keys(mut keys: Vec<(f32, T)>) -> Self {
+        assert!(!keys.is_empty(), "A curve needs at least one key");
+
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        Self { keys }
+    }
+
+    /// Value of the curve at the given fraction of lifetime
+    #[must_use]
+    pub fn sample(&self, t: f32) -> T {
+        let next = self.keys.partition_point(|(time, _)| *time <= t);
+
+        if next == 0 {
+            return self.keys[0].1;
+        }
+
+        let (start_time, start) = self.keys[next - 1];
+
+        let Some(&(end_time, end)) = self.keys.get(next) else {
+            return start;
+        };
+
+        let factor = (t - start_time) / (end_time - start_time);
+
+        start * (1. - factor) + end * factor
+    }
+}
+
+/// Spawn `count` particles `tick` ticks after the emitter started
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Burst {
+    pub tick: u32,
+    pub count: u32,
+}
+
+/// Parameters shared by all the particles of an emitter.
+/// Ranges are (min, max) pairs, values are picked uniformly inside them, speeds are in world units per tick
+#[derive(Clone)]
+pub struct EmitterConfig {
+    /// Particles spawned on every tick, fractional rates spawn a particle every few ticks
+    pub rate: f32,
+    pub bursts: Vec<Burst>,
+    /// Number of ticks during which the emitter spawns particles, forever if None
+    pub duration: Option<u32>,
+    /// Spawned particles are ignored once this count is reached
+    pub max_particles: usize,
+    /// Lifetime in ticks
+    pub lifetime: (u32, u32),
+    /// Particles are spawned at a random position inside a circle of this radius around the emitter
+    pub spawn_radius: f32,
+    pub speed: (f32, f32),
+    /// Direction of the initial velocity in radians, 0 pointing to the right
+    pub angle: (f32, f32),
+    /// Acceleration added to the velocity of each particle on every tick
+    pub gravity: Vec2,
+    /// Fraction of the velocity lost on every tick
+    pub drag: f32,
+    /// Initial rotation in radians
+    pub rotation: (f32, f32),
+    /// Rotation added on every tick in radians
+    pub angular_velocity: (f32, f32),
+    pub color: Curve<Vec4>,
+    /// Width and height of the particles
+    pub size: Curve<f32>,
+    /// Sprite frames, the white texture is used when empty
+    pub frames: Vec<TextureRect>,
+    /// Play the frames over the lifetime of each particle instead of picking a random one
+    pub animate_frames: bool,
+    pub blend_mode: BlendMode,
+}
+
+impl EmitterConfig {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            rate: 1.,
+            bursts: Vec::new(),
+            duration: None,
+            max_particles: 1000,
+            lifetime: (60, 60),
+            spawn_radius: 0.,
+            speed: (0., 0.),
+            angle: (0., std::f32::consts::TAU),
+            gravity: Vec2::ZERO,
+            drag: 0.,
+            rotation: (0., 0.),
+            angular_velocity: (0., 0.),
+            color: Curve::constant(Vec4::ONE),
+            size: Curve::constant(1.),
+            frames: Vec::new(),
+            animate_frames: false,
+            blend_mode: BlendMode::Alpha,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Particle {
+    position: Vec2,
+    velocity: Vec2,
+    rotation: f32,
+    angular_velocity: f32,
+    age: u32,
+    lifetime: u32,
+    frame: usize,
+}
+
+/// Emits and simulates particles, call `tick` at a fixed rate and `draw` every frame
+pub struct Emitter {
+    pub config: EmitterConfig,
+    /// Position of the emitter in world coordinates, moving it doesn't move spawned particles
+    pub position: Vec2,
+    /// Stop spawning particles from the rate and bursts, existing particles keep living
+    pub paused: bool,
+    particles: Vec<Particle>,
+    spawn_accumulator: f32,
+    ticks: u32,
+    rng_state: u32,
+}
+
+impl Emitter {
+    #[must_use]
+    pub const fn new(config: EmitterConfig, position: Vec2) -> Self {
+        Self {
+            config,
+            position,
+            paused: false,
+            particles: Vec::new(),
+            spawn_accumulator: 0.,
+            ticks: 0,
+            rng_state: 0x9E37_79B9,
+        }
+    }
+
+    /// Seed the random generator, emitters with the same seed and config produce the same particles
+    pub const fn set_seed(&mut self, seed: u32) {
+        // Xorshift can't leave the zero state
+        self.rng_state = if seed == 0 { 0x9E37_79B9 } else { seed };
+    }
+
+    /// Restart the emitter, removing all particles
+    pub fn reset(&mut self) {
+        self.particles.clear();
+        self.spawn_accumulator = 0.;
+        self.ticks = 0;
+    }
+
+    /// Spawn `count` particles immediately
+    pub fn burst(&mut self, count: u32) {
+        for _ in 0..count {
+            self.spawn();
+        }
+    }
+
+    #[must_use]
+    pub const fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Check if the emitter won't spawn particles anymore and all its particles died
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.particles.is_empty()
+            && self.config.duration.is_some_and(|duration| {
+                self.ticks >= duration
+                    && self
+                        .config
+                        .bursts
+                        .iter()
+                        .all(|burst| burst.tick < self.ticks)
+            })
+    }
+
+    /// Advance the simulation by one tick
+    pub fn tick(&mut self) {
+        self.particles.retain_mut(|particle| {
+            particle.age += 1;
+
+            particle.velocity += self.config.gravity;
+            particle.velocity *= 1. - self.config.drag;
+            particle.position += particle.velocity;
+            particle.rotation += particle.angular_velocity;
+
+            particle.age < particle.lifetime
+        });
+
+        if !self.paused {
+            let spawning = self
+                .config
+                .duration
+                .is_none_or(|duration| self.ticks < duration);
+
+            if spawning {
+                self.spawn_accumulator += self.config.rate;
+
+                let count = self.spawn_accumulator.floor();
+                self.spawn_accumulator -= count;
+
+                self.burst(count as u32);
+            }
+
+            let ticks = self.ticks;
+
+            let burst_count = self
+                .config
+                .bursts
+                .iter()
+                .filter(|burst| burst.tick == ticks)
+                .map(|burst| burst.count)
+                .sum();
+
+            self.burst(burst_count);
+        }
+
+        self.ticks = self.ticks.saturating_add(1);
+    }
+
+    /// Draw the particles on the canvas with the blend mode of the emitter
+    pub fn draw(&self, canvas: &mut Canvas2d) {
+        let blend_mode = canvas.blend_mode();
+
+        canvas.set_blend_mode(self.config.blend_mode);
+        self.draw_on(canvas, &canvas.white_texture());
+        canvas.set_blend_mode(blend_mode);
+    }
+
+    /// Draw the particles on any target, ignoring the blend mode. `white_texture` is used when the config has no frames
+    pub fn draw_on<T: DrawTarget2d + ?Sized>(&self, target: &mut T, white_texture: &TextureRect) {
+        let mut vertices = Vec::with_capacity(4);
+
+        for particle in &self.particles {
+            let t = particle.age as f32 / particle.lifetime as f32;
+
+            let texture = if self.config.frames.is_empty() {
+                white_texture
+            } else if self.config.animate_frames {
+                let count = self.config.frames.len();
+                &self.config.frames[((t * count as f32) as usize).min(count - 1)]
+            } else {
+                // Frames may have been removed from the config after the particle spawned
+                &self.config.frames[particle.frame.min(self.config.frames.len() - 1)]
+            };
+
+            let half_size = self.config.size.sample(t) / 2.;
+            let rotation = Mat2::from_angle(particle.rotation);
+
+            let color = self.config.color.sample(t);
+
+            vertices.clear();
+
+            let t_min = texture.position;
+            let t_max = texture.position + texture.size;
+
+            for (corner, texcoord) in [
+                (Vec2::new(-1., -1.), Vec2::new(t_min.x, t_max.y)),
+                (Vec2::new(1., -1.), t_max),
+                (Vec2::new(-1., 1.), t_min),
+                (Vec2::new(1., 1.), Vec2::new(t_max.x, t_min.y)),
+            ] {
+                let position = particle.position + rotation * (corner * half_size);
+
+                vertices.push(Vertex2d::new(position, color, texcoord));
+            }
+
+            target.draw_vertices(&[0, 1, 2, 1, 2, 3], &vertices, &texture.texture);
+        }
+    }
+
+    fn spawn(&mut self) {
+        if self.particles.len() >= self.config.max_particles {
+            return;
+        }
+
+        let angle = self.random_range(self.config.angle);
+        let speed = self.random_range(self.config.speed);
+
+        let offset_angle = self.random_range((0., std::f32::consts::TAU));
+        // Square root keeps the distribution uniform over the disc
+        let offset_distance = self.random().sqrt() * self.config.spawn_radius;
+
+        let rotation = self.random_range(self.config.rotation);
+        let angular_velocity = self.random_range(self.config.angular_velocity);
+
+        let (min_lifetime, max_lifetime) = self.config.lifetime;
+        let lifetime = min_lifetime.saturating_add(
+            (self.random() * max_lifetime.saturating_sub(min_lifetime).saturating_add(1) as f32)
+                as u32,
+        );
+
+        let frame = (self.random() * self.config.frames.len() as f32) as usize;
+
+        self.particles.push(Particle {
+            position: self.position + Vec2::from_angle(offset_angle) * offset_distance,
+            velocity: Vec2::from_angle(angle) * speed,
+            rotation,
+            angular_velocity,
+            age: 0,
+            lifetime: lifetime.max(1),
+            frame: frame.min(self.config.frames.len().saturating_sub(1)),
+        });
+    }
+
+    /// Random number in [0, 1)
+    fn random(&mut self) -> f32 {
+        // Xorshift32
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 17;
+        self.rng_state ^= self.rng_state << 5;
+
+        (self.rng_state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn random_range(&mut self, (min, max): (f32, f32)) -> f32 {
+        min + self.random() * (max - min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::software::SoftwareCanvas2d;
+    use glam::UVec2;
+
+    fn config(rate: f32, lifetime: u32) -> EmitterConfig {
+        EmitterConfig {
+            rate,
+            lifetime: (lifetime, lifetime),
+            ..EmitterConfig::new()
+        }
+    }
+
+    #[test]
+    fn spawn_rate() {
+        let mut emitter = Emitter::new(config(0.5, 100), Vec2::ZERO);
+
+        for _ in 0..10 {
+            emitter.tick();
+        }
+
+        assert_eq!(emitter.particle_count(), 5);
+
+        let mut emitter = Emitter::new(
+            EmitterConfig {
+                duration: Some(3),
+                ..config(2., 100)
+            },
+            Vec2::ZERO,
+        );
+
+        for _ in 0..10 {
+            emitter.tick();
+        }
+
+        assert_eq!(emitter.particle_count(), 6);
+
+        emitter.paused = true;
+        emitter.reset();
+        emitter.tick();
+        assert_eq!(emitter.particle_count(), 0);
+    }
+
+    #[test]
+    fn bursts() {
+        let mut emitter = Emitter::new(
+            EmitterConfig {
+                bursts: vec![Burst { tick: 2, count: 7 }, Burst { tick: 4, count: 3 }],
+                max_particles: 8,
+                ..config(0., 100)
+            },
+            Vec2::ZERO,
+        );
+
+        emitter.tick();
+        emitter.tick();
+        assert_eq!(emitter.particle_count(), 0);
+
+        emitter.tick();
+        assert_eq!(emitter.particle_count(), 7);
+
+        // Limited by max_particles
+        emitter.tick();
+        emitter.tick();
+        assert_eq!(emitter.particle_count(), 8);
+    }
+
+    #[test]
+    fn lifetime_expiry() {
+        let mut emitter = Emitter::new(
+            EmitterConfig {
+                duration: Some(1),
+                bursts: vec![Burst { tick: 0, count: 5 }],
+                ..config(0., 2)
+            },
+            Vec2::ZERO,
+        );
+
+        emitter.tick();
+        emitter.tick();
+        assert_eq!(emitter.particle_count(), 5);
+        assert!(!emitter.is_finished());
+
+        emitter.tick();
+        assert_eq!(emitter.particle_count(), 0);
+        assert!(emitter.is_finished());
+    }
+
+    #[test]
+    fn full_lifetime_range() {
+        let mut emitter = Emitter::new(
+            EmitterConfig {
+                lifetime: (0, u32::MAX),
+                ..EmitterConfig::new()
+            },
+            Vec2::ZERO,
+        );
+
+        emitter.burst(100);
+        emitter.tick();
+        assert_eq!(emitter.particle_count(), 101);
+    }
+
+    #[test]
+    fn seeded_emitters_match() {
+        let canvas = SoftwareCanvas2d::new(UVec2::splat(16));
+        let white_texture = canvas.white_texture();
+
+        let draw = |seed| {
+            let mut emitter = Emitter::new(
+                EmitterConfig {
+                    spawn_radius: 0.5,
+                    speed: (0.01, 0.02),
+                    size: Curve::constant(0.1),
+                    ..config(3., 10)
+                },
+                Vec2::ZERO,
+            );
+            emitter.set_seed(seed);
+
+            let mut canvas = SoftwareCanvas2d::new(UVec2::splat(16));
+
+            for _ in 0..5 {
+                emitter.tick();
+            }
+
+            emitter.draw_on(&mut canvas, &white_texture);
+            canvas.pixels().to_vec()
+        };
+
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+    }
+
+    #[test]
+    fn removed_frames() {
+        let canvas = SoftwareCanvas2d::new(UVec2::ONE);
+        let white_texture = canvas.white_texture();
+
+        let mut emitter = Emitter::new(
+            EmitterConfig {
+                frames: vec![white_texture.clone(); 4],
+                ..EmitterConfig::new()
+            },
+            Vec2::ZERO,
+        );
+
+        emitter.burst(20);
+        emitter.config.frames.truncate(1);
+
+        let mut canvas = SoftwareCanvas2d::new(UVec2::ONE);
+        emitter.draw_on(&mut canvas, &white_texture);
+    }
+
+    #[test]
+    fn curve_sampling() {
+        let curve = Curve::from_keys(vec![(1., 10.), (0., 0.), (0.5, 2.)]);
+
+        for (t, expected) in [(-1., 0.), (0.25, 1.), (0.5, 2.), (0.75, 6.), (2., 10.)] {
+            assert!((curve.sample(t) - expected).abs() < 1e-6, "{t}");
+        }
+
+        let linear = Curve::linear(Vec4::ZERO, Vec4::ONE);
+        assert!(linear.sample(0.5).abs_diff_eq(Vec4::splat(0.5), 1e-6));
+
+        assert!((Curve::constant(3.).sample(0.9) - 3.).abs() < f32::EPSILON);
+    }
+}
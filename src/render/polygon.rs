@@ -0,0 +1,204 @@
+use glam::Vec2;
+
+/// Triangulate a simple polygon (without holes or self intersections) by ear clipping.
+/// Points can be in clockwise or counterclockwise order, the returned indexes refer to them
+///
+/// # Panics
+///
+/// Panics if there are more than 65535 points
+#[must_use]
+pub fn triangulate(points: &[Vec2]) -> Vec<u16> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+
+    let signed_area = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a.perp_dot(*b))
+        .sum::<f32>();
+
+    // Work in counterclockwise order so that ears are convex corners
+    let count = u16::try_from(points.len()).expect("Error, polygons are limited to 65535 points");
+    let mut remaining = (0..count).collect::<Vec<_>>();
+
+    if signed_area < 0. {
+        remaining.reverse();
+    }
+
+    let mut indexes = Vec::with_capacity((points.len() - 2) * 3);
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+
+        let ear = (0..count).find(|&i| {
+            let prev = points[remaining[(i + count - 1) % count] as usize];
+            let current = points[remaining[i] as usize];
+            let next = points[remaining[(i + 1) % count] as usize];
+
+            if (current - prev).perp_dot(next - current) <= 0. {
+                return false;
+            }
+
+            !remaining.iter().any(|&other| {
+                let point = points[other as usize];
+
+                point != prev
+                    && point != current
+                    && point != next
+                    && in_triangle(point, prev, current, next)
+            })
+        });
+
+        // Degenerate polygons have no ear left, clip any corner to always terminate
+        let ear = ear.unwrap_or(0);
+
+        indexes.extend_from_slice(&[
+            remaining[(ear + count - 1) % count],
+            remaining[ear],
+            remaining[(ear + 1) % count],
+        ]);
+
+        remaining.remove(ear);
+    }
+
+    indexes.extend_from_slice(&remaining);
+
+    indexes
+}
+
+fn in_triangle(point: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    let ab = (b - a).perp_dot(point - a);
+    let bc = (c - b).perp_dot(point - b);
+    let ca = (a - c).perp_dot(point - c);
+
+    ab >= 0. && bc >= 0. && ca >= 0.
+}
+
+/// Points of a regular polygon, the first one being on the right of the center
+#[must_use]
+pub fn regular(center: Vec2, radius: f32, sides: u16) -> Vec<Vec2> {
+    let step_size = std::f32::consts::TAU / sides as f32;
+
+    (0..sides)
+        .map(|i| center + Vec2::from_angle(i as f32 * step_size) * radius)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(points: &[Vec2]) -> f32 {
+        points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(a, b)| a.perp_dot(*b))
+            .sum::<f32>()
+            .abs()
+            / 2.
+    }
+
+    /// Check the triangle count and that the triangles cover the polygon area with the same winding
+    fn assert_triangulation(points: &[Vec2]) {
+        let indexes = triangulate(points);
+        assert_eq!(indexes.len(), (points.len() - 2) * 3);
+
+        let triangles = indexes
+            .chunks_exact(3)
+            .map(|triangle| {
+                triangle
+                    .iter()
+                    .map(|&i| points[i as usize])
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let total = triangles.iter().map(|triangle| area(triangle)).sum::<f32>();
+        assert!(
+            (total - area(points)).abs() < area(points) * 1e-5,
+            "{total}"
+        );
+
+        for triangle in &triangles {
+            assert!((triangle[1] - triangle[0]).perp_dot(triangle[2] - triangle[1]) >= 0.);
+        }
+    }
+
+    #[test]
+    fn convex() {
+        assert_triangulation(&regular(Vec2::ZERO, 2., 9));
+        assert_triangulation(&[Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y]);
+    }
+
+    #[test]
+    fn concave() {
+        // An arrow pointing up
+        assert_triangulation(&[
+            Vec2::new(0., 0.),
+            Vec2::new(2., 2.),
+            Vec2::new(4., 0.),
+            Vec2::new(2., 4.),
+        ]);
+
+        // A comb with three teeth
+        assert_triangulation(&[
+            Vec2::new(0., 0.),
+            Vec2::new(5., 0.),
+            Vec2::new(5., 3.),
+            Vec2::new(4., 3.),
+            Vec2::new(4., 1.),
+            Vec2::new(3., 1.),
+            Vec2::new(3., 3.),
+            Vec2::new(2., 3.),
+            Vec2::new(2., 1.),
+            Vec2::new(1., 1.),
+            Vec2::new(1., 3.),
+            Vec2::new(0., 3.),
+        ]);
+    }
+
+    #[test]
+    fn ladder() {
+        let rows = (0..40).map(|i| i as f32 * 64. / 39.);
+        let points = rows
+            .clone()
+            .map(|y| Vec2::new(64., y))
+            .chain(rows.rev().map(|y| Vec2::new(0., y)))
+            .collect::<Vec<_>>();
+
+        assert_triangulation(&points);
+    }
+
+    #[test]
+    fn clockwise() {
+        let mut points = vec![
+            Vec2::new(0., 0.),
+            Vec2::new(2., 2.),
+            Vec2::new(4., 0.),
+            Vec2::new(2., 4.),
+        ];
+        points.reverse();
+
+        assert_triangulation(&points);
+    }
+
+    #[test]
+    fn degenerate() {
+        assert!(triangulate(&[Vec2::ZERO, Vec2::X]).is_empty());
+
+        // Collinear points on the edges still give every triangle
+        assert_triangulation(&[
+            Vec2::new(0., 0.),
+            Vec2::new(1., 0.),
+            Vec2::new(2., 0.),
+            Vec2::new(2., 2.),
+            Vec2::new(0., 2.),
+        ]);
+
+        // All points on a line terminate with zero area triangles
+        let line = [Vec2::ZERO, Vec2::X, Vec2::new(2., 0.), Vec2::new(3., 0.)];
+        let indexes = triangulate(&line);
+        assert_eq!(indexes.len(), 6);
+    }
+}
@@ -0,0 +1,429 @@
+//! Recording of draw calls into a serializable command list
+//!
+//! A `DrawRecorder` is drawn on like a canvas, its commands can be replayed on a `Canvas2d` later,
+//! saved as JSON to be attached to a bug report, or compared between two frames with `DrawList::diff`.
+//! Textures can't be serialized, commands refer to them by `TextureId` and the textures are given back when replaying
+
+use super::{
+    canvas2d::{BlendMode, Canvas2d, DrawTarget2d, Texture},
+    vertex::Vertex2d,
+};
+use glam::{Mat3, Vec4};
+use serde::{Deserialize, Serialize};
+
+/// Index of a texture in the order textures were first drawn by a recorder
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub struct TextureId(pub u32);
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum DrawCommand {
+    /// Triangles, as given to `DrawTarget2d::draw_vertices`
+    Draw {
+        indexes: Vec<u16>,
+        vertices: Vec<Vertex2d>,
+        texture: TextureId,
+    },
+    /// Columns of the new view matrix
+    SetViewMatrix([f32; 9]),
+    SetBlendMode(BlendMode),
+    Clear([f32; 4]),
+}
+
+/// Difference between two command lists at an index
+#[derive(Clone, PartialEq, Debug)]
+pub enum CommandDiff {
+    Changed {
+        index: usize,
+        before: DrawCommand,
+        after: DrawCommand,
+    },
+    Added {
+        index: usize,
+        command: DrawCommand,
+    },
+    Removed {
+        index: usize,
+        command: DrawCommand,
+    },
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct DrawList {
+    pub commands: Vec<DrawCommand>,
+}
+
+impl DrawList {
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Read a list saved by `to_json`
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if the text isn't a valid command list
+    pub fn from_json(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+
+    /// Execute the commands on the canvas, `textures` being indexed by the texture ids of the commands
+    ///
+    /// # Panics
+    ///
+    /// Panics if a texture id has no texture
+    pub fn replay(&self, canvas: &mut Canvas2d, textures: &[Texture]) {
+        for command in &self.commands {
+            match command {
+                DrawCommand::Draw {
+                    indexes,
+                    vertices,
+                    texture,
+                } => canvas.draw_vertices(
+                    indexes,
+                    vertices,
+                    textures
+                        .get(texture.0 as usize)
+                        .expect("Error, missing texture for replay"),
+                ),
+                DrawCommand::SetViewMatrix(matrix) => {
+                    canvas.set_view_matrix(Mat3::from_cols_array(matrix));
+                }
+                DrawCommand::SetBlendMode(blend_mode) => canvas.set_blend_mode(*blend_mode),
+                DrawCommand::Clear(color) => {
+                    // Pending draws must not end up above the cleared canvas
+                    canvas.flush();
+                    canvas.clear(Vec4::from_array(*color));
+                }
+            }
+        }
+    }
+
+    /// Commands that differ from the ones of `other`, `self` being the earlier list.
+    /// Commands are aligned so that an inserted or removed command is reported alone, unmatched commands between
+    /// two aligned ones are reported as changed. Removed indexes refer to `self`, added and changed ones to `other`
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> Vec<CommandDiff> {
+        let (before, after) = (&self.commands, &other.commands);
+
+        let prefix = before
+            .iter()
+            .zip(after)
+            .take_while(|(before, after)| before == after)
+            .count();
+        let suffix = before[prefix..]
+            .iter()
+            .rev()
+            .zip(after[prefix..].iter().rev())
+            .take_while(|(before, after)| before == after)
+            .count();
+
+        let before = &before[prefix..before.len() - suffix];
+        let after = &after[prefix..after.len() - suffix];
+
+        let mut matches = common_subsequence(before, after);
+        matches.push((before.len(), after.len()));
+
+        let mut diffs = Vec::new();
+        let (mut i, mut j) = (0, 0);
+
+        for (next_i, next_j) in matches {
+            let changed = (next_i - i).min(next_j - j);
+
+            diffs.extend(
+                before[i..i + changed]
+                    .iter()
+                    .zip(&after[j..j + changed])
+                    .enumerate()
+                    .map(|(k, (before, after))| CommandDiff::Changed {
+                        index: prefix + j + k,
+                        before: before.clone(),
+                        after: after.clone(),
+                    }),
+            );
+
+            diffs.extend(
+                before[i + changed..next_i]
+                    .iter()
+                    .enumerate()
+                    .map(|(k, command)| CommandDiff::Removed {
+                        index: prefix + i + changed + k,
+                        command: command.clone(),
+                    }),
+            );
+
+            diffs.extend(
+                after[j + changed..next_j]
+                    .iter()
+                    .enumerate()
+                    .map(|(k, command)| CommandDiff::Added {
+                        index: prefix + j + changed + k,
+                        command: command.clone(),
+                    }),
+            );
+
+            (i, j) = (next_i + 1, next_j + 1);
+        }
+
+        diffs
+    }
+}
+
+/// Largest number of cells of the table used to align commands, longer lists are compared index by index
+const MAX_DIFF_CELLS: usize = 1 << 22;
+
+/// Longest common subsequence of two command lists as (index in `a`, index in `b`) pairs
+fn common_subsequence(a: &[DrawCommand], b: &[DrawCommand]) -> Vec<(usize, usize)> {
+    if a.len().saturating_mul(b.len()) > MAX_DIFF_CELLS {
+        return Vec::new();
+    }
+
+    let width = b.len() + 1;
+    // Length of the common subsequence of a[i..] and b[j..] at i * width + j
+    let mut lengths = vec![0u32; (a.len() + 1) * width];
+
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i * width + j] = if a[i] == b[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut matches = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            matches.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    matches
+}
+
+/// A draw target recording the draw calls instead of drawing them
+pub struct DrawRecorder {
+    list: DrawList,
+    textures: Vec<Texture>,
+    view_matrix: Mat3,
+    blend_mode: BlendMode,
+}
+
+impl DrawRecorder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            list: DrawList::default(),
+            textures: Vec::new(),
+            view_matrix: Mat3::IDENTITY,
+            blend_mode: BlendMode::Alpha,
+        }
+    }
+
+    /// Commands recorded since the creation or the last `reset`
+    #[must_use]
+    pub const fn list(&self) -> &DrawList {
+        &self.list
+    }
+
+    /// Take the recorded commands and start a new list, texture ids stay the same so lists of successive frames can be compared
+    pub fn reset(&mut self) -> DrawList {
+        std::mem::take(&mut self.list)
+    }
+
+    /// Textures drawn so far, indexed by their id
+    #[must_use]
+    pub fn textures(&self) -> &[Texture] {
+        &self.textures
+    }
+
+    /// Id of a texture, a new one is given to textures that were never drawn
+    pub fn texture_id(&mut self, texture: &Texture) -> TextureId {
+        let index = self
+            .textures
+            .iter()
+            .position(|known| known.is_same(texture))
+            .unwrap_or_else(|| {
+                self.textures.push(texture.clone());
+                self.textures.len() - 1
+            });
+
+        TextureId(index as u32)
+    }
+
+    /// Record a view matrix change, the view matrix of the canvas is used until the first one when replaying
+    pub fn set_view_matrix(&mut self, view_matrix: Mat3) {
+        self.view_matrix = view_matrix;
+        self.push(DrawCommand::SetViewMatrix(view_matrix.to_cols_array()));
+    }
+
+    #[must_use]
+    pub const fn view_matrix(&self) -> Mat3 {
+        self.view_matrix
+    }
+
+    /// Record a blend mode change, the blend mode of the canvas is used until the first one when replaying
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+        self.push(DrawCommand::SetBlendMode(blend_mode));
+    }
+
+    #[must_use]
+    pub const fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    pub fn clear(&mut self, color: Vec4) {
+        self.push(DrawCommand::Clear(color.to_array()));
+    }
+
+    /// Replay the recorded commands on a canvas with the recorded textures
+    pub fn replay(&self, canvas: &mut Canvas2d) {
+        self.list.replay(canvas, &self.textures);
+    }
+
+    fn push(&mut self, command: DrawCommand) {
+        self.list.commands.push(command);
+    }
+}
+
+impl DrawTarget2d for DrawRecorder {
+    fn draw_vertices(&mut self, indexes: &[u16], vertices: &[Vertex2d], texture: &Texture) {
+        let texture = self.texture_id(texture);
+
+        self.push(DrawCommand::Draw {
+            indexes: indexes.to_vec(),
+            vertices: vertices.to_vec(),
+            texture,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::software::SoftwareCanvas2d;
+    use glam::{UVec2, Vec2};
+
+    fn clear(value: f32) -> DrawCommand {
+        DrawCommand::Clear([value; 4])
+    }
+
+    fn list(values: &[f32]) -> DrawList {
+        DrawList {
+            commands: values.iter().map(|&value| clear(value)).collect(),
+        }
+    }
+
+    #[test]
+    fn diff_same_length() {
+        assert_eq!(list(&[1., 2., 3.]).diff(&list(&[1., 2., 3.])), []);
+
+        assert_eq!(
+            list(&[1., 2., 3.]).diff(&list(&[1., 5., 3.])),
+            [CommandDiff::Changed {
+                index: 1,
+                before: clear(2.),
+                after: clear(5.),
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_insertions_and_removals() {
+        // An inserted command doesn't shift the comparison of the following ones
+        assert_eq!(
+            list(&[1., 2., 3., 4.]).diff(&list(&[1., 9., 2., 3., 4.])),
+            [CommandDiff::Added {
+                index: 1,
+                command: clear(9.),
+            }]
+        );
+
+        assert_eq!(
+            list(&[1., 2., 3., 4.]).diff(&list(&[1., 3., 4.])),
+            [CommandDiff::Removed {
+                index: 1,
+                command: clear(2.),
+            }]
+        );
+
+        assert_eq!(
+            list(&[1., 2., 3., 4., 5.]).diff(&list(&[0., 2., 6., 4., 5., 7.])),
+            [
+                CommandDiff::Changed {
+                    index: 0,
+                    before: clear(1.),
+                    after: clear(0.),
+                },
+                CommandDiff::Changed {
+                    index: 2,
+                    before: clear(3.),
+                    after: clear(6.),
+                },
+                CommandDiff::Added {
+                    index: 5,
+                    command: clear(7.),
+                },
+            ]
+        );
+
+        assert_eq!(
+            list(&[]).diff(&list(&[1.])),
+            [CommandDiff::Added {
+                index: 0,
+                command: clear(1.),
+            }]
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let canvas = SoftwareCanvas2d::new(UVec2::ONE);
+        let mut recorder = DrawRecorder::new();
+
+        recorder.clear(Vec4::new(0.1, 0.2, 0.3, 1.));
+        recorder.set_view_matrix(Mat3::from_scale(Vec2::new(2., 0.5)));
+        recorder.set_blend_mode(BlendMode::Additive);
+        recorder.draw_rect(Vec2::ZERO, Vec2::ONE, Vec4::ONE, &canvas.white_texture());
+
+        let list = recorder.reset();
+        assert_eq!(list.commands.len(), 4);
+        assert!(recorder.list().commands.is_empty());
+
+        assert_eq!(DrawList::from_json(&list.to_json()).unwrap(), list);
+        assert!(DrawList::from_json("{\"commands\": [{\"Clear\": [1]}]}").is_err());
+    }
+
+    #[test]
+    fn texture_ids() {
+        let canvas = SoftwareCanvas2d::new(UVec2::ONE);
+        let white = canvas.white_texture().texture;
+        let other = canvas.create_texture(1, 1, &[0; 4]).texture;
+
+        let mut recorder = DrawRecorder::new();
+
+        assert_eq!(recorder.texture_id(&white), TextureId(0));
+        assert_eq!(recorder.texture_id(&other), TextureId(1));
+        assert_eq!(recorder.texture_id(&white.clone()), TextureId(0));
+        assert_eq!(recorder.textures().len(), 2);
+
+        recorder.draw_vertices(&[], &[], &other);
+        assert!(matches!(
+            recorder.list().commands[..],
+            [DrawCommand::Draw {
+                texture: TextureId(1),
+                ..
+            }]
+        ));
+    }
+}
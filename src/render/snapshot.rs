@@ -0,0 +1,669 @@
+//! Golden image testing, rendered frames are compared with reference PNG files
+//!
+//! Frames are RGBA bytes with rows going from top to bottom, like the ones of `SoftwareCanvas2d::pixels`.
+//! When the `MARMALADE_BLESS` environment variable is set, frames are written as the new references instead of being compared.
+//! When a comparison fails, the frame and an image highlighting the differing pixels in red are written next to the reference
+//! as `name.actual.png` and `name.diff.png`
+
+use super::software::SoftwareCanvas2d;
+use glam::UVec2;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable enabling the update of reference images
+pub const BLESS_VAR: &str = "MARMALADE_BLESS";
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// The reference isn't a PNG or uses a feature this decoder doesn't handle, like interlacing or 16 bits channels
+    InvalidPng(String),
+    /// There is no reference image yet, it can be created by running the test with `MARMALADE_BLESS` set
+    MissingReference(PathBuf),
+    SizeMismatch {
+        expected: UVec2,
+        actual: UVec2,
+    },
+    /// Some channels differ from the reference by more than the tolerance
+    Mismatch {
+        differing_pixels: usize,
+        max_difference: u8,
+    },
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Compare the canvas content with the reference image at the given path, see `compare`
+///
+/// # Panics
+///
+/// Panics if the comparison fails
+#[track_caller]
+pub fn assert_snapshot(path: impl AsRef<Path>, canvas: &SoftwareCanvas2d, tolerance: u8) {
+    let path = path.as_ref();
+
+    if let Err(error) = compare(path, canvas.size(), canvas.pixels(), tolerance) {
+        panic!("Snapshot {} doesn't match: {error:?}", path.display());
+    }
+}
+
+/// Compare a frame with the reference image at the given path, channels can differ by up to `tolerance`.
+/// Writes the frame as the reference instead when `MARMALADE_BLESS` is set
+///
+/// # Errors
+///
+/// Returns Err if the images differ or if the files couldn't be read or written
+///
+/// # Panics
+///
+/// Panics if the size doesn't match the pixels
+pub fn compare(
+    path: impl AsRef<Path>,
+    size: UVec2,
+    pixels: &[u8],
+    tolerance: u8,
+) -> Result<(), SnapshotError> {
+    assert_eq!(
+        pixels.len(),
+        (size.x * size.y * 4) as usize,
+        "Error, frame size doesn't match its pixels"
+    );
+
+    let path = path.as_ref();
+
+    if env::var_os(BLESS_VAR).is_some() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, encode_png(size, pixels))?;
+
+        return Ok(());
+    }
+
+    let reference = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            fs::write(sibling(path, "actual"), encode_png(size, pixels))?;
+            return Err(SnapshotError::MissingReference(path.into()));
+        }
+        Err(error) => return Err(error.into()),
+    };
+
+    let (expected_size, expected) = decode_png(&reference)?;
+
+    if expected_size != size {
+        fs::write(sibling(path, "actual"), encode_png(size, pixels))?;
+
+        return Err(SnapshotError::SizeMismatch {
+            expected: expected_size,
+            actual: size,
+        });
+    }
+
+    let mut differing_pixels = 0;
+    let mut max_difference = 0;
+    let mut diff = Vec::with_capacity(pixels.len());
+
+    for (actual, expected) in pixels.chunks_exact(4).zip(expected.chunks_exact(4)) {
+        let difference = actual
+            .iter()
+            .zip(expected)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap_or(0);
+
+        max_difference = max_difference.max(difference);
+
+        if difference > tolerance {
+            differing_pixels += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            // Matching pixels are a faded gray version of the reference
+            let luma =
+                (u16::from(expected[0]) + u16::from(expected[1]) + u16::from(expected[2])) / 12;
+            let luma = luma as u8;
+            diff.extend_from_slice(&[luma, luma, luma, 255]);
+        }
+    }
+
+    if differing_pixels == 0 {
+        return Ok(());
+    }
+
+    fs::write(sibling(path, "actual"), encode_png(size, pixels))?;
+    fs::write(sibling(path, "diff"), encode_png(size, &diff))?;
+
+    Err(SnapshotError::Mismatch {
+        differing_pixels,
+        max_difference,
+    })
+}
+
+/// Encode RGBA pixels, rows going from top to bottom, as a PNG file
+///
+/// # Panics
+///
+/// Panics if the size doesn't match the pixels
+#[must_use]
+pub fn encode_png(size: UVec2, pixels: &[u8]) -> Vec<u8> {
+    assert_eq!(
+        pixels.len(),
+        (size.x * size.y * 4) as usize,
+        "Error, image size doesn't match its pixels"
+    );
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&size.x.to_be_bytes());
+    header.extend_from_slice(&size.y.to_be_bytes());
+    // 8 bits RGBA, default compression and filtering, no interlacing
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // Every row starts with its filter type, rows are not filtered
+    let mut data = Vec::with_capacity(pixels.len() + size.y as usize);
+
+    for row in pixels.chunks_exact(size.x as usize * 4) {
+        data.push(0);
+        data.extend_from_slice(row);
+    }
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut png, *b"IHDR", &header);
+    write_chunk(
+        &mut png,
+        *b"IDAT",
+        &miniz_oxide::deflate::compress_to_vec_zlib(&data, 6),
+    );
+    write_chunk(&mut png, *b"IEND", &[]);
+
+    png
+}
+
+/// Decode a non interlaced 8 bits PNG file to RGBA pixels, rows going from top to bottom
+///
+/// # Errors
+///
+/// Returns Err if the file isn't a valid PNG or uses a palette, 16 bits channels or interlacing
+pub fn decode_png(bytes: &[u8]) -> Result<(UVec2, Vec<u8>), SnapshotError> {
+    let invalid = |message: &str| SnapshotError::InvalidPng(message.into());
+
+    let mut rest = bytes
+        .strip_prefix(&PNG_SIGNATURE)
+        .ok_or_else(|| invalid("Missing signature"))?;
+
+    let mut header = None;
+    let mut data = Vec::new();
+
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let kind = &rest[4..8];
+        let content = rest
+            .get(8..8 + length)
+            .ok_or_else(|| invalid("Truncated chunk"))?;
+
+        match kind {
+            b"IHDR" => header = Some(content),
+            b"IDAT" => data.extend_from_slice(content),
+            b"IEND" => break,
+            _ => {}
+        }
+
+        rest = rest.get(12 + length..).unwrap_or_default();
+    }
+
+    let header = header
+        .filter(|header| header.len() == 13)
+        .ok_or_else(|| invalid("Missing header"))?;
+
+    let size = UVec2::new(
+        u32::from_be_bytes([header[0], header[1], header[2], header[3]]),
+        u32::from_be_bytes([header[4], header[5], header[6], header[7]]),
+    );
+
+    let channels = match (header[8], header[9], header[12]) {
+        (8, 0, 0) => 1,
+        (8, 2, 0) => 3,
+        (8, 4, 0) => 2,
+        (8, 6, 0) => 4,
+        _ => return Err(invalid("Unsupported color type, bit depth or interlacing")),
+    };
+
+    let data = miniz_oxide::inflate::decompress_to_vec_zlib(&data)
+        .map_err(|error| invalid(&error.to_string()))?;
+
+    let stride = size.x as usize * channels;
+
+    if data.len() != (stride + 1) * size.y as usize {
+        return Err(invalid("Wrong image data size"));
+    }
+
+    let mut previous = vec![0; stride];
+    let mut pixels = Vec::with_capacity((size.x * size.y * 4) as usize);
+
+    for line in data.chunks_exact(stride + 1) {
+        let row = unfilter(line[0], &line[1..], &previous, channels)
+            .ok_or_else(|| invalid("Unknown filter type"))?;
+
+        for pixel in row.chunks_exact(channels) {
+            pixels.extend_from_slice(&match *pixel {
+                [gray] => [gray, gray, gray, 255],
+                [gray, alpha] => [gray, gray, gray, alpha],
+                [r, g, b] => [r, g, b, 255],
+                [r, g, b, a] => [r, g, b, a],
+                _ => unreachable!(),
+            });
+        }
+
+        previous = row;
+    }
+
+    Ok((size, pixels))
+}
+
+/// Path of a file written next to a reference, `name.png` becoming `name.suffix.png`
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}.{suffix}.png"))
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: [u8; 4], content: &[u8]) {
+    png.extend_from_slice(&(content.len() as u32).to_be_bytes());
+
+    let start = png.len();
+    png.extend_from_slice(&kind);
+    png.extend_from_slice(content);
+
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(u32::MAX, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+            if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xEDB8_8320
+            }
+        })
+    })
+}
+
+/// Reverse the filter of a row, `channels` being the bytes per pixel
+fn unfilter(filter: u8, row: &[u8], previous: &[u8], channels: usize) -> Option<Vec<u8>> {
+    let mut result = Vec::with_capacity(row.len());
+
+    for (index, &byte) in row.iter().enumerate() {
+        let left = if index >= channels {
+            result[index - channels]
+        } else {
+            0
+        };
+        let up = previous[index];
+        let up_left = if index >= channels {
+            previous[index - channels]
+        } else {
+            0
+        };
+
+        let predictor = match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => u16::midpoint(u16::from(left), u16::from(up)) as u8,
+            4 => paeth(left, up, up_left),
+            _ => return None,
+        };
+
+        result.push(byte.wrapping_add(predictor));
+    }
+
+    Some(result)
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+
+    let distance_left = (estimate - i16::from(left)).abs();
+    let distance_up = (estimate - i16::from(up)).abs();
+    let distance_up_left = (estimate - i16::from(up_left)).abs();
+
+    if distance_left <= distance_up && distance_left <= distance_up_left {
+        left
+    } else if distance_up <= distance_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        font::{self, FontStack},
+        render::{canvas2d::DrawTarget2d, gradient::Gradient},
+    };
+    use glam::{Vec2, Vec4};
+    use std::{
+        process,
+        sync::{Mutex, MutexGuard},
+    };
+
+    /// The bless test changes the environment, snapshot tests must not run at the same time
+    static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+    fn lock_environment() -> MutexGuard<'static, ()> {
+        ENVIRONMENT
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn reference(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/snapshots")
+            .join(format!("{name}.png"))
+    }
+
+    fn scene() -> SoftwareCanvas2d {
+        let mut canvas = SoftwareCanvas2d::new(UVec2::splat(64));
+        canvas.pixel_perfect_view();
+        canvas.clear(Vec4::new(0.1, 0.1, 0.2, 1.));
+        canvas
+    }
+
+    /// Pixels with varied values so that every filter predicts something different
+    fn test_pixels(size: UVec2, channels: usize) -> Vec<u8> {
+        (0..size.x * size.y * channels as u32)
+            .map(|i| (i * 37 + i * i / 7) as u8)
+            .collect()
+    }
+
+    /// Filter a row like a PNG encoder, the opposite of `unfilter`
+    fn filter(filter: u8, row: &[u8], previous: &[u8], channels: usize) -> Vec<u8> {
+        (0..row.len())
+            .map(|index| {
+                let left = if index >= channels {
+                    row[index - channels]
+                } else {
+                    0
+                };
+                let up = previous[index];
+                let up_left = if index >= channels {
+                    previous[index - channels]
+                } else {
+                    0
+                };
+
+                let predictor = match filter {
+                    0 => 0,
+                    1 => left,
+                    2 => up,
+                    3 => u16::midpoint(u16::from(left), u16::from(up)) as u8,
+                    _ => paeth(left, up, up_left),
+                };
+
+                row[index].wrapping_sub(predictor)
+            })
+            .collect()
+    }
+
+    /// PNG file whose rows use the filter types in turn
+    fn filtered_png(size: UVec2, color_type: u8, channels: usize, pixels: &[u8]) -> Vec<u8> {
+        let stride = size.x as usize * channels;
+
+        let mut data = Vec::new();
+        let mut previous = vec![0; stride];
+
+        for (index, row) in pixels.chunks_exact(stride).enumerate() {
+            let filter_type = (index % 5) as u8;
+
+            data.push(filter_type);
+            data.extend(filter(filter_type, row, &previous, channels));
+
+            previous = row.to_vec();
+        }
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&size.x.to_be_bytes());
+        header.extend_from_slice(&size.y.to_be_bytes());
+        header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut png, *b"IHDR", &header);
+        write_chunk(
+            &mut png,
+            *b"IDAT",
+            &miniz_oxide::deflate::compress_to_vec_zlib(&data, 6),
+        );
+        write_chunk(&mut png, *b"IEND", &[]);
+        png
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let size = UVec2::new(7, 5);
+        let pixels = test_pixels(size, 4);
+
+        assert_eq!(
+            decode_png(&encode_png(size, &pixels)).unwrap(),
+            (size, pixels)
+        );
+    }
+
+    #[test]
+    fn png_filter_types() {
+        let size = UVec2::new(6, 10);
+
+        // Gray, RGB, gray with alpha and RGBA
+        for (color_type, channels) in [(0, 1), (2, 3), (4, 2), (6, 4)] {
+            let pixels = test_pixels(size, channels);
+            let (decoded_size, decoded) =
+                decode_png(&filtered_png(size, color_type, channels, &pixels)).unwrap();
+
+            let expected = pixels
+                .chunks_exact(channels)
+                .flat_map(|pixel| match *pixel {
+                    [gray] => [gray, gray, gray, 255],
+                    [gray, alpha] => [gray, gray, gray, alpha],
+                    [r, g, b] => [r, g, b, 255],
+                    [r, g, b, a] => [r, g, b, a],
+                    _ => unreachable!(),
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(decoded_size, size);
+            assert_eq!(decoded, expected, "color type {color_type}");
+        }
+    }
+
+    #[test]
+    fn png_errors() {
+        let png = encode_png(UVec2::ONE, &[1, 2, 3, 4]);
+
+        assert!(matches!(
+            decode_png(&png[1..]),
+            Err(SnapshotError::InvalidPng(_))
+        ));
+        assert!(matches!(
+            decode_png(&png[..40]),
+            Err(SnapshotError::InvalidPng(_))
+        ));
+
+        // 16 bits channels
+        let mut sixteen_bits = png.clone();
+        sixteen_bits[24] = 16;
+        assert!(decode_png(&sixteen_bits).is_err());
+
+        let mut data = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut data, *b"IHDR", &png[16..29]);
+        write_chunk(
+            &mut data,
+            *b"IDAT",
+            &miniz_oxide::deflate::compress_to_vec_zlib(&[5, 1, 2, 3, 4], 6),
+        );
+        assert!(decode_png(&data).is_err());
+
+        // Known checksum of an empty IEND chunk
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+    }
+
+    #[test]
+    fn bless() {
+        let _lock = lock_environment();
+
+        let directory = env::temp_dir().join(format!("marmalade-snapshot-{}", process::id()));
+        let path = directory.join("bless.png");
+        let size = UVec2::new(3, 2);
+        let pixels = test_pixels(size, 4);
+
+        fs::create_dir_all(&directory).unwrap();
+        let previous = env::var_os(BLESS_VAR);
+
+        env::remove_var(BLESS_VAR);
+        assert!(matches!(
+            compare(&path, size, &pixels, 0),
+            Err(SnapshotError::MissingReference(_))
+        ));
+        assert!(directory.join("bless.actual.png").exists());
+
+        env::set_var(BLESS_VAR, "1");
+        compare(&path, size, &pixels, 0).unwrap();
+        env::remove_var(BLESS_VAR);
+
+        assert_eq!(
+            decode_png(&fs::read(&path).unwrap()).unwrap(),
+            (size, pixels.clone())
+        );
+        compare(&path, size, &pixels, 0).unwrap();
+
+        let mut changed = pixels;
+        changed[0] = changed[0].wrapping_add(10);
+
+        assert!(compare(&path, size, &changed, 10).is_ok());
+        assert!(matches!(
+            compare(&path, size, &changed, 9),
+            Err(SnapshotError::Mismatch {
+                differing_pixels: 1,
+                max_difference: 10
+            })
+        ));
+        assert!(directory.join("bless.actual.png").exists());
+        assert!(directory.join("bless.diff.png").exists());
+
+        if let Some(previous) = previous {
+            env::set_var(BLESS_VAR, previous);
+        }
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn golden_text() {
+        let _lock = lock_environment();
+
+        let mut canvas = scene();
+        let mut font = FontStack::new(font::from_bytes(font::MONOGRAM));
+        let white = canvas.white_texture();
+
+        canvas.draw_text(
+            Vec2::new(4., 36.),
+            24.,
+            "Hi!",
+            &mut font,
+            Vec4::new(1., 0.8, 0.2, 1.),
+            &white,
+        );
+        canvas.draw_text(Vec2::new(4., 8.), 12., "gyp", &mut font, Vec4::ONE, &white);
+
+        assert_snapshot(reference("text"), &canvas, 1);
+    }
+
+    #[test]
+    fn golden_regular() {
+        let _lock = lock_environment();
+
+        let mut canvas = scene();
+        let white = canvas.white_texture();
+
+        canvas.draw_regular(
+            Vec2::splat(20.),
+            14.,
+            3,
+            Vec4::new(1., 0.3, 0.3, 1.),
+            &white,
+        );
+        canvas.draw_regular(
+            Vec2::new(44., 20.),
+            14.,
+            6,
+            Vec4::new(0.3, 1., 0.3, 1.),
+            &white,
+        );
+        canvas.draw_regular(
+            Vec2::new(32., 44.),
+            16.,
+            32,
+            Vec4::new(0.3, 0.3, 1., 0.6),
+            &white,
+        );
+
+        assert_snapshot(reference("regular"), &canvas, 1);
+    }
+
+    #[test]
+    fn golden_polygon() {
+        let _lock = lock_environment();
+
+        let mut canvas = scene();
+        let white = canvas.white_texture();
+
+        // Concave star
+        let star = (0..10)
+            .map(|i| {
+                let radius = if i % 2 == 0 { 28. } else { 12. };
+                Vec2::splat(32.) + Vec2::from_angle(i as f32 * std::f32::consts::TAU / 10.) * radius
+            })
+            .collect::<Vec<_>>();
+
+        canvas.draw_polygon(&star, Vec4::new(1., 0.9, 0.3, 1.), &white);
+
+        assert_snapshot(reference("polygon"), &canvas, 1);
+    }
+
+    #[test]
+    fn golden_gradients() {
+        let _lock = lock_environment();
+
+        let mut canvas = scene();
+        let white = canvas.white_texture();
+
+        let linear = Gradient::linear(
+            Vec2::new(4., 0.),
+            Vec2::new(60., 0.),
+            vec![
+                (0., Vec4::new(1., 0., 0., 1.)),
+                (0.5, Vec4::new(1., 1., 0., 1.)),
+                (1., Vec4::new(0., 0., 1., 1.)),
+            ],
+        );
+        canvas.draw_gradient_rect(Vec2::new(4., 40.), Vec2::new(56., 20.), &linear, &white);
+
+        let radial = Gradient::radial(
+            Vec2::new(32., 18.),
+            16.,
+            vec![(0., Vec4::ONE), (1., Vec4::new(0., 0.5, 1., 0.))],
+        );
+        canvas.draw_gradient_regular(Vec2::new(32., 18.), 16., 24, &radial, &white);
+
+        assert_snapshot(reference("gradients"), &canvas, 1);
+    }
+}
@@ -0,0 +1,442 @@
+use super::{
+    camera2d::Camera2d,
+    canvas2d::{BlendMode, DrawTarget2d, Texture, TextureRect},
+    vertex::Vertex2d,
+};
+use glam::{Mat3, UVec2, Vec2, Vec3, Vec4};
+use std::rc::Rc;
+
+/// RGBA pixels of a texture drawn by a `SoftwareCanvas2d`, rows going from top to bottom with straight alpha
+pub struct SoftwareTexture {
+    size: UVec2,
+    pixels: Vec<u8>,
+}
+
+impl SoftwareTexture {
+    #[must_use]
+    pub const fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Color of the texel at the given texture coordinates, with nearest filtering and clamped to the edges
+    fn sample(&self, texcoord: Vec2) -> Vec4 {
+        let position = (texcoord * self.size.as_vec2()).floor();
+        let x = (position.x.max(0.) as u32).min(self.size.x - 1);
+        let y = (position.y.max(0.) as u32).min(self.size.y - 1);
+
+        let index = ((y * self.size.x + x) * 4) as usize;
+        let texel = &self.pixels[index..index + 4];
+
+        Vec4::new(
+            f32::from(texel[0]),
+            f32::from(texel[1]),
+            f32::from(texel[2]),
+            f32::from(texel[3]),
+        ) / 255.
+    }
+}
+
+/// A drawing surface rendered on the CPU into an RGBA buffer, it doesn't need a browser or a GPU
+/// so scenes can be drawn natively, for example to compare them with reference images in tests.
+///
+/// It draws like a `Canvas2d` with the same view matrices and blend modes, textures use nearest filtering
+pub struct SoftwareCanvas2d {
+    size: UVec2,
+    /// RGBA bytes with premultiplied colors, rows going from top to bottom
+    pixels: Vec<u8>,
+    view_matrix: Mat3,
+    blend_mode: BlendMode,
+    white_texture: TextureRect,
+}
+
+impl SoftwareCanvas2d {
+    /// Create a transparent black canvas of the given size in pixels
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size is zero
+    #[must_use]
+    pub fn new(size: UVec2) -> Self {
+        assert!(size.x > 0 && size.y > 0, "Error, canvas size is zero");
+
+        let white_texture = Texture::Software(Rc::new(SoftwareTexture {
+            size: UVec2::ONE,
+            pixels: vec![255; 4],
+        }));
+
+        Self {
+            size,
+            pixels: vec![0; (size.x * size.y * 4) as usize],
+            view_matrix: Mat3::IDENTITY,
+            blend_mode: BlendMode::Alpha,
+            white_texture: TextureRect::new(white_texture),
+        }
+    }
+
+    #[must_use]
+    pub const fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Get a single pixel white texture, this is used to draw objects that have a color and no texture
+    #[must_use]
+    pub fn white_texture(&self) -> TextureRect {
+        self.white_texture.clone()
+    }
+
+    /// Create a texture from RGBA pixels, rows going from top to bottom
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size is zero or doesn't match the pixels
+    #[must_use]
+    pub fn create_texture(&self, width: u32, height: u32, pixels: &[u8]) -> TextureRect {
+        assert!(
+            width > 0 && height > 0 && pixels.len() == (width * height * 4) as usize,
+            "Error, texture size doesn't match its pixels"
+        );
+
+        TextureRect::new(Texture::Software(Rc::new(SoftwareTexture {
+            size: UVec2::new(width, height),
+            pixels: pixels.to_vec(),
+        })))
+    }
+
+    /// Fill the canvas with the given color
+    pub fn clear(&mut self, color: Vec4) {
+        let color = to_bytes(color);
+
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+
+    /// Set the view matrix of this canvas, this is used to convert from world coordinates to opengl coordinates
+    pub const fn set_view_matrix(&mut self, view_matrix: Mat3) {
+        self.view_matrix = view_matrix;
+    }
+
+    /// Get the current view matrix
+    #[must_use]
+    pub const fn view_matrix(&self) -> Mat3 {
+        self.view_matrix
+    }
+
+    /// Set the view matrix so that world coordinates corresponds to pixels on the canvas
+    pub fn pixel_perfect_view(&mut self) {
+        let size = self.size.as_vec2();
+
+        self.view_matrix = Mat3::from_cols(
+            Vec3::new(2. / size.x, 0., 0.),
+            Vec3::new(0., 2. / size.y, 0.),
+            Vec3::new(-1., -1., 1.),
+        );
+    }
+
+    /// Set the view matrix from the given camera
+    pub fn set_camera(&mut self, camera: &Camera2d) {
+        self.view_matrix = camera.view_matrix(self.size);
+    }
+
+    /// Set how the next draw calls are blended with the canvas content
+    pub const fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    /// Get the current blend mode
+    #[must_use]
+    pub const fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// RGBA bytes of the canvas, rows going from top to bottom.
+    /// Colors are premultiplied by alpha like the content of a webgl canvas
+    #[must_use]
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Color of a pixel, (0, 0) being the top left corner
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pixel is outside of the canvas
+    #[must_use]
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        assert!(
+            x < self.size.x && y < self.size.y,
+            "Error, pixel out of canvas"
+        );
+
+        let index = ((y * self.size.x + x) * 4) as usize;
+        self.pixels[index..index + 4].try_into().unwrap()
+    }
+
+    fn blend(&mut self, x: u32, y: u32, source: Vec4) {
+        let index = ((y * self.size.x + x) * 4) as usize;
+        let pixel = &mut self.pixels[index..index + 4];
+
+        let destination = Vec4::new(
+            f32::from(pixel[0]),
+            f32::from(pixel[1]),
+            f32::from(pixel[2]),
+            f32::from(pixel[3]),
+        ) / 255.;
+
+        let color = match self.blend_mode {
+            BlendMode::Alpha => source + destination * (1. - source.w),
+            BlendMode::Additive => source + destination,
+            BlendMode::Multiply => source * destination + destination * (1. - source.w),
+        };
+
+        pixel.copy_from_slice(&to_bytes(color));
+    }
+
+    /// Fill the pixels whose center is inside the triangle, vertices being (screen position, color, texcoord)
+    fn draw_triangle(&mut self, mut vertices: [(Vec2, Vec4, Vec2); 3], texture: &SoftwareTexture) {
+        let mut area = edge(vertices[0].0, vertices[1].0, vertices[2].0);
+
+        if area == 0. {
+            return;
+        }
+
+        // Same winding for every triangle so that the fill rule is consistent
+        if area < 0. {
+            vertices.swap(1, 2);
+            area = -area;
+        }
+
+        let [a, b, c] = vertices.map(|vertex| vertex.0);
+
+        let min = a.min(b).min(c).max(Vec2::ZERO).floor();
+        let max = a.max(b).max(c).min(self.size.as_vec2()).ceil();
+
+        for y in min.y as u32..max.y as u32 {
+            for x in min.x as u32..max.x as u32 {
+                let point = Vec2::new(x as f32, y as f32) + 0.5;
+
+                let weights = [edge(b, c, point), edge(c, a, point), edge(a, b, point)];
+                let edges = [(b, c), (c, a), (a, b)];
+
+                // Pixels on an edge shared by two triangles are only filled by one of them
+                let inside = weights.iter().zip(edges).all(|(&weight, (start, end))| {
+                    weight > 0. || (weight == 0. && is_top_left(start, end))
+                });
+
+                if !inside {
+                    continue;
+                }
+
+                let [w_a, w_b, w_c] = weights.map(|weight| weight / area);
+                let color = vertices[0].1 * w_a + vertices[1].1 * w_b + vertices[2].1 * w_c;
+                let texcoord = vertices[0].2 * w_a + vertices[1].2 * w_b + vertices[2].2 * w_c;
+
+                let color = texture.sample(texcoord) * color;
+                let color = (color.truncate() * color.w).extend(color.w);
+
+                self.blend(x, y, color);
+            }
+        }
+    }
+}
+
+impl DrawTarget2d for SoftwareCanvas2d {
+    fn draw_vertices(&mut self, indexes: &[u16], vertices: &[Vertex2d], texture: &Texture) {
+        let Texture::Software(texture) = texture else {
+            panic!("Error, webgl textures can't be drawn on a SoftwareCanvas2d");
+        };
+
+        let size = self.size.as_vec2();
+
+        let vertex = |index: u16| {
+            let vertex = vertices[index as usize];
+            let position = self.view_matrix.transform_point2(vertex.position);
+
+            (
+                Vec2::new(position.x + 1., 1. - position.y) * size / 2.,
+                vertex.color,
+                vertex.texcoord,
+            )
+        };
+
+        let triangles = indexes
+            .chunks_exact(3)
+            .map(|triangle| {
+                [
+                    vertex(triangle[0]),
+                    vertex(triangle[1]),
+                    vertex(triangle[2]),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        for triangle in triangles {
+            self.draw_triangle(triangle, texture);
+        }
+    }
+}
+
+/// Twice the signed area of the triangle (start, end, point)
+fn edge(start: Vec2, end: Vec2, point: Vec2) -> f32 {
+    (end - start).perp_dot(point - start)
+}
+
+/// Each edge shared by two triangles goes in opposite directions in them, so exactly one of them owns it
+fn is_top_left(start: Vec2, end: Vec2) -> bool {
+    let direction = end - start;
+    direction.y > 0. || (direction.y == 0. && direction.x < 0.)
+}
+
+fn to_bytes(color: Vec4) -> [u8; 4] {
+    (color.clamp(Vec4::ZERO, Vec4::ONE) * 255.)
+        .round()
+        .to_array()
+        .map(|channel| channel as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Vec4 = Vec4::new(1., 0., 0., 1.);
+
+    /// Blend equation from the premultiplied source and the destination
+    type Formula = fn(Vec4, Vec4) -> Vec4;
+
+    fn canvas(width: u32, height: u32) -> SoftwareCanvas2d {
+        let mut canvas = SoftwareCanvas2d::new(UVec2::new(width, height));
+        canvas.pixel_perfect_view();
+        canvas
+    }
+
+    /// Pixels of the canvas whose red channel isn't zero, (x, y) from the top left corner
+    fn lit_pixels(canvas: &SoftwareCanvas2d) -> Vec<(u32, u32)> {
+        (0..canvas.size().y)
+            .flat_map(|y| (0..canvas.size().x).map(move |x| (x, y)))
+            .filter(|&(x, y)| canvas.pixel(x, y)[0] != 0)
+            .collect()
+    }
+
+    #[test]
+    fn draw_rect_pixels() {
+        let mut canvas = canvas(8, 8);
+        let white = canvas.white_texture();
+
+        canvas.draw_rect(Vec2::new(2., 1.), Vec2::new(3., 4.), RED, &white);
+
+        // World y goes up, rows go down
+        for y in 0..8 {
+            for x in 0..8 {
+                let inside = (2..5).contains(&x) && (3..7).contains(&y);
+                let expected = if inside { [255, 0, 0, 255] } else { [0; 4] };
+
+                assert_eq!(canvas.pixel(x, y), expected, "pixel ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn pixel_perfect_view_mapping() {
+        let mut canvas = canvas(5, 3);
+        let white = canvas.white_texture();
+
+        for position in [Vec2::ZERO, Vec2::new(4., 0.), Vec2::new(1., 2.)] {
+            canvas.clear(Vec4::ZERO);
+            canvas.draw_rect(position, Vec2::ONE, RED, &white);
+
+            assert_eq!(
+                lit_pixels(&canvas),
+                [(position.x as u32, 2 - position.y as u32)]
+            );
+        }
+    }
+
+    #[test]
+    fn shared_edges_are_filled_once() {
+        let mut canvas = canvas(8, 8);
+        let white = canvas.white_texture();
+
+        // Additive blending shows pixels filled twice, edges go exactly through pixel centers
+        canvas.set_blend_mode(BlendMode::Additive);
+
+        let color = Vec4::new(0.2, 0., 0., 1.);
+        canvas.draw_rect(Vec2::splat(0.5), Vec2::new(4., 7.), color, &white);
+        canvas.draw_rect(Vec2::new(4.5, 0.5), Vec2::new(3., 7.), color, &white);
+
+        let lit = lit_pixels(&canvas);
+        assert_eq!(lit.len(), 7 * 7);
+        assert!(lit.iter().all(|&(x, y)| canvas.pixel(x, y)[0] == 51));
+
+        // Every triangle of the fan touches the center pixel
+        canvas.clear(Vec4::ZERO);
+        canvas.draw_regular(Vec2::splat(4.5), 4., 12, color, &white);
+
+        assert_eq!(canvas.pixel(4, 4)[0], 51);
+        assert!(lit_pixels(&canvas)
+            .iter()
+            .all(|&(x, y)| canvas.pixel(x, y)[0] == 51));
+    }
+
+    #[test]
+    fn blend_modes() {
+        let destination = Vec4::new(0.5, 0.25, 1., 1.);
+        let source = Vec4::new(0.2, 0.6, 0.4, 0.5);
+
+        // Sources are premultiplied, factors are the ones given to `blendFunc` by `Canvas2d`
+        let premultiplied = (source.truncate() * source.w).extend(source.w);
+
+        let formulas: [(BlendMode, Formula); 3] = [
+            (BlendMode::Alpha, |s, d| s + d * (1. - s.w)),
+            (BlendMode::Additive, |s, d| s + d),
+            (BlendMode::Multiply, |s, d| s * d + d * (1. - s.w)),
+        ];
+
+        for (blend_mode, formula) in formulas {
+            let mut canvas = canvas(1, 1);
+            let white = canvas.white_texture();
+
+            canvas.clear(destination);
+            let [r, g, b, a] = canvas.pixel(0, 0).map(f32::from);
+            let destination = Vec4::new(r, g, b, a) / 255.;
+
+            canvas.set_blend_mode(blend_mode);
+            canvas.draw_rect(Vec2::ZERO, Vec2::ONE, source, &white);
+
+            let expected = to_bytes(formula(premultiplied, destination));
+
+            assert_eq!(canvas.pixel(0, 0), expected, "{blend_mode:?}");
+        }
+    }
+
+    #[test]
+    fn texture_orientation() {
+        let mut canvas = canvas(4, 4);
+
+        // Rows from top to bottom: red, green then blue, white
+        let texture = canvas.create_texture(
+            2,
+            2,
+            &[
+                255, 0, 0, 255, 0, 255, 0, 255, //
+                0, 0, 255, 255, 255, 255, 255, 255,
+            ],
+        );
+
+        canvas.draw_rect(Vec2::ZERO, Vec2::splat(4.), Vec4::ONE, &texture);
+
+        assert_eq!(canvas.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(canvas.pixel(3, 0), [0, 255, 0, 255]);
+        assert_eq!(canvas.pixel(0, 3), [0, 0, 255, 255]);
+        assert_eq!(canvas.pixel(3, 3), [255; 4]);
+
+        // Texture rects select a part of the texture with the same orientation
+        let mut right_column = texture;
+        right_column.position = Vec2::new(0.5, 0.);
+        right_column.size = Vec2::new(0.5, 1.);
+
+        canvas.draw_rect(Vec2::ZERO, Vec2::splat(4.), Vec4::ONE, &right_column);
+
+        assert_eq!(canvas.pixel(0, 0), [0, 255, 0, 255]);
+        assert_eq!(canvas.pixel(0, 3), [255; 4]);
+    }
+}
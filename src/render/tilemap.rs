@@ -0,0 +1,609 @@
+use super::{
+    canvas2d::{BufferedObject2d, Canvas2d, DrawTarget2d, ObjectBuilder2d, TextureRect},
+    vertex::Vertex2d,
+};
+use glam::{IVec2, Mat3, UVec2, Vec2, Vec4};
+use std::collections::{BTreeMap, HashMap};
+
+/// A texture split in a grid of equally sized tiles, tile ids go from left to right then top to bottom
+#[derive(Clone)]
+pub struct Tileset {
+    pub texture: TextureRect,
+    pub columns: u32,
+    pub rows: u32,
+    /// Space around the grid, as a fraction of the texture size
+    pub margin: Vec2,
+    /// Space between two tiles, as a fraction of the texture size
+    pub spacing: Vec2,
+}
+
+impl Tileset {
+    #[must_use]
+    pub const fn new(texture: TextureRect, columns: u32, rows: u32) -> Self {
+        Self::with_spacing(texture, columns, rows, Vec2::ZERO, Vec2::ZERO)
+    }
+
+    /// Create a tileset whose tiles are separated, `margin` and `spacing` are fractions of the texture size
+    #[must_use]
+    pub const fn with_spacing(
+        texture: TextureRect,
+        columns: u32,
+        rows: u32,
+        margin: Vec2,
+        spacing: Vec2,
+    ) -> Self {
+        Self {
+            texture,
+            columns,
+            rows,
+            margin,
+            spacing,
+        }
+    }
+
+    /// Number of tiles in this tileset
+    #[must_use]
+    pub const fn tile_count(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    /// Get the texture rect of the given tile
+    #[must_use]
+    pub fn tile_rect(&self, tile: u32) -> TextureRect {
+        let grid = UVec2::new(self.columns, self.rows).as_vec2();
+        let cell = UVec2::new(tile % self.columns, tile / self.columns).as_vec2();
+
+        let size = (Vec2::ONE - 2. * self.margin - (grid - 1.) * self.spacing) / grid;
+        let position = self.margin + cell * (size + self.spacing);
+
+        TextureRect {
+            texture: self.texture.texture.clone(),
+            position: self.texture.position + position * self.texture.size,
+            size: size * self.texture.size,
+        }
+    }
+}
+
+/// A tile of a tileset with its orientation
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Tile {
+    pub id: u32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Swap the x and y axis of the tile, this is applied before the other flips
+    pub flip_diagonal: bool,
+}
+
+impl Tile {
+    #[must_use]
+    pub const fn new(id: u32) -> Self {
+        Self {
+            id,
+            flip_x: false,
+            flip_y: false,
+            flip_diagonal: false,
+        }
+    }
+
+    /// Draw this tile with its orientation applied, `texture` is the texture rect of the tile in the tileset
+    pub fn draw<T: DrawTarget2d + ?Sized>(
+        self,
+        target: &mut T,
+        position: Vec2,
+        size: Vec2,
+        texture: &TextureRect,
+    ) {
+        self.draw_with_color(target, position, size, Vec4::ONE, texture);
+    }
+
+    /// Draw this tile with its orientation applied and its texture multiplied by the color
+    pub fn draw_with_color<T: DrawTarget2d + ?Sized>(
+        self,
+        target: &mut T,
+        position: Vec2,
+        size: Vec2,
+        color: Vec4,
+        texture: &TextureRect,
+    ) {
+        let x = position.x;
+        let y = position.y;
+
+        let w = size.x;
+        let h = size.y;
+
+        // Corners in texture space (y going down) in the order used by `draw_rect`
+        let vertices = [
+            (Vec2::new(x, y), Vec2::new(0., 1.)),
+            (Vec2::new(x + w, y), Vec2::new(1., 1.)),
+            (Vec2::new(x, y + h), Vec2::new(0., 0.)),
+            (Vec2::new(x + w, y + h), Vec2::new(1., 0.)),
+        ]
+        .map(|(position, mut corner)| {
+            if self.flip_x {
+                corner.x = 1. - corner.x;
+            }
+            if self.flip_y {
+                corner.y = 1. - corner.y;
+            }
+            if self.flip_diagonal {
+                corner = Vec2::new(corner.y, corner.x);
+            }
+
+            Vertex2d::new(position, color, texture.position + corner * texture.size)
+        });
+
+        target.draw_vertices(&[0, 1, 2, 1, 2, 3], &vertices, &texture.texture);
+    }
+}
+
+impl From<u32> for Tile {
+    fn from(id: u32) -> Self {
+        Self::new(id)
+    }
+}
+
+/// Why a `TileAnimation` couldn't be created
+#[derive(Debug, PartialEq, Eq)]
+pub enum TileAnimationError {
+    NoFrames,
+    /// A frame is displayed for zero ticks
+    ZeroDuration,
+    /// The frame durations add up to more than `u32::MAX` ticks
+    TooLong,
+}
+
+/// A tile cycling through several tiles of the tileset
+pub struct TileAnimation {
+    /// Displayed tile and number of ticks it stays displayed
+    frames: Vec<(u32, u32)>,
+    total_ticks: u32,
+}
+
+impl TileAnimation {
+    /// Create an animation where every frame is displayed for the same number of ticks
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if there are no frames or if they are displayed for zero ticks
+    pub fn new(frames: Vec<u32>, ticks_per_frame: u32) -> Result<Self, TileAnimationError> {
+        Self::from_durations(
+            frames
+                .into_iter()
+                .map(|tile| (tile, ticks_per_frame))
+                .collect(),
+        )
+    }
+
+    /// Create an animation from a list of tiles with the number of ticks each one stays displayed
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if there are no frames, if a frame is displayed for zero ticks or if the animation is too long
+    pub fn from_durations(frames: Vec<(u32, u32)>) -> Result<Self, TileAnimationError> {
+        if frames.is_empty() {
+            return Err(TileAnimationError::NoFrames);
+        }
+
+        if frames.iter().any(|&(_, ticks)| ticks == 0) {
+            return Err(TileAnimationError::ZeroDuration);
+        }
+
+        let total_ticks = frames
+            .iter()
+            .try_fold(0u32, |total, &(_, ticks)| total.checked_add(ticks))
+            .ok_or(TileAnimationError::TooLong)?;
+
+        Ok(Self {
+            frames,
+            total_ticks,
+        })
+    }
+
+    /// Get the tile displayed at the given tick
+    #[must_use]
+    pub fn frame(&self, tick: u32) -> u32 {
+        let mut remaining = tick % self.total_ticks;
+
+        for &(tile, ticks) in &self.frames {
+            if remaining < ticks {
+                return tile;
+            }
+
+            remaining -= ticks;
+        }
+
+        unreachable!()
+    }
+}
+
+struct Chunk {
+    tiles: Vec<Option<Tile>>,
+    buffer: Option<BufferedObject2d>,
+    animated: Vec<(usize, Tile)>,
+    dirty: bool,
+}
+
+impl Chunk {
+    fn new(chunk_size: u32) -> Self {
+        Self {
+            tiles: vec![None; (chunk_size * chunk_size) as usize],
+            buffer: None,
+            animated: Vec::new(),
+            dirty: true,
+        }
+    }
+}
+
+struct TileLayer {
+    chunks: HashMap<IVec2, Chunk>,
+    visible: bool,
+    opacity: f32,
+}
+
+/// A grid of tiles split in chunks baked into static buffers
+///
+/// Only chunks modified since the last draw are rebuilt and chunks outside the view are not drawn.
+/// Tile (0, 0) has its bottom left corner at the origin, x goes right and y goes up
+pub struct Tilemap {
+    tile_size: Vec2,
+    chunk_size: u32,
+    tileset: Tileset,
+    layers: Vec<TileLayer>,
+    animations: BTreeMap<u32, TileAnimation>,
+    tick: u32,
+    builder: ObjectBuilder2d,
+}
+
+impl Tilemap {
+    /// Create a tilemap using a default chunk size of 16 by 16 tiles
+    #[must_use]
+    pub fn new(tile_size: Vec2, tileset: Tileset) -> Self {
+        Self::with_chunk_size(tile_size, tileset, 16)
+    }
+
+    /// Create a tilemap where chunks are `chunk_size` by `chunk_size` tiles
+    #[must_use]
+    pub fn with_chunk_size(tile_size: Vec2, tileset: Tileset, chunk_size: u32) -> Self {
+        assert!(
+            (1..=127).contains(&chunk_size),
+            "Chunk size must be between 1 and 127 so that chunks fit in a buffer"
+        );
+
+        Self {
+            tile_size,
+            chunk_size,
+            tileset,
+            layers: Vec::new(),
+            animations: BTreeMap::new(),
+            tick: 0,
+            builder: ObjectBuilder2d::new(),
+        }
+    }
+
+    #[must_use]
+    pub const fn tile_size(&self) -> Vec2 {
+        self.tile_size
+    }
+
+    #[must_use]
+    pub const fn tileset(&self) -> &Tileset {
+        &self.tileset
+    }
+
+    /// Add a layer drawn above the existing ones and return its index
+    pub fn add_layer(&mut self) -> usize {
+        self.layers.push(TileLayer {
+            chunks: HashMap::new(),
+            visible: true,
+            opacity: 1.,
+        });
+
+        self.layers.len() - 1
+    }
+
+    #[must_use]
+    pub const fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Show or hide the given layer
+    pub fn set_layer_visible(&mut self, layer: usize, visible: bool) {
+        self.layers[layer].visible = visible;
+    }
+
+    /// Set the opacity of the given layer from 0 to 1, its chunks are rebuilt
+    pub fn set_layer_opacity(&mut self, layer: usize, opacity: f32) {
+        let layer = &mut self.layers[layer];
+        layer.opacity = opacity;
+
+        for chunk in layer.chunks.values_mut() {
+            chunk.dirty = true;
+        }
+    }
+
+    fn split_position(&self, position: IVec2) -> (IVec2, usize) {
+        let chunk_size = self.chunk_size as i32;
+
+        let chunk = IVec2::new(
+            position.x.div_euclid(chunk_size),
+            position.y.div_euclid(chunk_size),
+        );
+        let local = position - chunk * chunk_size;
+
+        (chunk, (local.y * chunk_size + local.x) as usize)
+    }
+
+    /// Set the tile at the given grid position, `None` removes it
+    pub fn set_tile(&mut self, layer: usize, position: IVec2, tile: Option<Tile>) {
+        let (chunk_position, index) = self.split_position(position);
+        let chunk_size = self.chunk_size;
+
+        let chunk = self.layers[layer]
+            .chunks
+            .entry(chunk_position)
+            .or_insert_with(|| Chunk::new(chunk_size));
+
+        if chunk.tiles[index] != tile {
+            chunk.tiles[index] = tile;
+            chunk.dirty = true;
+        }
+    }
+
+    /// Get the tile at the given grid position
+    #[must_use]
+    pub fn tile(&self, layer: usize, position: IVec2) -> Option<Tile> {
+        let (chunk_position, index) = self.split_position(position);
+
+        self.layers[layer]
+            .chunks
+            .get(&chunk_position)
+            .and_then(|chunk| chunk.tiles[index])
+    }
+
+    /// Grid position of the tile containing the given world position
+    #[must_use]
+    pub fn world_to_tile(&self, world_pos: Vec2) -> IVec2 {
+        (world_pos / self.tile_size).floor().as_ivec2()
+    }
+
+    /// World position of the bottom left corner of the given tile
+    #[must_use]
+    pub fn tile_to_world(&self, position: IVec2) -> Vec2 {
+        position.as_vec2() * self.tile_size
+    }
+
+    /// Animate every occurrence of `tile` with the given animation
+    pub fn set_animation(&mut self, tile: u32, animation: TileAnimation) {
+        self.animations.insert(tile, animation);
+        self.mark_all_dirty();
+    }
+
+    /// Stop animating the given tile
+    pub fn remove_animation(&mut self, tile: u32) {
+        if self.animations.remove(&tile).is_some() {
+            self.mark_all_dirty();
+        }
+    }
+
+    fn mark_all_dirty(&mut self) {
+        for layer in &mut self.layers {
+            for chunk in layer.chunks.values_mut() {
+                chunk.dirty = true;
+            }
+        }
+    }
+
+    /// Advance tile animations by one tick
+    pub const fn tick(&mut self) {
+        self.tick = self.tick.wrapping_add(1);
+    }
+
+    /// World rectangle (min, max) visible with the given view matrix
+    fn visible_rect(view_matrix: Mat3) -> (Vec2, Vec2) {
+        let inverse = view_matrix.inverse();
+
+        [
+            Vec2::new(-1., -1.),
+            Vec2::new(1., -1.),
+            Vec2::new(-1., 1.),
+            Vec2::new(1., 1.),
+        ]
+        .into_iter()
+        .map(|corner| inverse.transform_point2(corner))
+        .fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), point| (min.min(point), max.max(point)),
+        )
+    }
+
+    fn tile_position(&self, chunk_position: IVec2, index: usize) -> Vec2 {
+        let chunk_size = self.chunk_size as usize;
+        let local = IVec2::new((index % chunk_size) as i32, (index / chunk_size) as i32);
+
+        self.tile_to_world(chunk_position * self.chunk_size as i32 + local)
+    }
+
+    fn rebuild_chunk(&mut self, canvas: &Canvas2d, layer: usize, chunk_position: IVec2) {
+        let chunk = &self.layers[layer].chunks[&chunk_position];
+        let color = Vec4::new(1., 1., 1., self.layers[layer].opacity);
+
+        let mut animated = Vec::new();
+
+        for (index, tile) in chunk.tiles.iter().enumerate() {
+            let Some(tile) = *tile else {
+                continue;
+            };
+
+            if self.animations.contains_key(&tile.id) {
+                animated.push((index, tile));
+            } else {
+                let position = self.tile_position(chunk_position, index);
+                let texture = self.tileset.tile_rect(tile.id);
+
+                tile.draw_with_color(&mut self.builder, position, self.tile_size, color, &texture);
+            }
+        }
+
+        let buffer = canvas.build_buffer(&mut self.builder);
+
+        let chunk = self.layers[layer].chunks.get_mut(&chunk_position).unwrap();
+
+        if let Some(old) = chunk.buffer.take() {
+            canvas.delete_buffer(&old);
+        }
+
+        chunk.buffer = buffer;
+        chunk.animated = animated;
+        chunk.dirty = false;
+    }
+
+    /// Free the GPU memory of every chunk, they are rebuilt if the tilemap is drawn again
+    pub fn delete(&mut self, canvas: &Canvas2d) {
+        for layer in &mut self.layers {
+            for chunk in layer.chunks.values_mut() {
+                if let Some(buffer) = chunk.buffer.take() {
+                    canvas.delete_buffer(&buffer);
+                }
+
+                chunk.dirty = true;
+            }
+        }
+    }
+
+    /// Draw the chunks visible with the current view matrix of the canvas, rebuilding the modified ones
+    pub fn draw(&mut self, canvas: &mut Canvas2d) {
+        let (min, max) = Self::visible_rect(canvas.view_matrix());
+
+        let chunk_world_size = self.tile_size * self.chunk_size as f32;
+
+        let min_chunk = (min / chunk_world_size).floor().as_ivec2();
+        let max_chunk = (max / chunk_world_size).floor().as_ivec2();
+
+        canvas.flush();
+
+        for layer in 0..self.layers.len() {
+            if !self.layers[layer].visible {
+                continue;
+            }
+
+            let visible_chunks = self.layers[layer]
+                .chunks
+                .keys()
+                .copied()
+                .filter(|chunk| chunk.cmpge(min_chunk).all() && chunk.cmple(max_chunk).all())
+                .collect::<Vec<_>>();
+
+            for &chunk_position in &visible_chunks {
+                if self.layers[layer].chunks[&chunk_position].dirty {
+                    self.rebuild_chunk(canvas, layer, chunk_position);
+                }
+
+                if let Some(buffer) = &self.layers[layer].chunks[&chunk_position].buffer {
+                    canvas.draw_buffer(buffer);
+                }
+            }
+
+            let color = Vec4::new(1., 1., 1., self.layers[layer].opacity);
+
+            for &chunk_position in &visible_chunks {
+                for &(index, tile) in &self.layers[layer].chunks[&chunk_position].animated {
+                    let frame = self.animations[&tile.id].frame(self.tick);
+
+                    tile.draw_with_color(
+                        canvas,
+                        self.tile_position(chunk_position, index),
+                        self.tile_size,
+                        color,
+                        &self.tileset.tile_rect(frame),
+                    );
+                }
+            }
+
+            canvas.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::software::SoftwareCanvas2d;
+
+    fn tileset(columns: u32, rows: u32) -> Tileset {
+        let canvas = SoftwareCanvas2d::new(UVec2::ONE);
+
+        Tileset::new(canvas.white_texture(), columns, rows)
+    }
+
+    #[test]
+    fn animation_frames() {
+        let animation = TileAnimation::from_durations(vec![(3, 2), (5, 1), (7, 3)]).unwrap();
+
+        let frames = (0..8).map(|tick| animation.frame(tick)).collect::<Vec<_>>();
+        assert_eq!(frames, [3, 3, 5, 7, 7, 7, 3, 3]);
+
+        let animation = TileAnimation::new(vec![1, 2], 1).unwrap();
+        assert_eq!(animation.frame(u32::MAX), 2);
+    }
+
+    #[test]
+    fn animation_errors() {
+        assert!(matches!(
+            TileAnimation::new(Vec::new(), 1),
+            Err(TileAnimationError::NoFrames)
+        ));
+        assert!(matches!(
+            TileAnimation::new(vec![1], 0),
+            Err(TileAnimationError::ZeroDuration)
+        ));
+        assert!(matches!(
+            TileAnimation::from_durations(vec![(1, u32::MAX), (2, 1)]),
+            Err(TileAnimationError::TooLong)
+        ));
+    }
+
+    #[test]
+    fn tile_rects() {
+        let tileset = tileset(4, 2);
+        assert_eq!(tileset.tile_count(), 8);
+
+        let rect = tileset.tile_rect(6);
+        assert!(rect.position.abs_diff_eq(Vec2::new(0.5, 0.5), 1e-6));
+        assert!(rect.size.abs_diff_eq(Vec2::new(0.25, 0.5), 1e-6));
+
+        let spaced =
+            Tileset::with_spacing(tileset.texture, 2, 2, Vec2::splat(0.1), Vec2::splat(0.2));
+
+        let rect = spaced.tile_rect(3);
+        assert!(rect.position.abs_diff_eq(Vec2::splat(0.6), 1e-6));
+        assert!(rect.size.abs_diff_eq(Vec2::splat(0.3), 1e-6));
+    }
+
+    #[test]
+    fn positions_split_in_chunks() {
+        let mut tilemap = Tilemap::with_chunk_size(Vec2::splat(2.), tileset(1, 1), 4);
+
+        assert_eq!(tilemap.split_position(IVec2::new(0, 0)), (IVec2::ZERO, 0));
+        assert_eq!(
+            tilemap.split_position(IVec2::new(5, 2)),
+            (IVec2::new(1, 0), 9)
+        );
+        assert_eq!(
+            tilemap.split_position(IVec2::new(-1, -4)),
+            (IVec2::new(-1, -1), 3)
+        );
+
+        let (chunk, index) = tilemap.split_position(IVec2::new(-6, 7));
+        assert_eq!(tilemap.tile_position(chunk, index), Vec2::new(-12., 14.));
+
+        let layer = tilemap.add_layer();
+        tilemap.set_tile(layer, IVec2::new(-1, -4), Some(Tile::new(0)));
+
+        assert_eq!(tilemap.tile(layer, IVec2::new(-1, -4)), Some(Tile::new(0)));
+        assert_eq!(tilemap.tile(layer, IVec2::new(3, -4)), None);
+        assert_eq!(
+            tilemap.world_to_tile(Vec2::new(-0.5, -7.5)),
+            IVec2::new(-1, -4)
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use glam::{Vec2, Vec4};
+use serde::{Deserialize, Serialize};
+
+/// An input of a vertex shader, matched by name
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct VertexAttribute {
+    pub name: &'static str,
+    /// Number of floats, from 1 to 4
+    pub size: i32,
+}
+
+/// A vertex format uploaded in an interleaved buffer
+///
+/// Attributes are stored one after the other in the order of `ATTRIBUTES`.
+/// Implement it to draw custom vertices with `Canvas2d::draw_with_shader`
+pub trait Vertex: Copy {
+    const ATTRIBUTES: &'static [VertexAttribute];
+
+    /// Append the floats of every attribute in the order of `ATTRIBUTES`, writing another count panics in debug builds
+    fn write(&self, data: &mut Vec<f32>);
+}
+
+/// A vertex of the 2d shaders, drawn with `DrawTarget2d::draw_vertices`
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Vertex2d {
+    pub position: Vec2,
+    /// Multiplied with the texture color
+    pub color: Vec4,
+    pub texcoord: Vec2,
+}
+
+impl Vertex2d {
+    #[must_use]
+    pub const fn new(position: Vec2, color: Vec4, texcoord: Vec2) -> Self {
+        Self {
+            position,
+            color,
+            texcoord,
+        }
+    }
+}
+
+impl Vertex for Vertex2d {
+    const ATTRIBUTES: &'static [VertexAttribute] = &[
+        VertexAttribute {
+            name: "aPosition",
+            size: 2,
+        },
+        VertexAttribute {
+            name: "aColor",
+            size: 4,
+        },
+        VertexAttribute {
+            name: "aTexcoord",
+            size: 2,
+        },
+    ];
+
+    fn write(&self, data: &mut Vec<f32>) {
+        data.extend_from_slice(&self.position.to_array());
+        data.extend_from_slice(&self.color.to_array());
+        data.extend_from_slice(&self.texcoord.to_array());
+    }
+}
+
+/// Size of a vertex in bytes
+pub(crate) fn stride<V: Vertex>() -> i32 {
+    V::ATTRIBUTES
+        .iter()
+        .map(|attribute| attribute.size * 4)
+        .sum()
+}
+
+/// Floats of the vertices one after the other
+pub(crate) fn interleave<V: Vertex>(vertices: &[V]) -> Vec<f32> {
+    let mut data = Vec::with_capacity(vertices.len() * stride::<V>() as usize / 4);
+
+    for vertex in vertices {
+        vertex.write(&mut data);
+    }
+
+    debug_assert_eq!(
+        data.len(),
+        vertices.len() * stride::<V>() as usize / 4,
+        "Error, Vertex::write must write the floats of ATTRIBUTES"
+    );
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy)]
+    struct Short;
+
+    impl Vertex for Short {
+        const ATTRIBUTES: &'static [VertexAttribute] = &[VertexAttribute {
+            name: "aValue",
+            size: 2,
+        }];
+
+        fn write(&self, data: &mut Vec<f32>) {
+            data.push(1.);
+        }
+    }
+
+    #[test]
+    fn interleaved_vertices() {
+        let vertex = Vertex2d::new(
+            Vec2::new(1., 2.),
+            Vec4::new(3., 4., 5., 6.),
+            Vec2::new(7., 8.),
+        );
+
+        assert_eq!(stride::<Vertex2d>(), 32);
+        assert_eq!(
+            interleave(&[vertex, Vertex2d::default()]),
+            [1., 2., 3., 4., 5., 6., 7., 8., 0., 0., 0., 0., 0., 0., 0., 0.]
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "must write the floats of ATTRIBUTES")]
+    fn mismatched_write() {
+        let _ = interleave(&[Short]);
+    }
+}
@@ -0,0 +1,69 @@
+use glam::{IVec2, Mat3, UVec2, Vec2};
+
+/// A sub rectangle of the drawing surface with its own view matrix, used for split screen or picture in picture
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport2d {
+    /// Bottom left corner, as a fraction of the drawing surface size
+    pub position: Vec2,
+    /// Size, as a fraction of the drawing surface size
+    pub size: Vec2,
+    pub view_matrix: Mat3,
+}
+
+impl Viewport2d {
+    /// A viewport covering the whole drawing surface
+    pub const FULL: Self = Self::new(Vec2::ZERO, Vec2::ONE);
+
+    #[must_use]
+    pub const fn new(position: Vec2, size: Vec2) -> Self {
+        Self {
+            position,
+            size,
+            view_matrix: Mat3::IDENTITY,
+        }
+    }
+
+    /// Split the drawing surface in `count` side by side columns
+    #[must_use]
+    pub fn columns(count: u32) -> Vec<Self> {
+        let width = 1. / count as f32;
+
+        (0..count)
+            .map(|i| Self::new(Vec2::new(i as f32 * width, 0.), Vec2::new(width, 1.)))
+            .collect()
+    }
+
+    /// Split the drawing surface in `count` stacked rows, the first one being at the top
+    #[must_use]
+    pub fn rows(count: u32) -> Vec<Self> {
+        let height = 1. / count as f32;
+
+        (0..count)
+            .map(|i| {
+                Self::new(
+                    Vec2::new(0., 1. - (i + 1) as f32 * height),
+                    Vec2::new(1., height),
+                )
+            })
+            .collect()
+    }
+
+    /// Position (from the bottom left corner) and size in pixels of this viewport on a surface of the given size
+    #[must_use]
+    pub fn pixel_rect(&self, surface_size: UVec2) -> (IVec2, UVec2) {
+        let surface_size = surface_size.as_vec2();
+
+        let min = (self.position * surface_size).round();
+        let max = ((self.position + self.size) * surface_size).round();
+
+        (min.as_ivec2(), (max - min).max(Vec2::ZERO).as_uvec2())
+    }
+
+    /// Check if the given point, as a fraction of the drawing surface size from the bottom left corner, is inside this viewport
+    #[must_use]
+    pub fn contains(&self, point: Vec2) -> bool {
+        let max = self.position + self.size;
+
+        point.cmpge(self.position).all() && point.cmplt(max).all()
+    }
+}
@@ -81,3 +81,16 @@ pub fn buffer_u16_indexes(webgl: &WebGl2RenderingContext, buffer: &WebGlBuffer,
         WebGl2RenderingContext::DYNAMIC_DRAW, // Flexible choice but possibly not the most optimal
     );
 }
+
+/// Safe wrapper around `js_sys` view
+pub fn buffer_u32_indexes(webgl: &WebGl2RenderingContext, buffer: &WebGlBuffer, data: &[u32]) {
+    webgl.bind_buffer(WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER, Some(buffer));
+
+    let indexes_array_buf_view = unsafe { js_sys::Uint32Array::view(data) };
+
+    webgl.buffer_data_with_array_buffer_view(
+        WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
+        &indexes_array_buf_view,
+        WebGl2RenderingContext::STATIC_DRAW,
+    );
+}
@@ -0,0 +1,266 @@
+//! Record gameplay clips of a `Canvas2d` to `WebM` files with `MediaRecorder`.
+//!
+//! A recording covers everything since `start`, or with `start_rolling` only the last seconds.
+//! Rolling recordings are restarted regularly and overlap, clips are taken from the oldest one so that
+//! they are always complete files.
+
+use crate::{
+    audio,
+    dom::{document, set_timeout, window},
+    render::canvas2d::Canvas2d,
+};
+use futures_channel::oneshot;
+use js_sys::Array;
+use std::{cell::RefCell, collections::VecDeque, rc::Rc, time::Duration};
+use wasm_bindgen::{prelude::Closure, JsCast};
+use web_sys::{
+    Blob, BlobEvent, BlobPropertyBag, HtmlAnchorElement, MediaRecorder, MediaRecorderOptions,
+    MediaStream, MediaStreamTrack, RecordingState, Url,
+};
+
+/// Formats tried in order, the first one supported by the browser is used
+const MIME_TYPES: [&str; 3] = [
+    "video/webm;codecs=vp9,opus",
+    "video/webm;codecs=vp8,opus",
+    "video/webm",
+];
+
+#[derive(Debug)]
+pub enum RecorderError {
+    /// The canvas isn't shown on the page, its content can't be captured
+    NoStream,
+    /// The browser can't record `WebM` videos
+    Unsupported,
+}
+
+/// A single `MediaRecorder`, its data is a complete file once requested
+#[derive(Clone)]
+struct Segment {
+    recorder: MediaRecorder,
+    chunks: Rc<RefCell<Vec<Blob>>>,
+    /// Notified by the next data event
+    waiting: Rc<RefCell<Vec<oneshot::Sender<()>>>>,
+}
+
+impl Segment {
+    fn start(stream: &MediaStream, mime_type: &str) -> Self {
+        let options = MediaRecorderOptions::new();
+        options.set_mime_type(mime_type);
+
+        let recorder =
+            MediaRecorder::new_with_media_stream_and_media_recorder_options(stream, &options)
+                .unwrap();
+
+        let chunks = Rc::new(RefCell::new(Vec::new()));
+        let waiting = Rc::new(RefCell::new(Vec::<oneshot::Sender<()>>::new()));
+
+        let chunks_clone = chunks.clone();
+        let waiting_clone = waiting.clone();
+
+        recorder.set_ondataavailable(Some(
+            Closure::<dyn Fn(BlobEvent)>::new(move |event: BlobEvent| {
+                if let Some(data) = event.data() {
+                    chunks_clone.borrow_mut().push(data);
+                }
+
+                for sender in waiting_clone.borrow_mut().drain(..) {
+                    sender.send(()).ok();
+                }
+            })
+            .into_js_value()
+            .unchecked_ref(),
+        ));
+
+        recorder.start().unwrap();
+
+        Self {
+            recorder,
+            chunks,
+            waiting,
+        }
+    }
+
+    /// Get everything recorded so far in a file, the recording continues unless stopped
+    async fn collect(&self, stop: bool, mime_type: &str) -> Blob {
+        let (send, recv) = oneshot::channel();
+        self.waiting.borrow_mut().push(send);
+
+        if stop {
+            self.recorder.stop().unwrap();
+        } else {
+            self.recorder.request_data().unwrap();
+        }
+
+        recv.await.unwrap();
+
+        let parts = self.chunks.borrow().iter().collect::<Array>();
+
+        let options = BlobPropertyBag::new();
+        options.set_type(mime_type);
+
+        Blob::new_with_blob_sequence_and_options(&parts, &options).unwrap()
+    }
+
+    /// The last data event still fires, a pending `collect` gets its file
+    fn discard(&self) {
+        if self.recorder.state() != RecordingState::Inactive {
+            self.recorder.stop().unwrap();
+        }
+    }
+}
+
+/// Records the content of a canvas, and optionally the sounds played with `audio`
+pub struct ScreenRecorder {
+    stream: MediaStream,
+    mime_type: &'static str,
+    /// Oldest first, there are two of them at most when rolling
+    segments: Rc<RefCell<VecDeque<Segment>>>,
+    /// Timer starting the rolling recordings
+    interval: Option<i32>,
+}
+
+impl ScreenRecorder {
+    /// Prepare to record the canvas at the given frames per second, nothing is recorded before `start`
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if the canvas can't be captured, like when it was created offscreen, or if the browser can't record `WebM`
+    pub fn new(
+        canvas: &Canvas2d,
+        frame_rate: f64,
+        record_audio: bool,
+    ) -> Result<Self, RecorderError> {
+        let mime_type = MIME_TYPES
+            .into_iter()
+            .find(|mime_type| MediaRecorder::is_type_supported(mime_type))
+            .ok_or(RecorderError::Unsupported)?;
+
+        let stream = canvas
+            .capture_stream(frame_rate)
+            .ok_or(RecorderError::NoStream)?;
+
+        if record_audio {
+            for track in audio::capture_stream().get_audio_tracks() {
+                stream.add_track(track.unchecked_ref());
+            }
+        }
+
+        Ok(Self {
+            stream,
+            mime_type,
+            segments: Rc::new(RefCell::new(VecDeque::new())),
+            interval: None,
+        })
+    }
+
+    /// MIME type of the recorded files
+    #[must_use]
+    pub const fn mime_type(&self) -> &'static str {
+        self.mime_type
+    }
+
+    #[must_use]
+    pub fn is_recording(&self) -> bool {
+        !self.segments.borrow().is_empty()
+    }
+
+    /// Record everything until `stop`, a recording in progress is discarded
+    pub fn start(&mut self) {
+        self.discard();
+
+        self.segments
+            .borrow_mut()
+            .push_back(Segment::start(&self.stream, self.mime_type));
+    }
+
+    /// Keep recording the last seconds, clips cover at least the given duration and up to twice as much.
+    /// A recording in progress is discarded
+    pub fn start_rolling(&mut self, duration: Duration) {
+        self.start();
+
+        let segments = self.segments.clone();
+        let stream = self.stream.clone();
+        let mime_type = self.mime_type;
+
+        let interval = window()
+            .set_interval_with_callback_and_timeout_and_arguments_0(
+                Closure::<dyn Fn()>::new(move || {
+                    let mut segments = segments.borrow_mut();
+                    segments.push_back(Segment::start(&stream, mime_type));
+
+                    // The next oldest recording already covers the duration
+                    if segments.len() > 2 {
+                        if let Some(segment) = segments.pop_front() {
+                            segment.discard();
+                        }
+                    }
+                })
+                .into_js_value()
+                .unchecked_ref(),
+                duration.as_millis() as i32,
+            )
+            .unwrap();
+
+        self.interval = Some(interval);
+    }
+
+    /// Get the recording so far without stopping it, None if nothing is being recorded
+    pub async fn clip(&self) -> Option<Blob> {
+        let segment = self.segments.borrow().front().cloned()?;
+
+        Some(segment.collect(false, self.mime_type).await)
+    }
+
+    /// Stop recording and get the recording, None if nothing was being recorded
+    pub async fn stop(&mut self) -> Option<Blob> {
+        let segment = self.segments.borrow_mut().pop_front()?;
+        self.discard();
+
+        Some(segment.collect(true, self.mime_type).await)
+    }
+
+    fn discard(&mut self) {
+        if let Some(interval) = self.interval.take() {
+            window().clear_interval_with_handle(interval);
+        }
+
+        for segment in self.segments.borrow_mut().drain(..) {
+            segment.discard();
+        }
+    }
+}
+
+impl Drop for ScreenRecorder {
+    fn drop(&mut self) {
+        self.discard();
+
+        // The audio tracks are shared with the other recorders
+        for track in self.stream.get_video_tracks() {
+            track.unchecked_into::<MediaStreamTrack>().stop();
+        }
+    }
+}
+
+/// Save a recording on the player's device with the given file name
+pub fn download(blob: &Blob, file_name: &str) {
+    let url = Url::create_object_url_with_blob(blob).unwrap();
+
+    let anchor = document()
+        .create_element("a")
+        .unwrap()
+        .dyn_into::<HtmlAnchorElement>()
+        .unwrap();
+
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    // Revoking right away can cancel the download in some browsers
+    set_timeout(
+        Closure::once_into_js(move || {
+            Url::revoke_object_url(&url).unwrap();
+        })
+        .unchecked_ref(),
+        1000,
+    );
+}
@@ -0,0 +1,216 @@
+use crate::dom::document;
+use futures_channel::mpsc;
+use futures_util::StreamExt;
+use js_sys::{Array, Uint8Array};
+use std::cell::Cell;
+use wasm_bindgen::{__rt::IntoJsResult, prelude::Closure, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, HtmlMediaElement, HtmlVideoElement, Url};
+
+/// Value of `HAVE_CURRENT_DATA`, the current frame can be drawn from this ready state
+const HAVE_CURRENT_DATA: u16 = 2;
+
+/// A video decoded by the browser, its frames are drawn with `Canvas2d::update_video_texture`
+pub struct Video {
+    element: HtmlVideoElement,
+    /// Object url of the bytes the video was loaded from, revoked on drop
+    object_url: Option<String>,
+    uploaded_time: Cell<Option<f64>>,
+}
+
+impl Video {
+    /// Load a video from the network at the given src, it is paused on its first frame
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if the video couldn't be loaded or decoded
+    pub async fn load(src: &str) -> Result<Self, ()> {
+        Self::internal_load(src, None).await
+    }
+
+    /// Load a video from the bytes of a file, it is paused on its first frame
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if the video couldn't be decoded
+    pub async fn from_bytes(bytes: &[u8]) -> Result<Self, ()> {
+        let array = Array::new();
+        array.push(&Uint8Array::from(bytes).into_js_result().unwrap());
+
+        let url =
+            Url::create_object_url_with_blob(&Blob::new_with_u8_array_sequence(&array).unwrap())
+                .unwrap();
+
+        Self::internal_load(&url.clone(), Some(url)).await
+    }
+
+    async fn internal_load(src: &str, object_url: Option<String>) -> Result<Self, ()> {
+        let element = document()
+            .create_element("video")
+            .unwrap()
+            .dyn_into::<HtmlVideoElement>()
+            .unwrap();
+
+        // Cross origin frames can't be uploaded to textures without CORS
+        element.set_cross_origin(Some("anonymous"));
+        element.set_preload("auto");
+        element.set_attribute("playsinline", "").unwrap();
+
+        let (mut send, mut recv) = mpsc::channel(0);
+        let mut send_clone = send.clone();
+
+        element.set_onloadeddata(Some(
+            Closure::once(move || {
+                send_clone.try_send(true).unwrap();
+            })
+            .into_js_value()
+            .unchecked_ref(),
+        ));
+
+        element.set_onerror(Some(
+            Closure::once(move || {
+                send.try_send(false).unwrap();
+            })
+            .into_js_value()
+            .unchecked_ref(),
+        ));
+
+        element.set_src(src);
+
+        let loaded = recv.next().await.unwrap_or(false);
+
+        element.set_onloadeddata(None);
+        element.set_onerror(None);
+
+        let video = Self {
+            element,
+            object_url,
+            uploaded_time: Cell::new(None),
+        };
+
+        if loaded {
+            Ok(video)
+        } else {
+            Err(())
+        }
+    }
+
+    /// Start or resume playing
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if the browser refused to play, videos with sound usually need a user interaction first
+    pub async fn play(&self) -> Result<(), ()> {
+        let promise = self.element.play().map_err(|_| ())?;
+
+        JsFuture::from(promise).await.map(|_| ()).map_err(|_| ())
+    }
+
+    /// Pause on the current frame
+    pub fn pause(&self) {
+        self.element.pause().unwrap();
+    }
+
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.element.paused()
+    }
+
+    /// Check if the last frame was reached, a looping video never ends
+    #[must_use]
+    pub fn is_ended(&self) -> bool {
+        self.element.ended()
+    }
+
+    /// Jump to the given time in seconds, the frame is updated once the browser decoded it
+    pub fn seek(&self, time: f64) {
+        self.element.set_current_time(time);
+    }
+
+    /// Current playback time in seconds
+    #[must_use]
+    pub fn time(&self) -> f64 {
+        self.element.current_time()
+    }
+
+    /// Length of the video in seconds
+    #[must_use]
+    pub fn duration(&self) -> f64 {
+        self.element.duration()
+    }
+
+    /// Restart from the beginning when the end is reached
+    pub fn set_looping(&self, looping: bool) {
+        self.element.set_loop(looping);
+    }
+
+    #[must_use]
+    pub fn is_looping(&self) -> bool {
+        self.element.loop_()
+    }
+
+    /// Set the sound volume, from 0 to 1
+    pub fn set_volume(&self, volume: f32) {
+        self.element.set_volume(f64::from(volume.clamp(0., 1.)));
+    }
+
+    #[must_use]
+    pub fn volume(&self) -> f32 {
+        self.element.volume() as f32
+    }
+
+    /// Muted videos can start playing without a user interaction
+    pub fn set_muted(&self, muted: bool) {
+        self.element.set_muted(muted);
+    }
+
+    #[must_use]
+    pub fn is_muted(&self) -> bool {
+        self.element.muted()
+    }
+
+    /// Set the playback speed, 1 is the normal speed
+    pub fn set_speed(&self, speed: f64) {
+        self.element.set_playback_rate(speed);
+    }
+
+    /// Size of the video frames in pixels
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        self.element.video_width()
+    }
+
+    /// Size of the video frames in pixels
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        self.element.video_height()
+    }
+
+    pub(crate) const fn element(&self) -> &HtmlVideoElement {
+        &self.element
+    }
+
+    /// Check if a frame different from the last uploaded one is available, and mark it as uploaded
+    pub(crate) fn take_new_frame(&self) -> bool {
+        let media: &HtmlMediaElement = &self.element;
+
+        if media.ready_state() < HAVE_CURRENT_DATA {
+            return false;
+        }
+
+        let time = media.current_time();
+
+        self.uploaded_time.replace(Some(time)) != Some(time)
+    }
+}
+
+impl Drop for Video {
+    fn drop(&mut self) {
+        self.element.pause().unwrap();
+        self.element.remove_attribute("src").unwrap();
+
+        if let Some(url) = &self.object_url {
+            Url::revoke_object_url(url).unwrap();
+        }
+    }
+}
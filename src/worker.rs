@@ -0,0 +1,273 @@
+//! Run the game loop in a dedicated web worker, keeping the page responsive under heavy simulation.
+//!
+//! The page creates a `WorkerHost` with the canvas, it forwards input events and window resizes to the worker
+//! and plays its sounds since the audio API isn't available in workers.
+//! The worker calls `receive_canvas` and draws with `Canvas2d::from_offscreen`, `draw_scheduler`,
+//! `tick_scheduler` and `input` then work as on the main thread.
+
+use crate::{
+    audio,
+    dom::{window, worker_scope},
+    input::{self, InputEvent},
+};
+use futures_channel::oneshot;
+use glam::UVec2;
+use js_sys::{Array, Uint8Array};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{
+    Event, HtmlCanvasElement, MessageEvent, OffscreenCanvas, Worker, WorkerOptions, WorkerType,
+};
+
+#[derive(Serialize, Deserialize)]
+enum ToWorker {
+    /// Sent with the canvas
+    Canvas {
+        screen_size: UVec2,
+    },
+    Input(InputEvent),
+    Resize(UVec2),
+}
+
+#[derive(Serialize, Deserialize)]
+enum ToHost {
+    /// The worker listens to messages and waits for the canvas
+    Ready,
+    /// Sent with the bytes of the sound file
+    LoadSound(u32),
+    PlaySound {
+        id: u32,
+        volume: f32,
+    },
+}
+
+/// Messages are a JSON string followed by an optional transferred object
+fn encode(message: &impl Serialize, payload: Option<&JsValue>) -> Array {
+    let array = Array::new();
+    array.push(&serde_json::to_string(message).unwrap().into());
+
+    if let Some(payload) = payload {
+        array.push(payload);
+    }
+
+    array
+}
+
+fn decode<T: DeserializeOwned>(event: &MessageEvent) -> Option<(T, JsValue)> {
+    let array = event.data().dyn_into::<Array>().ok()?;
+    let message = serde_json::from_str(&array.get(0).as_string()?).ok()?;
+
+    Some((message, array.get(1)))
+}
+
+fn window_size() -> UVec2 {
+    let window = window();
+
+    UVec2::new(
+        window.inner_width().unwrap().as_f64().unwrap() as u32,
+        window.inner_height().unwrap().as_f64().unwrap() as u32,
+    )
+}
+
+/// The worker couldn't be started
+#[derive(Debug)]
+pub struct WorkerError;
+
+/// The page side of a game running in a worker
+pub struct WorkerHost {
+    worker: Worker,
+}
+
+impl WorkerHost {
+    /// Start the worker from an ES module script loading the game, the canvas is transferred once the worker calls `receive_canvas`.
+    /// Input events and window resizes are then forwarded to it
+    ///
+    /// # Errors
+    ///
+    /// Returns Err if the worker can't be started or if the canvas was already transferred
+    pub fn new(script_url: &str, canvas: &HtmlCanvasElement) -> Result<Self, WorkerError> {
+        let canvas = canvas
+            .transfer_control_to_offscreen()
+            .map_err(|_| WorkerError)?;
+
+        let options = WorkerOptions::new();
+        options.set_type(WorkerType::Module);
+
+        let worker = Worker::new_with_options(script_url, &options).map_err(|_| WorkerError)?;
+
+        let mut canvas = Some(canvas);
+        let sounds = Rc::new(RefCell::new(HashMap::new()));
+
+        let worker_clone = worker.clone();
+
+        worker.set_onmessage(Some(
+            Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                let Some((message, payload)) = decode(&event) else {
+                    return;
+                };
+
+                match message {
+                    ToHost::Ready => {
+                        if let Some(canvas) = canvas.take() {
+                            Self::start(&worker_clone, &canvas);
+                        }
+                    }
+                    ToHost::LoadSound(id) => {
+                        let bytes = payload.unchecked_into::<Uint8Array>().to_vec();
+                        let sounds = sounds.clone();
+
+                        spawn_local(async move {
+                            let buffer = audio::from_bytes(&bytes).await;
+                            sounds.borrow_mut().insert(id, buffer);
+                        });
+                    }
+                    ToHost::PlaySound { id, volume } => {
+                        if let Some(buffer) = sounds.borrow().get(&id) {
+                            audio::play(buffer, volume);
+                        }
+                    }
+                }
+            })
+            .into_js_value()
+            .unchecked_ref(),
+        ));
+
+        Ok(Self { worker })
+    }
+
+    /// Give the canvas to the worker and start forwarding events
+    fn start(worker: &Worker, canvas: &OffscreenCanvas) {
+        worker
+            .post_message_with_transfer(
+                &encode(
+                    &ToWorker::Canvas {
+                        screen_size: window_size(),
+                    },
+                    Some(canvas),
+                ),
+                &Array::of1(canvas),
+            )
+            .unwrap();
+
+        let worker_clone = worker.clone();
+
+        input::listen(move |event| {
+            worker_clone
+                .post_message(&encode(&ToWorker::Input(event), None))
+                .unwrap();
+        });
+
+        let worker_clone = worker.clone();
+
+        window()
+            .add_event_listener_with_callback(
+                "resize",
+                Closure::wrap(Box::new(move |_: Event| {
+                    worker_clone
+                        .post_message(&encode(&ToWorker::Resize(window_size()), None))
+                        .unwrap();
+                }) as Box<dyn Fn(Event)>)
+                .into_js_value()
+                .unchecked_ref(),
+            )
+            .unwrap();
+    }
+
+    /// Stop the worker immediately, the canvas stops being updated
+    pub fn terminate(&self) {
+        self.worker.terminate();
+    }
+}
+
+thread_local! {
+    static SCREEN_SIZE: Cell<UVec2> = const { Cell::new(UVec2::ZERO) };
+    static NEXT_SOUND: Cell<u32> = const { Cell::new(0) };
+}
+
+fn post(message: &ToHost, payload: Option<&JsValue>, transfer: &Array) {
+    worker_scope()
+        .post_message_with_transfer(&encode(message, payload), transfer)
+        .unwrap();
+}
+
+/// Wait for the canvas of the page, this should be called once when the worker starts.
+/// Input events are received from then on
+///
+/// # Panics
+///
+/// Panics if this isn't running in a dedicated worker
+pub async fn receive_canvas() -> OffscreenCanvas {
+    let (send, recv) = oneshot::channel();
+    let mut send = Some(send);
+
+    worker_scope().set_onmessage(Some(
+        Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            let Some((message, payload)) = decode(&event) else {
+                return;
+            };
+
+            match message {
+                ToWorker::Canvas { screen_size } => {
+                    SCREEN_SIZE.with(|size| size.set(screen_size));
+
+                    if let Some(send) = send.take() {
+                        send.send(payload.unchecked_into::<OffscreenCanvas>())
+                            .unwrap();
+                    }
+                }
+                ToWorker::Input(event) => input::push_event(event),
+                ToWorker::Resize(screen_size) => SCREEN_SIZE.with(|size| size.set(screen_size)),
+            }
+        })
+        .into_js_value()
+        .unchecked_ref(),
+    ));
+
+    post(&ToHost::Ready, None, &Array::new());
+
+    recv.await.unwrap()
+}
+
+/// Size of the page window, as last received from the page
+#[must_use]
+pub fn screen_size() -> UVec2 {
+    SCREEN_SIZE.with(Cell::get)
+}
+
+/// A sound decoded and played by the page
+pub struct Sound {
+    id: u32,
+}
+
+/// Send the bytes of a sound file to the page, it is silent until the page finished decoding it
+#[must_use]
+pub fn load_sound(bytes: &[u8]) -> Sound {
+    let id = NEXT_SOUND.with(|next| next.replace(next.get() + 1));
+    let bytes = Uint8Array::from(bytes);
+
+    post(
+        &ToHost::LoadSound(id),
+        Some(&bytes),
+        &Array::of1(&bytes.buffer()),
+    );
+
+    Sound { id }
+}
+
+/// Play a sound loaded with `load_sound`
+pub fn play_sound(sound: &Sound, volume: f32) {
+    post(
+        &ToHost::PlaySound {
+            id: sound.id,
+            volume,
+        },
+        None,
+        &Array::new(),
+    );
+}